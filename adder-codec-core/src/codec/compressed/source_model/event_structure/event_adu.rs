@@ -131,6 +131,13 @@ impl EventAdu {
             *byte = decoder.decode(stream).unwrap().unwrap() as u8;
         }
 
+        // Take the start time from the Adu itself, rather than counting the Adus decoded before
+        // it, so that decoding can begin at any Adu
+        self.start_t = AbsoluteT::from_be_bytes(start_t);
+        for cube in self.event_cubes.iter_mut() {
+            cube.start_t = self.start_t;
+        }
+
         for block_idx_y in 0..self.event_cubes.nrows() {
             for block_idx_x in 0..self.event_cubes.ncols() {
                 self.event_cubes[[block_idx_y, block_idx_x]].decompress_intra(
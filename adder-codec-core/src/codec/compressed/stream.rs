@@ -188,7 +188,6 @@ impl<R: Read + Seek> ReadCompression<R> for CompressedInput<R> {
 
         if let Some(adu) = &mut self.adu {
             if adu.decoder_is_empty() {
-                let start = std::time::Instant::now();
                 // Read the size of the Adu in bytes
                 let mut buffer = [0u8; 4];
                 reader.read_bytes(&mut buffer)?;
@@ -202,9 +201,6 @@ impl<R: Read + Seek> ReadCompression<R> for CompressedInput<R> {
 
                 // Decompress the Adu
                 adu.decompress(&mut adu_stream);
-
-                let duration = start.elapsed();
-                println!("Decompressed Adu in {:?} ns", duration.as_nanos());
             }
             // Then return the next event from the queue
             match adu.digest_event() {
@@ -226,14 +222,13 @@ impl<R: Read + Seek> ReadCompression<R> for CompressedInput<R> {
         reader: &mut BitReader<R, BigEndian>,
         pos: u64,
     ) -> Result<(), CodecError> {
-        if pos.saturating_sub(self.meta.header_size as u64) % u64::from(self.meta.event_size) != 0 {
-            eprintln!("Attempted to seek to bad position in stream: {pos}");
-            return Err(CodecError::Seek);
-        }
-
         if reader.seek_bits(SeekFrom::Start(pos * 8)).is_err() {
             return Err(CodecError::Seek);
         }
+
+        // `pos` must be the start of an Adu. Each Adu carries its own start time, so decoding
+        // can resume there with a fresh Adu.
+        self.adu = None;
         Ok(())
     }
 }
@@ -19,7 +19,7 @@ use crate::SourceType::U8;
 use bincode::config::{FixintEncoding, WithOtherEndian, WithOtherIntEncoding};
 use bincode::{DefaultOptions, Options};
 use bitstream_io::{BigEndian, BitRead, BitReader};
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom};
 
 /// Struct for decoding [`Event`]s from a stream
 pub struct Decoder<R: Read + Seek> {
@@ -224,6 +224,20 @@ impl<R: Read + Seek> Decoder<R> {
     //     self.input.digest_event_debug(reader)
     // }
 
+    /// Whether an error returned by [`Decoder::digest_event`] marks the end of the stream, rather
+    /// than a failure. A raw stream ends with its EOF event, and a compressed stream ends when no
+    /// further ADU can be read.
+    pub fn is_end_of_stream(&self, error: &CodecError) -> bool {
+        match error {
+            CodecError::Eof => true,
+            CodecError::IoError(e) => {
+                self.get_compression_type() == EncoderType::Compressed
+                    && e.kind() == io::ErrorKind::UnexpectedEof
+            }
+            _ => false,
+        }
+    }
+
     /// Sets the input stream position to the given absolute byte position
     pub fn set_input_stream_position(
         &mut self,
@@ -4,6 +4,7 @@ use crate::codec::header::Magic;
 use crate::{DeltaT, Event, PlaneSize, SourceCamera, TimeMode};
 use bitstream_io::{BigEndian, BitReader};
 use enum_dispatch::enum_dispatch;
+use serde::{Deserialize, Serialize};
 use std::io;
 use std::io::{Read, Seek, Sink, Write};
 
@@ -22,7 +23,7 @@ pub enum WriteCompressionEnum<W: Write> {
 }
 
 /// The encoder type, along with any associated options
-#[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum EncoderType {
     /// Perform (possibly lossy) compression on the ADΔER stream, and arithmetic coding
     Compressed,
@@ -302,7 +303,7 @@ pub enum EventDrop {
 }
 
 /// Reorder the events according to their firing times
-#[derive(Default, Copy, Clone, PartialEq, Debug)]
+#[derive(Default, Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum EventOrder {
     /// Pass on the events in the order they're received in
     #[default]
@@ -82,7 +82,7 @@ pub enum TimeMode {
 }
 
 /// The size of the image plane in pixels
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PlaneSize {
     width: u16,
     height: u16,
@@ -203,7 +203,7 @@ pub enum Mode {
     Continuous,
 }
 
-#[derive(Clone, Copy, PartialEq, Default, Debug)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
 pub enum PixelMultiMode {
     Normal,
 
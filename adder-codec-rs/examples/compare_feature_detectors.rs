@@ -0,0 +1,88 @@
+use adder_codec_core::{open_file_decoder, BigT, Coord};
+use adder_codec_rs::utils::cv::feature_set_precision_recall_accuracy;
+use adder_codec_rs::vision::detector::{
+    EventCorner, EventCornerMode, Fast, FeatureDetector, Harris,
+};
+use adder_codec_rs::vision::processor::VisionProcessor;
+use std::collections::HashSet;
+use std::error::Error;
+
+/// Run each corner detector on an ADΔER file, and compare its features to those of the FAST
+/// detector every 1/30th of a second of stream time. The stream should use absolute timestamps.
+///
+/// Usage: `cargo run --example compare_feature_detectors -- <input.adder>`
+fn main() -> Result<(), Box<dyn Error>> {
+    let Some(input_path) = std::env::args().nth(1) else {
+        eprintln!("Usage: compare_feature_detectors <input.adder>");
+        return Ok(());
+    };
+
+    let (mut reader, mut bitreader) = open_file_decoder(&input_path)?;
+    let meta = *reader.meta();
+
+    let detectors: Vec<Box<dyn FeatureDetector>> = vec![
+        Box::new(Fast::default()),
+        Box::new(Harris::harris()),
+        Box::new(Harris::shi_tomasi()),
+        Box::new(EventCorner::new(EventCornerMode::EFast)),
+        Box::new(EventCorner::new(EventCornerMode::ArcStar)),
+    ];
+    let mut processor = VisionProcessor::with_detectors(&meta, reader.get_source_type(), detectors);
+    let names = processor.detector_names();
+
+    let sample_interval = BigT::from(meta.tps / 30).max(1);
+    let mut next_sample = sample_interval;
+    let mut sums = vec![(0.0, 0.0, 0.0); names.len()];
+    let mut samples = 0;
+
+    let feature_set = |processor: &VisionProcessor, detector: usize| -> HashSet<Coord> {
+        processor
+            .features(detector)
+            .map(|features| {
+                features
+                    .keys()
+                    .map(|coord| Coord::new(coord.x, coord.y, None))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    while let Ok(event) = reader.digest_event(&mut bitreader) {
+        processor.ingest_event(&event);
+        while processor.pop_feature_event().is_some() {}
+
+        if BigT::from(event.t) >= next_sample {
+            next_sample += sample_interval;
+            let reference = feature_set(&processor, 0);
+            if reference.is_empty() {
+                continue;
+            }
+            for (detector, sum) in sums.iter_mut().enumerate() {
+                let (precision, recall, accuracy) = feature_set_precision_recall_accuracy(
+                    &reference,
+                    &feature_set(&processor, detector),
+                    meta.plane,
+                );
+                // A detector with no features has an undefined precision
+                sum.0 += if precision.is_nan() { 0.0 } else { precision };
+                sum.1 += recall;
+                sum.2 += accuracy;
+            }
+            samples += 1;
+        }
+    }
+
+    println!("Compared against FAST over {samples} samples");
+    println!("detector\tprecision\trecall\taccuracy");
+    for (name, (precision, recall, accuracy)) in names.iter().zip(sums) {
+        let samples = f64::from(samples.max(1));
+        println!(
+            "{name}\t{:.4}\t{:.4}\t{:.4}",
+            precision / samples,
+            recall / samples,
+            accuracy / samples
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use adder_codec_core::open_file_decoder;
+use adder_codec_rs::framer::driver::FramerBuilder;
+use adder_codec_rs::framer::tensor::{TensorFramer, TensorMode};
+use adder_codec_rs::framer::writers::write_npy;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Build 5-bin voxel grids at 30 tensors per second from an ADΔER file, and write each one as a
+/// `.npy` file in the output directory.
+///
+/// Usage: `cargo run --example events_to_tensors -- <input.adder> <output_dir>`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(input_path), Some(output_dir)) = (args.next(), args.next()) else {
+        eprintln!("Usage: events_to_tensors <input.adder> <output_dir>");
+        return Ok(());
+    };
+    std::fs::create_dir_all(&output_dir)?;
+
+    let (mut reader, mut bitreader) = open_file_decoder(&input_path)?;
+    let meta = *reader.meta();
+
+    let mut framer = FramerBuilder::new(meta.plane, 64)
+        .codec_version(meta.codec_version, meta.time_mode)
+        .time_parameters(meta.tps, meta.ref_interval, meta.delta_t_max, Some(30.0))
+        .source(reader.get_source_type(), meta.source_camera)
+        .finish_tensors(TensorMode::VoxelGrid { bins: 5 })?;
+
+    let mut tensor_count = 0;
+    let mut write_ready = |framer: &mut TensorFramer| {
+        while let Some(tensor) = framer.pop_tensor() {
+            let path = Path::new(&output_dir).join(format!("{tensor_count:06}.npy"));
+            let mut writer = BufWriter::new(File::create(path)?);
+            write_npy(&mut writer, &tensor)?;
+            tensor_count += 1;
+        }
+        Ok::<(), std::io::Error>(())
+    };
+
+    while let Ok(event) = reader.digest_event(&mut bitreader) {
+        if framer.ingest_event(&event) {
+            write_ready(&mut framer)?;
+        }
+    }
+    framer.flush();
+    write_ready(&mut framer)?;
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use adder_codec_core::{open_file_decoder, BigT, Coord};
+use adder_codec_rs::vision::processor::VisionProcessor;
+use adder_codec_rs::vision::tracker::{write_tracks_csv, write_tracks_json, FeatureTracker};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Detect FAST features in an ADΔER file, track them at 30 intervals per second, and write the
+/// tracks as CSV or JSON, depending on the output file's extension. The stream should use absolute
+/// timestamps.
+///
+/// Usage: `cargo run --example track_features -- <input.adder> <output.csv|output.json>`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let (Some(input_path), Some(output_path)) = (args.next(), args.next()) else {
+        eprintln!("Usage: track_features <input.adder> <output.csv|output.json>");
+        return Ok(());
+    };
+
+    let (mut reader, mut bitreader) = open_file_decoder(&input_path)?;
+    let meta = *reader.meta();
+
+    let mut processor = VisionProcessor::new(&meta, reader.get_source_type());
+    let mut tracker = FeatureTracker::new(meta.tps);
+    let interval = BigT::from(meta.tps / 30).max(1);
+    let mut interval_end = interval;
+
+    let current_features = |processor: &VisionProcessor| -> Vec<Coord> {
+        processor
+            .features(0)
+            .map(|features| features.keys().copied().collect())
+            .unwrap_or_default()
+    };
+
+    while let Ok(event) = reader.digest_event(&mut bitreader) {
+        while BigT::from(event.t) > interval_end {
+            tracker.update(interval_end, &current_features(&processor));
+            interval_end += interval;
+        }
+        processor.ingest_event(&event);
+        while processor.pop_feature_event().is_some() {}
+    }
+    tracker.update(interval_end, &current_features(&processor));
+
+    let tracks = tracker.finish();
+    println!("Found {} tracks", tracks.len());
+
+    let mut writer = BufWriter::new(File::create(&output_path)?);
+    match Path::new(&output_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("json") => write_tracks_json(&mut writer, &tracks)?,
+        _ => write_tracks_csv(&mut writer, &tracks, meta.tps)?,
+    }
+
+    Ok(())
+}
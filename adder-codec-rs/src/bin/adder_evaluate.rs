@@ -0,0 +1,368 @@
+/*
+Transcode a framed video to ADΔER, reconstruct it, and measure the quality of the reconstruction
+against the source. Run once per CRF level to build a rate-distortion curve.
+
+Example usage:
+cargo run --bin adder_evaluate --release -- --input-filename "./in.mp4" --scale 0.5 --crf 3 --frame-count-max 300 --output-filename "./crf3.csv"
+ */
+use adder_codec_core::codec::rate_controller::Crf;
+use adder_codec_core::codec::{EncoderOptions, EncoderType};
+use adder_codec_core::SourceCamera::FramedU8;
+use adder_codec_core::{open_file_decoder, PixelMultiMode, TimeMode};
+use adder_codec_rs::framer::driver::FramerMode::INSTANTANEOUS;
+use adder_codec_rs::framer::driver::{FrameSequence, Framer, FramerBuilder};
+use adder_codec_rs::framer::writers::FrameWriter;
+use adder_codec_rs::transcoder::source::framed::Framed;
+use adder_codec_rs::transcoder::source::video::{FramedViewMode, Source, VideoBuilder};
+use adder_codec_rs::utils::cv::{calculate_quality_metrics, handle_color, QualityMetrics};
+use clap::Parser;
+use ndarray::Array3;
+use rayon::current_num_threads;
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use video_rs_adder_dep::{Locator, Options, Resize};
+
+/// Command line argument parser
+#[derive(Parser, Debug, Default, serde::Deserialize)]
+#[clap(author, version, about, long_about = None)]
+pub struct EvaluateArgs {
+    /// Use color?
+    #[clap(long, action)]
+    pub color_input: bool,
+
+    /// Number of ticks per input frame
+    #[clap(short, long, default_value_t = 255)]
+    pub ref_time: u32,
+
+    /// Max number of ticks for any event
+    #[clap(short, long, default_value_t = 15300)]
+    pub delta_t_max: u32,
+
+    /// Max number of input frames to transcode (0 = no limit)
+    #[clap(short, long, default_value_t = 0)]
+    pub frame_count_max: u32,
+
+    /// Index of first input frame to transcode
+    #[clap(long, default_value_t = 0)]
+    pub frame_idx_start: u32,
+
+    /// Path to input file
+    #[clap(short, long, default_value = "./in.mp4")]
+    pub input_filename: String,
+
+    /// Resize scale
+    #[clap(short('z'), long, default_value_t = 1.0)]
+    pub scale: f64,
+
+    /// Constant rate factor (quality level) of the transcoder
+    #[clap(long, default_value_t = 3)]
+    pub crf: u8,
+
+    /// Use the compressed representation, instead of the raw one
+    #[clap(long, action)]
+    pub compressed: bool,
+
+    /// Path to keep the ADΔER file at. If not provided, a temporary file is used and removed.
+    #[clap(long, default_value = "")]
+    pub events_filename: String,
+
+    /// Path to the output metrics file. A `.csv` extension writes CSV, and anything else writes
+    /// JSON. If not provided, JSON is printed to stdout.
+    #[clap(short, long, default_value = "")]
+    pub output_filename: String,
+
+    /// Number of threads to use. If not provided, will default to the number of cores on the
+    /// system.
+    #[clap(long, default_value_t = 0)]
+    pub thread_count: u8,
+}
+
+/// The quality of one reconstructed frame
+#[derive(Debug, Serialize)]
+struct FrameQuality {
+    frame: usize,
+
+    #[serde(flatten)]
+    metrics: QualityMetrics,
+}
+
+/// The results of an evaluation
+#[derive(Debug, Serialize)]
+struct EvaluationReport {
+    input_filename: String,
+    crf: u8,
+    compressed: bool,
+    source_fps: f32,
+    frame_count: usize,
+    encoded_bytes: u64,
+
+    /// Bits per second of source video
+    bitrate: f64,
+
+    /// The mean of each metric over all frames
+    mean: QualityMetrics,
+    frames: Vec<FrameQuality>,
+}
+
+/// A temporary file, removed when dropped
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Compares each reconstructed frame to the next frame of the source video
+struct QualityEvaluator {
+    source: video_rs_adder_dep::Decoder,
+    color_input: bool,
+    frame_limit: usize,
+    frames: Vec<FrameQuality>,
+}
+
+impl FrameWriter<u8> for QualityEvaluator {
+    fn write_frame(&mut self, frame: &Array3<u8>) -> io::Result<()> {
+        // The framer may fill a few frames past the end of the transcoded ones
+        if self.frames.len() >= self.frame_limit {
+            return Ok(());
+        }
+
+        let to_io_error = |e: &dyn Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+        let (_, input) = self.source.decode().map_err(|e| to_io_error(&e))?;
+        let input = handle_color(input, self.color_input).map_err(|e| to_io_error(&e))?;
+        let metrics = calculate_quality_metrics(
+            &input,
+            frame,
+            QualityMetrics {
+                psnr: Some(0.0),
+                mse: Some(0.0),
+                ssim: Some(0.0),
+                ms_ssim: Some(0.0),
+            },
+        )
+        .map_err(|e| to_io_error(e.as_ref()))?;
+
+        self.frames.push(FrameQuality {
+            frame: self.frames.len(),
+            metrics,
+        });
+        eprint!("\rEvaluated frame {}", self.frames.len());
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: EvaluateArgs = EvaluateArgs::parse();
+
+    let num_threads = match args.thread_count {
+        0 => current_num_threads(),
+        num => num as usize,
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()?;
+
+    let events_path = if args.events_filename.is_empty() {
+        std::env::temp_dir().join(format!("adder_evaluate_{}.adder", std::process::id()))
+    } else {
+        PathBuf::from(&args.events_filename)
+    };
+
+    // An events file of our own is removed however we return
+    let _events_file_guard = args
+        .events_filename
+        .is_empty()
+        .then(|| TempFile(events_path.clone()));
+
+    // Transcode the source
+    let mut source: Framed<BufWriter<File>> =
+        Framed::new(args.input_filename.clone(), args.color_input, args.scale)?
+            .frame_start(args.frame_idx_start)?
+            .crf(args.crf)
+            .auto_time_parameters(args.ref_time, args.delta_t_max, Some(TimeMode::AbsoluteT))?;
+    let plane = source.get_video_ref().state.plane;
+    let mut options = EncoderOptions::default(plane);
+    options.crf = Crf::new(Some(args.crf), plane);
+    source = *source.write_out(
+        FramedU8,
+        TimeMode::AbsoluteT,
+        if args.compressed {
+            PixelMultiMode::Collapse
+        } else {
+            PixelMultiMode::Normal
+        },
+        Some((args.delta_t_max / args.ref_time) as usize),
+        if args.compressed {
+            EncoderType::Compressed
+        } else {
+            EncoderType::Raw
+        },
+        options,
+        BufWriter::new(File::create(&events_path)?),
+    )?;
+    let source_fps = source.source_fps;
+
+    let mut frame_count = 0;
+    while args.frame_count_max == 0 || frame_count < args.frame_count_max as usize {
+        match source.consume(1, &pool) {
+            Ok(_) => {}
+            Err(e) if e.is_end_of_input() => break,
+            Err(e) => return Err(e.into()),
+        }
+        frame_count += 1;
+        eprint!("\rTranscoded frame {frame_count}");
+    }
+    eprintln!();
+    if let Some(mut writer) = source.get_video_mut().end_write_stream()? {
+        writer.flush()?;
+    }
+    drop(source);
+    let encoded_bytes = std::fs::metadata(&events_path)?.len();
+
+    // Open the source again, resized the same way, to compare against
+    let locator = Locator::Path(PathBuf::from(&args.input_filename));
+    let (width, height) = video_rs_adder_dep::Decoder::new(&locator)?.size();
+    let mut cap = video_rs_adder_dep::Decoder::new_with_options_and_resize(
+        &locator,
+        &Options::default(),
+        Resize::Fit(
+            ((width as f64) * args.scale) as u32,
+            ((height as f64) * args.scale) as u32,
+        ),
+    )?;
+    cap.reader
+        .seek((args.frame_idx_start as f32 / source_fps * 1000.0) as i64)?;
+    let mut evaluator = QualityEvaluator {
+        source: cap,
+        color_input: args.color_input,
+        frame_limit: frame_count,
+        frames: Vec::with_capacity(frame_count),
+    };
+
+    // Reconstruct the ADΔER file
+    let path_str = events_path
+        .to_str()
+        .ok_or("Couldn't get events path string")?;
+    let (mut stream, mut bitreader) = open_file_decoder(path_str)?;
+    let meta = *stream.meta();
+    let mut frame_sequence: FrameSequence<u8> =
+        FramerBuilder::new(meta.plane, meta.plane.h_usize())
+            .codec_version(meta.codec_version, meta.time_mode)
+            .time_parameters(
+                meta.tps,
+                meta.ref_interval,
+                meta.delta_t_max,
+                Some(source_fps),
+            )
+            .mode(INSTANTANEOUS)
+            .view_mode(FramedViewMode::Intensity)
+            .source(stream.get_source_type(), meta.source_camera)
+            .finish();
+
+    while evaluator.frames.len() < frame_count {
+        match stream.digest_event(&mut bitreader) {
+            Ok(mut event) => {
+                if frame_sequence.ingest_event(&mut event, None) {
+                    frame_sequence.write_filled_frames(&mut evaluator)?;
+                }
+            }
+            Err(e) if stream.is_end_of_stream(&e) => {
+                if !frame_sequence.flush_frame_buffer() {
+                    break;
+                }
+                frame_sequence.write_filled_frames(&mut evaluator)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    eprintln!();
+
+    let seconds = frame_count as f64 / f64::from(source_fps);
+    let report = EvaluationReport {
+        input_filename: args.input_filename.clone(),
+        crf: args.crf,
+        compressed: args.compressed,
+        source_fps,
+        frame_count: evaluator.frames.len(),
+        encoded_bytes,
+        bitrate: if seconds > 0.0 {
+            encoded_bytes as f64 * 8.0 / seconds
+        } else {
+            0.0
+        },
+        mean: mean_metrics(&evaluator.frames),
+        frames: evaluator.frames,
+    };
+
+    if args.output_filename.is_empty() {
+        serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+        println!();
+    } else {
+        let mut writer = BufWriter::new(File::create(&args.output_filename)?);
+        match Path::new(&args.output_filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some("csv") => write_csv(&mut writer, &report)?,
+            _ => serde_json::to_writer_pretty(&mut writer, &report)?,
+        }
+        writer.flush()?;
+    }
+
+    eprintln!(
+        "{} frames, {:.0} bits/s, mean PSNR {:.3} dB, SSIM {:.3}, MS-SSIM {:.3}",
+        report.frame_count,
+        report.bitrate,
+        report.mean.psnr.unwrap_or_default(),
+        report.mean.ssim.unwrap_or_default(),
+        report.mean.ms_ssim.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Average each metric over all frames
+fn mean_metrics(frames: &[FrameQuality]) -> QualityMetrics {
+    let mean = |metric: fn(&QualityMetrics) -> Option<f64>| -> Option<f64> {
+        let values: Vec<f64> = frames.iter().filter_map(|f| metric(&f.metrics)).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    QualityMetrics {
+        psnr: mean(|m| m.psnr),
+        mse: mean(|m| m.mse),
+        ssim: mean(|m| m.ssim),
+        ms_ssim: mean(|m| m.ms_ssim),
+    }
+}
+
+/// Write one row per frame, followed by a row of the means labeled `all`, which also holds the
+/// bitrate. The CRF column lets the files of several runs be concatenated.
+fn write_csv<W: Write>(writer: &mut W, report: &EvaluationReport) -> io::Result<()> {
+    let value = |metric: Option<f64>| metric.map(|v| v.to_string()).unwrap_or_default();
+    writeln!(writer, "crf,frame,mse,psnr,ssim,ms_ssim,bitrate")?;
+    for frame in &report.frames {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},",
+            report.crf,
+            frame.frame,
+            value(frame.metrics.mse),
+            value(frame.metrics.psnr),
+            value(frame.metrics.ssim),
+            value(frame.metrics.ms_ssim)
+        )?;
+    }
+    writeln!(
+        writer,
+        "{},all,{},{},{},{},{}",
+        report.crf,
+        value(report.mean.mse),
+        value(report.mean.psnr),
+        value(report.mean.ssim),
+        value(report.mean.ms_ssim),
+        report.bitrate
+    )
+}
@@ -12,7 +12,7 @@ use std::fs::File;
 use adder_codec_core::codec::{EncoderOptions, EncoderType};
 use adder_codec_core::SourceCamera::FramedU8;
 use adder_codec_core::{PixelMultiMode, TimeMode};
-use adder_codec_rs::transcoder::source::framed::Framed;
+use adder_codec_rs::transcoder::source::framed::{FrameTiming, Framed};
 use std::io::{BufWriter, Cursor};
 use std::path::Path;
 use std::process::Command;
@@ -74,7 +74,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .frame_start(args.frame_idx_start)?
             .crf(args.crf)
             .show_display(args.show_display)
-            .auto_time_parameters(args.ref_time, args.delta_t_max, None)?;
+            .auto_time_parameters(args.ref_time, args.delta_t_max, None)?
+            .frame_timing(if args.vfr {
+                FrameTiming::ContainerPts
+            } else {
+                FrameTiming::Constant
+            })?;
 
     if !args.output_events_filename.is_empty() {
         let path = Path::new(&args.output_events_filename);
@@ -189,6 +194,7 @@ mod tests {
             time_mode: "delta_t".to_string(),
             crf: 0,
             integration_mode: "".to_string(),
+            vfr: false,
         };
         let mut source = Framed::new(args.input_filename, args.color_input, args.scale)?
             // .chunk_rows(64)
@@ -299,6 +299,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             mse: Some(0.0),
                             psnr: Some(0.0),
                             ssim: None,
+                            ms_ssim: None,
                         },
                     );
                     let metrics = metrics.unwrap();
@@ -1,4 +1,7 @@
 use crate::framer::scale_intensity::{FrameValue, SaeTime};
+use crate::framer::tensor::{TensorFramer, TensorMode};
+use crate::framer::timestamped::TimestampFramer;
+use crate::framer::writers::{assemble_frame, FrameWriter};
 use bincode::config::{BigEndian, FixintEncoding, WithOtherEndian, WithOtherIntEncoding};
 use bincode::{DefaultOptions, Options};
 use rayon::iter::ParallelIterator;
@@ -7,6 +10,7 @@ use std::collections::VecDeque;
 use std::error::Error;
 use std::fmt;
 
+use adder_codec_core::codec::CodecMetadata;
 use adder_codec_core::{
     BigT, Coord, DeltaT, Event, PlaneSize, SourceCamera, SourceType, TimeMode, D_EMPTY,
 };
@@ -33,19 +37,19 @@ pub enum FramerMode {
 /// Builder for a Framer.
 #[derive(Clone)]
 pub struct FramerBuilder {
-    plane: PlaneSize,
-    tps: DeltaT,
-    output_fps: Option<f32>,
-    mode: FramerMode,
-    view_mode: FramedViewMode,
-    source: SourceType,
-    codec_version: u8,
-    source_camera: SourceCamera,
-    time_mode: TimeMode,
-    ref_interval: DeltaT,
-    delta_t_max: DeltaT,
-    detect_features: bool,
-    buffer_limit: Option<u32>,
+    pub(crate) plane: PlaneSize,
+    pub(crate) tps: DeltaT,
+    pub(crate) output_fps: Option<f32>,
+    pub(crate) mode: FramerMode,
+    pub(crate) view_mode: FramedViewMode,
+    pub(crate) source: SourceType,
+    pub(crate) codec_version: u8,
+    pub(crate) source_camera: SourceCamera,
+    pub(crate) time_mode: TimeMode,
+    pub(crate) ref_interval: DeltaT,
+    pub(crate) delta_t_max: DeltaT,
+    pub(crate) detect_features: bool,
+    pub(crate) buffer_limit: Option<u32>,
 
     /// The number of rows to process in each chunk (thread).
     pub chunk_rows: usize,
@@ -146,6 +150,104 @@ impl FramerBuilder {
         self.detect_features = detect_features;
         self
     }
+
+    /// Build a [`TensorFramer`], which accumulates events into tensors of the given mode rather
+    /// than into frames. Each tensor spans one output frame interval.
+    ///
+    /// # Errors
+    /// Returns an error if the mode is a voxel grid with no bins.
+    pub fn finish_tensors(self, mode: TensorMode) -> Result<TensorFramer, FrameSequenceError> {
+        if matches!(mode, TensorMode::VoxelGrid { bins: 0 }) {
+            return Err(FrameSequenceError::NoTensorBins);
+        }
+        Ok(TensorFramer::new(self, mode))
+    }
+
+    /// Build a [`TimestampFramer`], which reconstructs the instantaneous frame at each of the
+    /// given timestamps (in ticks), rather than at a constant frame rate.
+    ///
+    /// # Errors
+    /// Returns an error if the timestamps are not strictly increasing.
+    pub fn finish_at_timestamps<T>(
+        self,
+        timestamps: Vec<BigT>,
+    ) -> Result<TimestampFramer<T>, FrameSequenceError>
+    where
+        T: FrameValue<Output = T> + Default + Copy,
+    {
+        if timestamps.windows(2).any(|pair| pair[1] <= pair[0]) {
+            return Err(FrameSequenceError::UnorderedTimestamps);
+        }
+        Ok(TimestampFramer::new(&self, timestamps))
+    }
+}
+
+/// Tracks the running time of each pixel from its events, accounting for the stream's time mode
+/// and source
+#[derive(Debug, Clone, Copy)]
+pub struct PixelClock {
+    codec_version: u8,
+    time_mode: TimeMode,
+    source_camera: SourceCamera,
+    ref_interval: DeltaT,
+}
+
+impl PixelClock {
+    pub(crate) fn new(builder: &FramerBuilder) -> Self {
+        PixelClock {
+            codec_version: builder.codec_version,
+            time_mode: builder.time_mode,
+            source_camera: builder.source_camera,
+            ref_interval: builder.ref_interval,
+        }
+    }
+
+    /// Create a clock for the events of a stream with the given metadata
+    #[must_use]
+    pub fn from_meta(meta: &CodecMetadata) -> Self {
+        PixelClock {
+            codec_version: meta.codec_version,
+            time_mode: meta.time_mode,
+            source_camera: meta.source_camera,
+            ref_interval: meta.ref_interval,
+        }
+    }
+
+    /// Advance a pixel's running time past an event. Returns the number of ticks spanned by the
+    /// event and the time at which it fired, or `None` if the event is older than the pixel's
+    /// running time.
+    pub fn advance(&self, running_t: &mut BigT, event: &Event) -> Option<(BigT, BigT)> {
+        let (delta_t, fired_t) =
+            if self.codec_version >= 2 && self.time_mode == TimeMode::AbsoluteT {
+                let t = BigT::from(event.t);
+                if t <= *running_t {
+                    return None;
+                }
+                (t - *running_t, t)
+            } else {
+                (BigT::from(event.t), *running_t + BigT::from(event.t))
+            };
+
+        // Framed sources fire on frame boundaries, so the running time is rounded up to the next
+        // multiple of `ref_interval`
+        let framed = matches!(
+            self.source_camera,
+            SourceCamera::FramedU8
+                | SourceCamera::FramedU16
+                | SourceCamera::FramedU32
+                | SourceCamera::FramedU64
+                | SourceCamera::FramedF32
+                | SourceCamera::FramedF64
+        );
+        let ref_interval = BigT::from(self.ref_interval);
+        *running_t = if self.codec_version >= 1 && framed && fired_t % ref_interval > 0 {
+            (fired_t / ref_interval + 1) * ref_interval
+        } else {
+            fired_t
+        };
+
+        Some((delta_t, fired_t))
+    }
 }
 
 /// A trait for accumulating ADΔER events into frames.
@@ -194,6 +296,15 @@ pub enum FrameSequenceError {
 
     /// An impossible "fill count" encountered
     BadFillCount,
+
+    /// Query timestamps which are not strictly increasing
+    UnorderedTimestamps,
+
+    /// A voxel grid tensor mode with no temporal bins
+    NoTensorBins,
+
+    /// The stream ends before the requested time
+    StreamEndsBeforeTime,
 }
 
 impl fmt::Display for FrameSequenceError {
@@ -203,6 +314,15 @@ impl fmt::Display for FrameSequenceError {
             FrameSequenceError::UninitializedFrame => write!(f, "Uninitialized frame"),
             FrameSequenceError::UninitializedFrameChunk => write!(f, "Uninitialized frame chunk"),
             FrameSequenceError::BadFillCount => write!(f, "Bad fill count"),
+            FrameSequenceError::UnorderedTimestamps => {
+                write!(f, "Timestamps must be strictly increasing")
+            }
+            FrameSequenceError::NoTensorBins => {
+                write!(f, "A voxel grid must have at least one bin")
+            }
+            FrameSequenceError::StreamEndsBeforeTime => {
+                write!(f, "The stream ends before the requested time")
+            }
         }
     }
 }
@@ -230,18 +350,75 @@ pub struct FrameSequenceState {
     /// Ticks per output frame
     pub tpf: DeltaT,
     pub(crate) source: SourceType,
-    codec_version: u8,
-    source_camera: SourceCamera,
+
+    /// Tracks the running time of each pixel
+    pub(crate) clock: PixelClock,
     ref_interval: DeltaT,
     source_dtm: DeltaT,
     view_mode: FramedViewMode,
-    time_mode: TimeMode,
 }
 
 impl FrameSequenceState {
+    pub(crate) fn new(builder: &FramerBuilder) -> Self {
+        let tpf = if let Some(output_fps) = builder.output_fps {
+            (builder.tps as f32 / output_fps) as u32
+        } else {
+            builder.ref_interval
+        };
+
+        FrameSequenceState {
+            plane: builder.plane,
+            frames_written: 0,
+            view_mode: builder.view_mode,
+            tpf,
+            source: builder.source,
+            clock: PixelClock::new(builder),
+            ref_interval: builder.ref_interval,
+            source_dtm: builder.delta_t_max,
+        }
+    }
+
     pub fn reset(&mut self) {
         self.frames_written = 0;
     }
+
+    /// Get the frame value of an event which spans `delta_t` ticks and fired at `fired_t`, for a
+    /// pixel whose previous event set its running time to `last_fired_t`
+    pub(crate) fn frame_value<T: FrameValue<Output = T>>(
+        &self,
+        event: &Event,
+        delta_t: BigT,
+        fired_t: BigT,
+        last_fired_t: BigT,
+    ) -> T {
+        let practical_d_max =
+            fast_math::log2_raw(T::max_f32() * (self.source_dtm / self.ref_interval) as f32);
+
+        // The SAE view works from the event's own timestamp, rather than the time it spans
+        let t = if self.view_mode == FramedViewMode::SAE {
+            event.t
+        } else {
+            delta_t as DeltaT
+        };
+
+        // TODO: Handle SAE view mode
+        T::get_frame_value(
+            &Event {
+                coord: event.coord,
+                d: event.d,
+                t,
+            },
+            self.source,
+            f64::from(self.ref_interval),
+            practical_d_max,
+            self.source_dtm,
+            self.view_mode,
+            Some(SaeTime {
+                running_t: fired_t as DeltaT,
+                last_fired_t: last_fired_t as DeltaT,
+            }),
+        )
+    }
 }
 
 pub struct FeatureInterval {
@@ -249,6 +426,14 @@ pub struct FeatureInterval {
     pub features: Vec<Coord>,
 }
 
+impl FeatureInterval {
+    /// Get the end of the interval, in ticks
+    #[must_use]
+    pub fn end_ts(&self) -> BigT {
+        self.end_ts
+    }
+}
+
 /// A sequence of frames, each of which is a 3D array of [`FrameValue`]s
 #[allow(dead_code)]
 pub struct FrameSequence<T> {
@@ -265,6 +450,9 @@ pub struct FrameSequence<T> {
     pub(crate) features: VecDeque<FeatureInterval>,
     buffer_limit: Option<u32>,
 
+    /// After [`FrameSequence::start_at`], marks the pixels whose running times are not yet known
+    unstarted: Option<Vec<Array3<bool>>>,
+
     pub(crate) running_intensities: Array3<u8>,
 
     /// Number of rows per chunk (per thread)
@@ -346,26 +534,9 @@ impl<
             }
         }
 
-        let tpf = if let Some(output_fps) = builder.output_fps {
-            (builder.tps as f32 / output_fps) as u32
-        } else {
-            builder.ref_interval
-        };
-
         // Array3::<Option<T>>::new(num_rows, num_cols, num_channels);
         FrameSequence {
-            state: FrameSequenceState {
-                plane: *plane,
-                frames_written: 0,
-                view_mode: builder.view_mode,
-                tpf,
-                source: builder.source,
-                codec_version: builder.codec_version,
-                source_camera: builder.source_camera,
-                ref_interval: builder.ref_interval,
-                source_dtm: builder.delta_t_max,
-                time_mode: builder.time_mode,
-            },
+            state: FrameSequenceState::new(&builder),
             frames,
             frame_idx_offsets: vec![0; num_chunks],
             pixel_ts_tracker,
@@ -383,6 +554,7 @@ impl<
             features: VecDeque::with_capacity(
                 (builder.delta_t_max / builder.ref_interval) as usize,
             ),
+            unstarted: None,
             chunk_rows,
             bincode: DefaultOptions::new()
                 .with_fixint_encoding()
@@ -434,6 +606,19 @@ impl<
         let time = event.t;
         event.coord.y -= (chunk_num * self.chunk_rows) as u16; // Modify the coordinate here, so it gets ingested at the right place
 
+        // After starting mid-stream, a pixel's first event only establishes its running time
+        if let Some(unstarted) = &mut self.unstarted {
+            let idx: [usize; 3] = [event.coord.y.into(), event.coord.x.into(), channel.into()];
+            if unstarted[chunk_num][idx] {
+                unstarted[chunk_num][idx] = false;
+                let _ = self
+                    .state
+                    .clock
+                    .advance(&mut self.pixel_ts_tracker[chunk_num][idx], event);
+                return self.chunk_filled_tracker.iter().all(|filled| *filled);
+            }
+        }
+
         let frame_chunk = &mut self.frames[chunk_num];
         let last_filled_frame_ref = &mut self.last_filled_tracker[chunk_num]
             [[event.coord.y.into(), event.coord.x.into(), channel.into()]];
@@ -825,6 +1010,48 @@ impl<T: Clone + Default + FrameValue<Output = T> + Serialize> FrameSequence<T> {
         &self.running_intensities
     }
 
+    /// Start the sequence at the first frame ending after `t`, for ingesting a stream from a
+    /// position found with [`random_access_position`] rather than from its start. Each pixel's
+    /// first event afterwards only establishes its running time, so the stream should be
+    /// positioned at least `delta_t_max` ticks before `t`.
+    ///
+    /// Only [`Framer::ingest_event`] accounts for this; [`Framer::ingest_events_events`] does not.
+    ///
+    /// [`random_access_position`]: crate::framer::timestamped::random_access_position
+    pub fn start_at(&mut self, t: BigT) {
+        let tpf = BigT::from(self.state.tpf);
+        let first_frame = t.div_ceil(tpf) as i64;
+        self.state.frames_written = first_frame;
+
+        for chunk in &mut self.frames {
+            chunk.truncate(1);
+            if let Some(frame) = chunk.front_mut() {
+                frame.array.fill(None);
+                frame.filled_count = 0;
+            }
+        }
+        self.frame_idx_offsets.fill(first_frame);
+        for chunk in &mut self.last_filled_tracker {
+            chunk.fill(first_frame - 1);
+        }
+        self.chunk_filled_tracker.fill(false);
+
+        self.features.clear();
+        for i in 1..=2 {
+            self.features.push_back(FeatureInterval {
+                end_ts: (first_frame as BigT + i) * tpf,
+                features: vec![],
+            });
+        }
+
+        self.unstarted = Some(
+            self.pixel_ts_tracker
+                .iter()
+                .map(|chunk| Array3::from_elem(chunk.raw_dim(), true))
+                .collect(),
+        );
+    }
+
     /// Get the features detected for the next frame, and pop that off the feature vec
     pub fn pop_features(&mut self) -> Option<FeatureInterval> {
         if self.features.is_empty() {
@@ -958,6 +1185,43 @@ impl<T: Clone + Default + FrameValue<Output = T> + Serialize> FrameSequence<T> {
     }
 }
 
+impl<T: Copy + Default + FrameValue<Output = T> + Serialize> FrameSequence<T> {
+    /// Pop the next frame and write it to the given [`FrameWriter`], such as a PNG sequence or a
+    /// Y4M video. Pixels which were never filled are written as the default value.
+    /// # Errors
+    /// * If the frame has not been initialized
+    /// * If the frame cannot be written
+    pub fn write_next_frame<F: FrameWriter<T>>(
+        &mut self,
+        writer: &mut F,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.pop_next_frame() {
+            Some(chunks) if !chunks.is_empty() => {
+                writer.write_frame(&assemble_frame(&chunks))?;
+                Ok(())
+            }
+            _ => Err(FrameSequenceError::UninitializedFrame.into()),
+        }
+    }
+
+    /// Write out next frames to the given [`FrameWriter`] so long as the frame is filled
+    /// # Returns
+    /// * `Result<i32, Box<dyn Error>>` - The number of frames written
+    /// # Errors
+    /// * If a frame could not be written
+    pub fn write_filled_frames<F: FrameWriter<T>>(
+        &mut self,
+        writer: &mut F,
+    ) -> Result<i32, Box<dyn Error>> {
+        let mut frame_count = 0;
+        while self.is_frame_filled(0)? {
+            self.write_next_frame(writer)?;
+            frame_count += 1;
+        }
+        Ok(frame_count)
+    }
+}
+
 // TODO: refactor this garbage
 fn ingest_event_for_chunk<
     T: Clone + Default + FrameValue<Output = T> + Copy + Serialize + Send + Sync + Into<f64>,
@@ -977,50 +1241,25 @@ fn ingest_event_for_chunk<
     let prev_last_filled_frame = *last_filled_frame_ref;
     let prev_running_ts = *running_ts_ref;
 
-    if state.codec_version >= 2 && state.time_mode == TimeMode::AbsoluteT {
-        if prev_running_ts >= event.t as BigT {
-            return (
-                frame_chunk[0].filled_count == frame_chunk[0].array.len(),
-                false,
-            );
-        }
-        *running_ts_ref = event.t as BigT;
-    } else {
-        *running_ts_ref += u64::from(event.t);
-    }
+    // Advance the pixel's running time. For framed sources, this also rounds it up to the next
+    // frame boundary, since they can take advantage of a scheme that halves the event rate.
+    let Some((delta_t, fired_t)) = state.clock.advance(running_ts_ref, event) else {
+        return (
+            frame_chunk[0].filled_count == frame_chunk[0].array.len(),
+            false,
+        );
+    };
 
-    if ((running_ts_ref.saturating_sub(1)) as i64 / i64::from(state.tpf)) > *last_filled_frame_ref {
+    if ((fired_t.saturating_sub(1)) as i64 / i64::from(state.tpf)) > *last_filled_frame_ref {
         // Set the frame's value from the event
 
         if event.d != D_EMPTY {
             // If d == 0xFF, then the event was empty, and we simply repeat the last non-empty
             // event's intensity. Else we reset the intensity here.
-            let practical_d_max =
-                fast_math::log2_raw(T::max_f32() * (state.source_dtm / state.ref_interval) as f32);
-            if state.codec_version >= 2
-                && state.time_mode == TimeMode::AbsoluteT
-                && state.view_mode != FramedViewMode::SAE
-            {
-                // event.delta_t -= ((*last_filled_frame_ref + 1) * state.ref_interval as i64) as u32;
-                event.t = event.t.saturating_sub(prev_running_ts as u32);
-            }
-
-            // TODO: Handle SAE view mode
-            *last_frame_intensity_ref = T::get_frame_value(
-                event,
-                state.source,
-                state.ref_interval as f64,
-                practical_d_max,
-                state.source_dtm,
-                state.view_mode,
-                Some(SaeTime {
-                    running_t: *running_ts_ref as DeltaT,
-                    last_fired_t: prev_running_ts as DeltaT,
-                }), // TODO
-            );
+            *last_frame_intensity_ref = state.frame_value(event, delta_t, fired_t, prev_running_ts);
         }
 
-        *last_filled_frame_ref = (running_ts_ref.saturating_sub(1)) as i64 / i64::from(state.tpf);
+        *last_filled_frame_ref = (fired_t.saturating_sub(1)) as i64 / i64::from(state.tpf);
 
         // Grow the frames vec if necessary
         match *last_filled_frame_ref - *frame_idx_offset {
@@ -1069,28 +1308,6 @@ fn ingest_event_for_chunk<
         }
     }
 
-    // If framed video source, we can take advantage of scheme that reduces event rate by half
-    if state.codec_version >= 1
-        // && state.time_mode == TimeMode::DeltaT
-        && match state.source_camera {
-            SourceCamera::FramedU8
-            | SourceCamera::FramedU16
-            | SourceCamera::FramedU32
-            | SourceCamera::FramedU64
-            | SourceCamera::FramedF32
-            | SourceCamera::FramedF64 => true,
-            SourceCamera::Dvs
-            | SourceCamera::DavisU8
-            | SourceCamera::Atis
-            | SourceCamera::Asint => false,
-            // TODO: switch statement on the transcode MODE (frame-perfect or continuous), not just the source
-        }
-        && *running_ts_ref % u64::from(state.ref_interval) > 0
-    {
-        *running_ts_ref =
-            ((*running_ts_ref / u64::from(state.ref_interval)) + 1) * u64::from(state.ref_interval);
-    }
-
     if let Some(buffer_limit) = buffer_limit {
         // dbg!("buffer filled");
         if *last_filled_frame_ref > state.frames_written + buffer_limit as i64 {
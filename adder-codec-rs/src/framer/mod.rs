@@ -3,3 +3,12 @@ pub mod driver;
 
 /// Tools for casting events to intensity values
 pub mod scale_intensity;
+
+/// Tensor representations of events, for machine learning
+pub mod tensor;
+
+/// Reconstruction of frames at arbitrary timestamps
+pub mod timestamped;
+
+/// Writers for reconstructed frames in common image formats
+pub mod writers;
@@ -260,6 +260,79 @@ impl FrameValue for u64 {
     }
 }
 
+/// Floating-point frames hold the linear intensity of each event, normalized so that 1.0 is the
+/// maximum value of the source type. Intensities are not clamped, so the full dynamic range of
+/// the events is preserved.
+impl FrameValue for f64 {
+    type Output = f64;
+
+    fn get_frame_value(
+        event: &Event,
+        source_type: SourceType,
+        tpf: f64,
+        practical_d_max: f32,
+        delta_t_max: DeltaT,
+        view_mode: FramedViewMode,
+        px: Option<SaeTime>,
+    ) -> Self::Output {
+        match view_mode {
+            FramedViewMode::Intensity => {
+                let intensity = event_to_intensity(event) * tpf;
+                match source_type {
+                    SourceType::U8 => intensity / f64::from(u8::MAX),
+                    SourceType::U16 => intensity / f64::from(u16::MAX),
+                    SourceType::U32 => intensity / f64::from(u32::MAX),
+                    SourceType::U64 => intensity / u64::MAX as f64,
+                    SourceType::F32 | SourceType::F64 => intensity,
+                }
+            }
+            FramedViewMode::D => f64::from(event.d) / f64::from(practical_d_max),
+            FramedViewMode::DeltaT => f64::from(event.t) / f64::from(delta_t_max),
+            FramedViewMode::SAE => {
+                if let Some(px) = px {
+                    f64::from(px.running_t.saturating_sub(px.last_fired_t)) / f64::from(delta_t_max)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Float frames are unbounded, so D values are normalized against the 8-bit range
+    fn max_f32() -> f32 {
+        f32::from(u8::MAX)
+    }
+}
+
+/// See the implementation for [`f64`]
+impl FrameValue for f32 {
+    type Output = f32;
+
+    fn get_frame_value(
+        event: &Event,
+        source_type: SourceType,
+        tpf: f64,
+        practical_d_max: f32,
+        delta_t_max: DeltaT,
+        view_mode: FramedViewMode,
+        px: Option<SaeTime>,
+    ) -> Self::Output {
+        f64::get_frame_value(
+            event,
+            source_type,
+            tpf,
+            practical_d_max,
+            delta_t_max,
+            view_mode,
+            px,
+        ) as f32
+    }
+
+    fn max_f32() -> f32 {
+        f64::max_f32()
+    }
+}
+
 /// Convert an event to an intensity value.
 #[must_use]
 pub fn event_to_intensity(event: &Event) -> Intensity {
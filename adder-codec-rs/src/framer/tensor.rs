@@ -0,0 +1,353 @@
+use crate::framer::driver::{FramerBuilder, PixelClock};
+use crate::framer::scale_intensity::FrameValue;
+use crate::transcoder::source::video::FramedViewMode;
+use adder_codec_core::{BigT, DeltaT, Event, PlaneSize, SourceType, D_EMPTY};
+use ndarray::{s, Array3, Array4, Axis};
+use std::collections::VecDeque;
+
+/// The learning-friendly representation that a [`TensorFramer`] builds from events. Each tensor
+/// has the shape `(layers, height, width, channels)`, and spans one output frame interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TensorMode {
+    /// A single layer holding `exp(-(t_end - t_last) / tau)` for each pixel, where `t_end` is the
+    /// end of the interval, `t_last` is the time of the pixel's last event, and `tau` is given in
+    /// seconds. Pixels which have never fired are 0.
+    TimeSurface {
+        /// The decay time constant, in seconds
+        tau: f64,
+    },
+
+    /// The interval is divided into `bins` temporal bins. The intensity of each event is split
+    /// between the two nearest bins, weighted by its distance from each.
+    VoxelGrid {
+        /// The number of temporal bins
+        bins: usize,
+    },
+
+    /// Two layers, holding the number of events for each pixel and their mean intensity.
+    Histogram,
+}
+
+impl TensorMode {
+    /// The number of layers in each tensor
+    #[must_use]
+    pub fn layers(&self) -> usize {
+        match self {
+            TensorMode::TimeSurface { .. } => 1,
+            TensorMode::VoxelGrid { bins } => *bins,
+            TensorMode::Histogram => 2,
+        }
+    }
+}
+
+/// Accumulates ADΔER events into tensors for machine learning, such as time surfaces, voxel grids,
+/// and histograms. Build one with [`FramerBuilder::finish_tensors`].
+///
+/// Intensities are linear and normalized so that 1.0 is the maximum value of the source type.
+/// Since events for different pixels may not be perfectly interleaved, a tensor is only complete
+/// once an event arrives at least `delta_t_max` ticks after its interval ends (or on
+/// [`TensorFramer::flush`]). Events for an interval which has already been completed are ignored.
+pub struct TensorFramer {
+    mode: TensorMode,
+    plane: PlaneSize,
+
+    /// Ticks per tensor
+    tpt: BigT,
+    tps: DeltaT,
+    ref_interval: DeltaT,
+    delta_t_max: DeltaT,
+    source: SourceType,
+    clock: PixelClock,
+
+    /// The running timestamp of each pixel
+    pixel_ts: Array3<BigT>,
+
+    /// For time surfaces, the time of each pixel's last event in a completed interval
+    last_fired: Array3<Option<BigT>>,
+
+    /// The tensors for intervals which are not yet complete, starting with interval
+    /// `first_open_idx`
+    open: VecDeque<Array4<f32>>,
+    first_open_idx: BigT,
+
+    /// The latest timestamp of any event ingested
+    latest_t: BigT,
+
+    ready: VecDeque<Array4<f32>>,
+}
+
+impl TensorFramer {
+    pub(crate) fn new(builder: FramerBuilder, mode: TensorMode) -> Self {
+        let plane = builder.plane;
+        let tpt = match builder.output_fps {
+            Some(output_fps) => (builder.tps as f32 / output_fps) as BigT,
+            None => BigT::from(builder.ref_interval),
+        };
+        let shape = (plane.h_usize(), plane.w_usize(), plane.c_usize());
+
+        TensorFramer {
+            mode,
+            plane,
+            tpt: tpt.max(1),
+            tps: builder.tps,
+            ref_interval: builder.ref_interval,
+            delta_t_max: builder.delta_t_max,
+            source: builder.source,
+            clock: PixelClock::new(&builder),
+            pixel_ts: Array3::zeros(shape),
+            last_fired: Array3::from_elem(shape, None),
+            open: VecDeque::new(),
+            first_open_idx: 0,
+            latest_t: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Get the number of ticks spanned by each tensor
+    #[must_use]
+    pub fn ticks_per_tensor(&self) -> BigT {
+        self.tpt
+    }
+
+    fn empty_tensor(&self) -> Array4<f32> {
+        Array4::zeros((
+            self.mode.layers(),
+            self.plane.h_usize(),
+            self.plane.w_usize(),
+            self.plane.c_usize(),
+        ))
+    }
+
+    /// Ingest an ADΔER event.
+    ///
+    /// Returns `true` if there are tensors ready to pop
+    pub fn ingest_event(&mut self, event: &Event) -> bool {
+        let (y, x, c) = (
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        );
+        if y >= self.plane.h_usize() || x >= self.plane.w_usize() || c >= self.plane.c_usize() {
+            return !self.ready.is_empty();
+        }
+
+        let Some((delta_t, fired_t)) = self.clock.advance(&mut self.pixel_ts[[y, x, c]], event)
+        else {
+            return !self.ready.is_empty();
+        };
+        self.latest_t = self.latest_t.max(fired_t);
+
+        if event.d != D_EMPTY {
+            let window_idx = fired_t.saturating_sub(1) / self.tpt;
+            if window_idx >= self.first_open_idx {
+                let offset = (window_idx - self.first_open_idx) as usize;
+                while self.open.len() <= offset {
+                    let tensor = self.empty_tensor();
+                    self.open.push_back(tensor);
+                }
+                let intensity = f64::get_frame_value(
+                    &Event {
+                        coord: event.coord,
+                        d: event.d,
+                        t: delta_t as DeltaT,
+                    },
+                    self.source,
+                    f64::from(self.ref_interval),
+                    1.0,
+                    self.delta_t_max,
+                    FramedViewMode::Intensity,
+                    None,
+                ) as f32;
+                let window_start = window_idx * self.tpt;
+                let into_window = (fired_t - window_start) as f32 / self.tpt as f32;
+                accumulate(
+                    self.mode,
+                    &mut self.open[offset],
+                    [y, x, c],
+                    intensity,
+                    into_window,
+                );
+            }
+        }
+
+        // Complete the intervals which no pixel can still contribute to
+        while self.latest_t >= (self.first_open_idx + 1) * self.tpt + BigT::from(self.delta_t_max) {
+            self.complete_first_open();
+        }
+
+        !self.ready.is_empty()
+    }
+
+    fn complete_first_open(&mut self) {
+        let mut tensor = match self.open.pop_front() {
+            Some(tensor) => tensor,
+            None => self.empty_tensor(),
+        };
+        let window_start = self.first_open_idx * self.tpt;
+        let window_end = window_start + self.tpt;
+
+        match self.mode {
+            TensorMode::TimeSurface { tau } => {
+                let tau_ticks = (tau * f64::from(self.tps)).max(f64::MIN_POSITIVE);
+                let mut layer = tensor.index_axis_mut(Axis(0), 0);
+                for (value, last_fired) in layer.iter_mut().zip(self.last_fired.iter_mut()) {
+                    // The layer holds the time into the interval of the last event, if any
+                    if *value > 0.0 {
+                        *last_fired = Some(window_start + (*value * self.tpt as f32) as BigT);
+                    }
+                    *value = match last_fired {
+                        Some(t) => (-((window_end - *t) as f64) / tau_ticks).exp() as f32,
+                        None => 0.0,
+                    };
+                }
+            }
+            TensorMode::VoxelGrid { .. } => {}
+            TensorMode::Histogram => {
+                let (counts, mut means) =
+                    tensor.multi_slice_mut((s![0, .., .., ..], s![1, .., .., ..]));
+                means.zip_mut_with(&counts, |sum, count| {
+                    if *count > 0.0 {
+                        *sum /= *count;
+                    }
+                });
+            }
+        }
+
+        self.ready.push_back(tensor);
+        self.first_open_idx += 1;
+    }
+
+    /// Complete all intervals up to the latest event ingested, regardless of whether events for
+    /// them may still arrive.
+    ///
+    /// Returns `true` if there are tensors ready to pop
+    pub fn flush(&mut self) -> bool {
+        while self.first_open_idx * self.tpt < self.latest_t {
+            self.complete_first_open();
+        }
+        !self.ready.is_empty()
+    }
+
+    /// Pop the next completed tensor, with the shape `(layers, height, width, channels)`
+    pub fn pop_tensor(&mut self) -> Option<Array4<f32>> {
+        self.ready.pop_front()
+    }
+}
+
+/// Accumulate an event into the tensor for its interval. `into_window` is the fraction of the
+/// interval which had elapsed when the event fired, in `(0, 1]`.
+fn accumulate(
+    mode: TensorMode,
+    tensor: &mut Array4<f32>,
+    [y, x, c]: [usize; 3],
+    intensity: f32,
+    into_window: f32,
+) {
+    match mode {
+        TensorMode::TimeSurface { .. } => {
+            let last = &mut tensor[[0, y, x, c]];
+            *last = last.max(into_window);
+        }
+        TensorMode::VoxelGrid { bins } => {
+            let position = into_window * (bins - 1) as f32;
+            let lower = (position.floor() as usize).min(bins - 1);
+            let upper = (lower + 1).min(bins - 1);
+            let weight = position - lower as f32;
+            tensor[[lower, y, x, c]] += intensity * (1.0 - weight);
+            if upper != lower {
+                tensor[[upper, y, x, c]] += intensity * weight;
+            }
+        }
+        TensorMode::Histogram => {
+            tensor[[0, y, x, c]] += 1.0;
+            tensor[[1, y, x, c]] += intensity;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framer::driver::FrameSequenceError;
+    use adder_codec_core::{Coord, SourceCamera, TimeMode};
+
+    fn builder() -> FramerBuilder {
+        FramerBuilder::new(PlaneSize::new(2, 1, 1).unwrap(), 1)
+            .codec_version(2, TimeMode::AbsoluteT)
+            .time_parameters(1000, 100, 200, Some(10.0))
+            .source(SourceType::U8, SourceCamera::Dvs)
+    }
+
+    fn event(x: u16, d: u8, t: DeltaT) -> Event {
+        Event {
+            coord: Coord { x, y: 0, c: None },
+            d,
+            t,
+        }
+    }
+
+    #[test]
+    fn histogram_completes_after_delta_t_max() {
+        let mut framer = builder().finish_tensors(TensorMode::Histogram).unwrap();
+        assert_eq!(framer.ticks_per_tensor(), 100);
+
+        assert!(!framer.ingest_event(&event(0, 7, 50)));
+        assert!(!framer.ingest_event(&event(0, 7, 100)));
+        assert!(!framer.ingest_event(&event(1, 7, 250)));
+        assert!(framer.ingest_event(&event(1, 7, 300)));
+
+        let tensor = framer.pop_tensor().unwrap();
+        assert_eq!(tensor.dim(), (2, 1, 2, 1));
+        assert_eq!(tensor[[0, 0, 0, 0]], 2.0);
+        assert_eq!(tensor[[0, 0, 1, 0]], 0.0);
+        assert!(tensor[[1, 0, 0, 0]] > 0.0);
+        assert!(framer.pop_tensor().is_none());
+
+        assert!(framer.flush());
+        assert_eq!(framer.pop_tensor().unwrap()[[0, 0, 1, 0]], 0.0);
+        assert_eq!(framer.pop_tensor().unwrap()[[0, 0, 1, 0]], 2.0);
+        assert!(framer.pop_tensor().is_none());
+    }
+
+    #[test]
+    fn time_surface_decays() {
+        let mut framer = builder()
+            .finish_tensors(TensorMode::TimeSurface { tau: 0.1 })
+            .unwrap();
+        framer.ingest_event(&event(0, 7, 100));
+        framer.flush();
+        framer.ingest_event(&event(1, 7, 300));
+        framer.flush();
+
+        let first = framer.pop_tensor().unwrap();
+        assert_eq!(first[[0, 0, 0, 0]], 1.0);
+        assert_eq!(first[[0, 0, 1, 0]], 0.0);
+
+        let _ = framer.pop_tensor().unwrap();
+        let third = framer.pop_tensor().unwrap();
+        assert!((third[[0, 0, 0, 0]] - (-2.0_f32).exp()).abs() < 1e-6);
+        assert_eq!(third[[0, 0, 1, 0]], 1.0);
+    }
+
+    #[test]
+    fn voxel_grid_splits_between_bins() {
+        let mut framer = builder()
+            .finish_tensors(TensorMode::VoxelGrid { bins: 3 })
+            .unwrap();
+        framer.ingest_event(&event(0, 7, 75));
+        framer.flush();
+        let tensor = framer.pop_tensor().unwrap();
+        let total: f32 = tensor.slice(s![.., 0, 0, 0]).sum();
+        assert_eq!(tensor[[0, 0, 0, 0]], 0.0);
+        assert!((tensor[[1, 0, 0, 0]] - total / 2.0).abs() < 1e-6);
+        assert!((tensor[[2, 0, 0, 0]] - total / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn voxel_grid_needs_bins() {
+        assert!(matches!(
+            builder().finish_tensors(TensorMode::VoxelGrid { bins: 0 }),
+            Err(FrameSequenceError::NoTensorBins)
+        ));
+    }
+}
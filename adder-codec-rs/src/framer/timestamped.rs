@@ -0,0 +1,511 @@
+use crate::framer::driver::{Frame, FrameSequenceError, FrameSequenceState, FramerBuilder};
+use crate::framer::scale_intensity::FrameValue;
+use adder_codec_core::codec::decoder::Decoder;
+use adder_codec_core::codec::{CodecError, CodecMetadata, EncoderType};
+use adder_codec_core::{BigT, Event, PlaneSize, TimeMode, D_EMPTY};
+use bitstream_io::{BigEndian, BitRead, BitReader};
+use ndarray::Array3;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Reconstructs the instantaneous frame at each of a list of (possibly non-uniform) timestamps,
+/// such as the capture times of another sensor. Build one with
+/// [`FramerBuilder::finish_at_timestamps`].
+///
+/// Each pixel of the frame at time `t` takes the value of the event which spans `t`. A frame is
+/// ready once every pixel has an event spanning its timestamp, or once
+/// [`TimestampFramer::flush`] fills the remaining pixels with their last values.
+///
+/// Pixels are tracked just as in a [`FrameSequence`](crate::framer::driver::FrameSequence): each
+/// event advances the pixel's running timestamp and sets its value through the same
+/// [`FrameSequenceState`], so both framers reconstruct the same values. Only the trackers
+/// themselves are kept here, unchunked, since the frames aren't a constant interval apart.
+pub struct TimestampFramer<T> {
+    plane: PlaneSize,
+    state: FrameSequenceState,
+
+    /// The timestamps to reconstruct frames at, in ticks
+    timestamps: Vec<BigT>,
+
+    /// The number of frames popped so far. The front of `frames` is for
+    /// `timestamps[frames_popped]`.
+    frames_popped: usize,
+    frames: VecDeque<Frame<Option<T>>>,
+
+    /// The running timestamp of each pixel
+    pixel_ts_tracker: Array3<BigT>,
+
+    /// The index of the next timestamp that each pixel has not yet filled
+    pixel_next_timestamp: Array3<usize>,
+
+    /// The value of each pixel's last non-empty event
+    last_frame_intensity_tracker: Array3<T>,
+
+    /// The latest timestamp of any event ingested
+    latest_t: BigT,
+
+    /// When reading from the middle of a stream, the pixels whose running time is not yet known
+    unstarted: Option<Array3<bool>>,
+}
+
+impl<T: FrameValue<Output = T> + Copy + Default> TimestampFramer<T> {
+    pub(crate) fn new(builder: &FramerBuilder, timestamps: Vec<BigT>) -> Self {
+        let plane = builder.plane;
+        let shape = (plane.h_usize(), plane.w_usize(), plane.c_usize());
+        TimestampFramer {
+            plane,
+            state: FrameSequenceState::new(builder),
+            timestamps,
+            frames_popped: 0,
+            frames: VecDeque::new(),
+            pixel_ts_tracker: Array3::zeros(shape),
+            pixel_next_timestamp: Array3::zeros(shape),
+            last_frame_intensity_tracker: Array3::from_elem(shape, T::default()),
+            latest_t: 0,
+            unstarted: None,
+        }
+    }
+
+    /// Get the timestamps whose frames have not been popped yet
+    #[must_use]
+    pub fn remaining_timestamps(&self) -> &[BigT] {
+        &self.timestamps[self.frames_popped..]
+    }
+
+    fn frame_for_timestamp(&mut self, idx: usize) -> &mut Frame<Option<T>> {
+        let offset = idx - self.frames_popped;
+        while self.frames.len() <= offset {
+            self.frames.push_back(Frame {
+                array: Array3::default((
+                    self.plane.h_usize(),
+                    self.plane.w_usize(),
+                    self.plane.c_usize(),
+                )),
+                filled_count: 0,
+            });
+        }
+        &mut self.frames[offset]
+    }
+
+    /// Whether the frame for the next remaining timestamp is ready to pop
+    #[must_use]
+    pub fn is_next_frame_filled(&self) -> bool {
+        self.frames
+            .front()
+            .map_or(false, |frame| frame.filled_count == frame.array.len())
+    }
+
+    /// Ingest an ADΔER event.
+    ///
+    /// Returns `true` if the frame for the next remaining timestamp is ready to pop
+    pub fn ingest_event(&mut self, event: &Event) -> bool {
+        let px = [
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ];
+        if px[0] >= self.plane.h_usize()
+            || px[1] >= self.plane.w_usize()
+            || px[2] >= self.plane.c_usize()
+        {
+            return self.is_next_frame_filled();
+        }
+
+        // After a seek, a pixel's first event only establishes its running time, since the time
+        // of the event before it is unknown
+        if let Some(unstarted) = &mut self.unstarted {
+            if unstarted[px] {
+                unstarted[px] = false;
+                let _ = self
+                    .state
+                    .clock
+                    .advance(&mut self.pixel_ts_tracker[px], event);
+                return self.is_next_frame_filled();
+            }
+        }
+
+        let last_fired_t = self.pixel_ts_tracker[px];
+        let Some((delta_t, fired_t)) = self
+            .state
+            .clock
+            .advance(&mut self.pixel_ts_tracker[px], event)
+        else {
+            return self.is_next_frame_filled();
+        };
+        self.latest_t = self.latest_t.max(fired_t);
+
+        // An empty event repeats the pixel's last value
+        if event.d != D_EMPTY {
+            self.last_frame_intensity_tracker[px] =
+                self.state
+                    .frame_value(event, delta_t, fired_t, last_fired_t);
+        }
+
+        // Fill the pixel for every timestamp the event spans
+        let value = self.last_frame_intensity_tracker[px];
+        let mut idx = self.pixel_next_timestamp[px].max(self.frames_popped);
+        while idx < self.timestamps.len() && self.timestamps[idx] <= fired_t {
+            let frame = self.frame_for_timestamp(idx);
+            if frame.array[px].is_none() {
+                frame.array[px] = Some(value);
+                frame.filled_count += 1;
+            }
+            idx += 1;
+        }
+        self.pixel_next_timestamp[px] = idx;
+
+        self.is_next_frame_filled()
+    }
+
+    /// For every remaining timestamp up to the latest event ingested, fill any pixels without an
+    /// event spanning it with their last values.
+    ///
+    /// Returns `true` if the frame for the next remaining timestamp is ready to pop
+    pub fn flush(&mut self) -> bool {
+        let mut idx = self.frames_popped;
+        while idx < self.timestamps.len() && self.timestamps[idx] <= self.latest_t {
+            let offset = idx - self.frames_popped;
+            self.frame_for_timestamp(idx);
+            let frame = &mut self.frames[offset];
+            for (value, last) in frame
+                .array
+                .iter_mut()
+                .zip(self.last_frame_intensity_tracker.iter())
+            {
+                if value.is_none() {
+                    *value = Some(*last);
+                }
+            }
+            frame.filled_count = frame.array.len();
+            idx += 1;
+        }
+        self.is_next_frame_filled()
+    }
+
+    /// Pop the frame for the next remaining timestamp, if it is ready
+    ///
+    /// returns: the timestamp and its frame
+    pub fn pop_frame(&mut self) -> Option<(BigT, Array3<T>)> {
+        if !self.is_next_frame_filled() {
+            return None;
+        }
+        let frame = self.frames.pop_front()?;
+        let timestamp = self.timestamps[self.frames_popped];
+        self.frames_popped += 1;
+        Some((timestamp, frame.array.mapv(Option::unwrap_or_default)))
+    }
+}
+
+/// Reconstruct the instantaneous frame at time `t` (in ticks) from a decoded stream, without
+/// playing the stream from its beginning where possible.
+///
+/// Raw streams in [`TimeMode::AbsoluteT`] are binary searched for the first event at least
+/// `2 * delta_t_max` ticks before `t`, and compressed streams are seeked to the ADU spanning that
+/// time. Since every pixel fires at least once per `delta_t_max`, each pixel's first event after
+/// that point establishes its running time, and its following events reconstruct its value at
+/// `t`. Other streams are decoded from their start. Afterwards, the stream is positioned after
+/// the last event read.
+///
+/// # Errors
+/// Returns an error if the stream cannot be seeked or decoded, or
+/// [`FrameSequenceError::StreamEndsBeforeTime`] if it ends before `t`.
+pub fn frame_at_time<T, R>(
+    decoder: &mut Decoder<R>,
+    reader: &mut BitReader<R, BigEndian>,
+    builder: FramerBuilder,
+    t: BigT,
+) -> Result<Array3<T>, Box<dyn Error>>
+where
+    T: FrameValue<Output = T> + Default + Copy,
+    R: Read + Seek,
+{
+    let meta = *decoder.meta();
+    let mut framer = builder.finish_at_timestamps::<T>(vec![t])?;
+    let margin = 2 * BigT::from(meta.delta_t_max);
+
+    let position = if t > margin {
+        random_access_position(decoder, reader, t - margin)?
+    } else {
+        None
+    };
+    if let Some(position) = position {
+        decoder.set_input_stream_position(reader, position)?;
+        framer.unstarted = Some(Array3::from_elem(framer.pixel_ts_tracker.raw_dim(), true));
+    } else {
+        decoder.set_input_stream_position(reader, meta.header_size as u64)?;
+    }
+
+    loop {
+        match decoder.digest_event(reader) {
+            Ok(event) => {
+                if framer.ingest_event(&event) {
+                    break;
+                }
+            }
+            Err(e) if decoder.is_end_of_stream(&e) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    framer.flush();
+
+    match framer.pop_frame() {
+        Some((_, frame)) => Ok(frame),
+        None => Err(FrameSequenceError::StreamEndsBeforeTime.into()),
+    }
+}
+
+/// Find a position to resume decoding a stream from, at or before its first event at time `t`,
+/// without decoding the stream from its start. Raw [`TimeMode::AbsoluteT`] streams are binary
+/// searched with [`seek_position`], and compressed streams are seeked to an ADU boundary with
+/// [`adu_position`].
+///
+/// Returns `None` if the stream can only be decoded from its start, as with
+/// [`TimeMode::DeltaT`] raw streams. Pixels' first events after the returned position do not
+/// carry their running times, so callers should only use them to establish those times.
+///
+/// # Errors
+/// Returns an error if the stream cannot be seeked or read.
+pub fn random_access_position<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut BitReader<R, BigEndian>,
+    t: BigT,
+) -> Result<Option<u64>, CodecError> {
+    let meta = *decoder.meta();
+    match decoder.get_compression_type() {
+        EncoderType::Raw if meta.codec_version >= 2 && meta.time_mode == TimeMode::AbsoluteT => {
+            Ok(Some(seek_position(decoder, reader, t)?))
+        }
+        EncoderType::Compressed => Ok(Some(adu_position(&meta, reader, t)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Find the position of the ADU in a compressed stream which starts at or before `t`. Each ADU
+/// spans `adu_interval * ref_interval` ticks, so the ADUs before it are skipped by their sizes
+/// alone. If the stream ends before `t`, this is the end of the stream.
+///
+/// # Errors
+/// Returns an error if the stream cannot be seeked or read.
+pub fn adu_position<R: Read + Seek>(
+    meta: &CodecMetadata,
+    reader: &mut BitReader<R, BigEndian>,
+    t: BigT,
+) -> Result<u64, CodecError> {
+    let adu_span = meta.adu_interval as BigT * BigT::from(meta.ref_interval);
+    let skip_count = t.checked_div(adu_span).unwrap_or(0);
+
+    let mut position = meta.header_size as u64;
+    for _ in 0..skip_count {
+        reader.seek_bits(SeekFrom::Start(position * 8))?;
+        let mut size = [0u8; 4];
+        match reader.read_bytes(&mut size) {
+            Ok(()) => position += 4 + u64::from(u32::from_be_bytes(size)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(position)
+}
+
+/// Find the position of the first event in a raw [`TimeMode::AbsoluteT`] stream whose timestamp
+/// is at least `t`, assuming that events are approximately in timestamp order
+///
+/// # Errors
+/// Returns an error if the stream cannot be seeked or its events cannot be decoded.
+pub fn seek_position<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut BitReader<R, BigEndian>,
+    t: BigT,
+) -> Result<u64, CodecError> {
+    let meta = *decoder.meta();
+    let header_size = meta.header_size as u64;
+    let event_size = u64::from(meta.event_size);
+    let stream_size = reader.seek_bits(SeekFrom::End(0))? / 8;
+
+    // The last event of a raw stream marks its end, so it is excluded from the search
+    let event_count = stream_size.saturating_sub(header_size) / event_size;
+    let (mut low, mut high) = (0, event_count.saturating_sub(1));
+    while low < high {
+        let mid = low + (high - low) / 2;
+        decoder.set_input_stream_position(reader, header_size + mid * event_size)?;
+        if BigT::from(decoder.digest_event(reader)?.t) < t {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(header_size + low * event_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adder_codec_core::codec::encoder::Encoder;
+    use adder_codec_core::codec::raw::stream::{RawInput, RawOutput};
+    use adder_codec_core::codec::{CodecMetadata, EncoderOptions};
+    use adder_codec_core::{Coord, DeltaT, SourceCamera, SourceType};
+    use std::io::{BufReader, BufWriter, Cursor};
+
+    fn event(x: u16, d: u8, t: DeltaT) -> Event {
+        Event {
+            coord: Coord { x, y: 0, c: None },
+            d,
+            t,
+        }
+    }
+
+    #[test]
+    fn frames_at_timestamps() {
+        let mut framer: TimestampFramer<u8> =
+            FramerBuilder::new(PlaneSize::new(2, 1, 1).unwrap(), 1)
+                .codec_version(2, TimeMode::AbsoluteT)
+                .time_parameters(1280, 128, 256, None)
+                .source(SourceType::U8, SourceCamera::Dvs)
+                .finish_at_timestamps(vec![64, 192, 200])
+                .unwrap();
+
+        assert!(!framer.ingest_event(&event(0, 7, 128)));
+        assert!(framer.pop_frame().is_none());
+        assert!(framer.ingest_event(&event(1, 7, 128)));
+        assert_eq!(
+            framer.pop_frame(),
+            Some((64, Array3::from_elem((1, 2, 1), 128)))
+        );
+
+        // Pixel 1 has no event spanning the remaining timestamps, so it holds its last value
+        assert!(!framer.ingest_event(&event(0, 6, 256)));
+        assert!(framer.flush());
+        let (timestamp, frame) = framer.pop_frame().unwrap();
+        assert_eq!(timestamp, 192);
+        assert_eq!(frame.into_raw_vec(), vec![64, 128]);
+        assert_eq!(framer.remaining_timestamps(), &[200]);
+        assert_eq!(framer.pop_frame().unwrap().1.into_raw_vec(), vec![64, 128]);
+        assert!(framer.pop_frame().is_none());
+    }
+
+    #[test]
+    fn unordered_timestamps() {
+        assert!(FramerBuilder::new(PlaneSize::new(2, 1, 1).unwrap(), 1)
+            .finish_at_timestamps::<u8>(vec![64, 64])
+            .is_err());
+    }
+
+    #[test]
+    fn random_access_frame() {
+        let plane = PlaneSize::new(2, 1, 1).unwrap();
+        let compression = RawOutput::new(
+            CodecMetadata {
+                codec_version: 2,
+                header_size: 0,
+                time_mode: TimeMode::AbsoluteT,
+                plane,
+                tps: 1000,
+                ref_interval: 100,
+                delta_t_max: 100,
+                event_size: 0,
+                source_camera: SourceCamera::Dvs,
+                adu_interval: 1,
+            },
+            BufWriter::new(Vec::new()),
+        );
+        let mut encoder = Encoder::new_raw(compression, EncoderOptions::default(plane));
+        for t in (100..=2000).step_by(100) {
+            encoder.ingest_event(event(0, 7, t)).unwrap();
+            encoder.ingest_event(event(1, 6, t)).unwrap();
+        }
+        let bytes = encoder
+            .close_writer()
+            .unwrap()
+            .unwrap()
+            .into_inner()
+            .unwrap();
+
+        let mut bitreader = BitReader::endian(BufReader::new(Cursor::new(bytes)), BigEndian);
+        let mut decoder = Decoder::new_raw(RawInput::new(), &mut bitreader).unwrap();
+        let meta = *decoder.meta();
+        let builder = FramerBuilder::new(meta.plane, 1)
+            .codec_version(meta.codec_version, meta.time_mode)
+            .time_parameters(meta.tps, meta.ref_interval, meta.delta_t_max, None)
+            .source(decoder.get_source_type(), meta.source_camera);
+
+        let frame: Array3<u8> =
+            frame_at_time(&mut decoder, &mut bitreader, builder.clone(), 1550).unwrap();
+        assert_eq!(frame.into_raw_vec(), vec![128, 64]);
+
+        // The stream ends before this time
+        let err = frame_at_time::<u8, _>(&mut decoder, &mut bitreader, builder, 5000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            FrameSequenceError::StreamEndsBeforeTime.to_string()
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn random_access_compressed_frame() {
+        use adder_codec_core::codec::compressed::stream::{CompressedInput, CompressedOutput};
+
+        let plane = PlaneSize::new(2, 1, 1).unwrap();
+        let compression = CompressedOutput::new(
+            CodecMetadata {
+                codec_version: 3,
+                header_size: 0,
+                time_mode: TimeMode::AbsoluteT,
+                plane,
+                tps: 1000,
+                ref_interval: 100,
+                delta_t_max: 100,
+                event_size: 0,
+                source_camera: SourceCamera::Dvs,
+                adu_interval: 2,
+            },
+            BufWriter::new(Vec::new()),
+        );
+        let mut encoder = Encoder::new_compressed(compression, EncoderOptions::default(plane));
+        for t in (100..=3000).step_by(100) {
+            encoder.ingest_event(event(0, 7, t)).unwrap();
+            encoder.ingest_event(event(1, 6, t)).unwrap();
+        }
+        let bytes = encoder
+            .close_writer()
+            .unwrap()
+            .unwrap()
+            .into_inner()
+            .unwrap();
+
+        let mut bitreader = BitReader::endian(BufReader::new(Cursor::new(bytes)), BigEndian);
+        let mut decoder =
+            Decoder::new_compressed(CompressedInput::new(100, 100, 2), &mut bitreader).unwrap();
+        let meta = *decoder.meta();
+        let builder = FramerBuilder::new(meta.plane, 1)
+            .codec_version(meta.codec_version, meta.time_mode)
+            .time_parameters(meta.tps, meta.ref_interval, meta.delta_t_max, None)
+            .source(decoder.get_source_type(), meta.source_camera);
+
+        // Playing the stream from its start gives the same frame as seeking into it
+        let mut framer = builder
+            .clone()
+            .finish_at_timestamps::<u8>(vec![1550])
+            .unwrap();
+        decoder
+            .set_input_stream_position(&mut bitreader, meta.header_size as u64)
+            .unwrap();
+        while let Ok(event) = decoder.digest_event(&mut bitreader) {
+            if framer.ingest_event(&event) {
+                break;
+            }
+        }
+        let (_, expected) = framer.pop_frame().unwrap();
+
+        let frame: Array3<u8> =
+            frame_at_time(&mut decoder, &mut bitreader, builder.clone(), 1550).unwrap();
+        assert_eq!(frame, expected);
+
+        let err = frame_at_time::<u8, _>(&mut decoder, &mut bitreader, builder, 9000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            FrameSequenceError::StreamEndsBeforeTime.to_string()
+        );
+    }
+}
@@ -0,0 +1,621 @@
+use ndarray::{concatenate, Array, Array3, ArrayView3, Axis, Dimension};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Assemble the chunks returned by [`FrameSequence::pop_next_frame`] into a single frame. Pixels
+/// which were never filled take the default value.
+///
+/// [`FrameSequence::pop_next_frame`]: crate::framer::driver::FrameSequence::pop_next_frame
+#[must_use]
+pub fn assemble_frame<T: Copy + Default>(chunks: &[Array3<Option<T>>]) -> Array3<T> {
+    let chunks: Vec<Array3<T>> = chunks
+        .iter()
+        .map(|chunk| chunk.mapv(Option::unwrap_or_default))
+        .collect();
+    let views: Vec<ArrayView3<T>> = chunks.iter().map(Array3::view).collect();
+    concatenate(Axis(0), &views).unwrap_or_else(|_| Array3::default((0, 0, 1)))
+}
+
+/// Get the number of channels of a frame, if it can be written as a grayscale or RGB image
+fn image_channels<T>(frame: &Array3<T>) -> io::Result<usize> {
+    match frame.shape()[2] {
+        c @ (1 | 3) => Ok(c),
+        c => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot write a frame with {c} channels as an image"),
+        )),
+    }
+}
+
+/// Write a floating-point frame as a little-endian Portable FloatMap (PFM) image. A frame with
+/// 1 channel is written as grayscale (`Pf`), and a frame with 3 channels as RGB (`PF`).
+///
+/// # Errors
+/// Returns an error if the frame does not have 1 or 3 channels, or if it cannot be written.
+pub fn write_pfm<W: Write, T: Copy + Into<f64>>(
+    writer: &mut W,
+    frame: &Array3<T>,
+) -> io::Result<()> {
+    let (height, width, _) = frame.dim();
+    let magic = if image_channels(frame)? == 1 {
+        "Pf"
+    } else {
+        "PF"
+    };
+
+    // A negative scale indicates little-endian data
+    write!(writer, "{magic}\n{width} {height}\n-1.0\n")?;
+
+    // PFM rows are stored from bottom to top
+    for row in frame.axis_iter(Axis(0)).rev() {
+        for px in row.iter() {
+            let value: f64 = (*px).into();
+            writer.write_all(&(value as f32).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Write an OpenEXR attribute: its name, type, size, and value
+fn write_exr_attribute<W: Write>(
+    writer: &mut W,
+    name: &str,
+    type_name: &str,
+    value: &[u8],
+) -> io::Result<()> {
+    writer.write_all(name.as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.write_all(type_name.as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.write_all(&(value.len() as i32).to_le_bytes())?;
+    writer.write_all(value)
+}
+
+/// Write a floating-point frame as a single-part, uncompressed, scanline OpenEXR image with
+/// 32-bit float channels. A frame with 1 channel is written as luminance (`Y`), and a frame with
+/// 3 channels as RGB.
+///
+/// # Errors
+/// Returns an error if the frame does not have 1 or 3 channels, or if it cannot be written.
+pub fn write_exr<W: Write, T: Copy + Into<f64>>(
+    writer: &mut W,
+    frame: &Array3<T>,
+) -> io::Result<()> {
+    let (height, width, _) = frame.dim();
+
+    // OpenEXR stores channels in alphabetical order. Each entry is the channel name and its
+    // index in the frame.
+    let channels: &[(&str, usize)] = if image_channels(frame)? == 1 {
+        &[("Y", 0)]
+    } else {
+        &[("B", 2), ("G", 1), ("R", 0)]
+    };
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]); // Magic number
+    header.extend_from_slice(&2_i32.to_le_bytes()); // Version 2, single-part scanline
+
+    let mut channel_list = Vec::new();
+    for (name, _) in channels {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&2_i32.to_le_bytes()); // FLOAT pixel type
+        channel_list.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channel_list.extend_from_slice(&1_i32.to_le_bytes()); // x sampling
+        channel_list.extend_from_slice(&1_i32.to_le_bytes()); // y sampling
+    }
+    channel_list.push(0);
+    write_exr_attribute(&mut header, "channels", "chlist", &channel_list)?;
+    write_exr_attribute(&mut header, "compression", "compression", &[0])?;
+
+    let mut window = Vec::new();
+    for coord in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&coord.to_le_bytes());
+    }
+    write_exr_attribute(&mut header, "dataWindow", "box2i", &window)?;
+    write_exr_attribute(&mut header, "displayWindow", "box2i", &window)?;
+    write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0])?; // Increasing y
+    write_exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8])?;
+    write_exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    header.push(0); // End of header
+    writer.write_all(&header)?;
+
+    // Each uncompressed block holds one scanline
+    let block_size = 8 + width * channels.len() * 4;
+    let first_block = header.len() + height * 8;
+    for y in 0..height {
+        writer.write_all(&((first_block + y * block_size) as u64).to_le_bytes())?;
+    }
+
+    for (y, row) in frame.axis_iter(Axis(0)).enumerate() {
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&((block_size - 8) as i32).to_le_bytes())?;
+        for (_, c) in channels {
+            for x in 0..width {
+                let value: f64 = row[[x, *c]].into();
+                writer.write_all(&(value as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write an array of 32-bit floats as a NumPy `.npy` file (format version 1.0), in C order
+///
+/// # Errors
+/// Returns an error if the array cannot be written.
+pub fn write_npy<W: Write, D: Dimension>(writer: &mut W, array: &Array<f32, D>) -> io::Result<()> {
+    let shape = match array.shape() {
+        [len] => format!("({len},)"),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {shape}, }}");
+
+    // The magic string, version, and header length take 10 bytes. The header is padded with
+    // spaces and terminated with a newline so that the data is 64-byte aligned.
+    let padding = 64 - (10 + header.len() + 1) % 64;
+    header.push_str(&" ".repeat(padding % 64));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in array.iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// A destination for whole reconstructed frames, such as an image sequence or a video file
+pub trait FrameWriter<T> {
+    /// Write a frame with the shape `(height, width, channels)`
+    ///
+    /// # Errors
+    /// Returns an error if the frame cannot be represented in the output format, or if it cannot
+    /// be written.
+    fn write_frame(&mut self, frame: &Array3<T>) -> io::Result<()>;
+}
+
+/// An integer sample type which can be written to 8-bit or 16-bit image formats
+pub trait ImageSample: Copy {
+    /// The number of bits per sample
+    const BIT_DEPTH: u8;
+
+    /// The maximum value of a sample
+    const MAX: u16;
+
+    /// Append the big-endian bytes of the sample
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>);
+}
+
+impl ImageSample for u8 {
+    const BIT_DEPTH: u8 = 8;
+    const MAX: u16 = u8::MAX as u16;
+
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.push(self);
+    }
+}
+
+impl ImageSample for u16 {
+    const BIT_DEPTH: u8 = 16;
+    const MAX: u16 = u16::MAX;
+
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+/// The lookup table for [`crc32`], built at compile time
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Build the byte-wise lookup table for the reflected CRC-32 polynomial
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0_u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            c = if c & 1 == 1 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            bit += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+/// Compute the CRC-32 (ISO-HDLC) of the given bytes, as used by PNG chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0_u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Compute the Adler-32 checksum of the given bytes, as used by zlib streams
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1_u32, 0_u32), |(a, b), byte| {
+        let a = (a + u32::from(*byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+fn write_png_chunk<W: Write>(writer: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut crc_input = Vec::with_capacity(4 + data.len());
+    crc_input.extend_from_slice(chunk_type);
+    crc_input.extend_from_slice(data);
+    writer.write_all(&crc_input)?;
+    writer.write_all(&crc32(&crc_input).to_be_bytes())
+}
+
+/// Write a frame as a grayscale or RGB PNG image, with 8-bit or 16-bit samples. The image data is
+/// stored without compression, so that no compression library is needed.
+///
+/// # Errors
+/// Returns an error if the frame does not have 1 or 3 channels, or if it cannot be written.
+pub fn write_png<W: Write, T: ImageSample>(writer: &mut W, frame: &Array3<T>) -> io::Result<()> {
+    let (height, width, _) = frame.dim();
+    let color_type = if image_channels(frame)? == 1 { 0 } else { 2 };
+
+    writer.write_all(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[T::BIT_DEPTH, color_type, 0, 0, 0]);
+    write_png_chunk(writer, b"IHDR", &header)?;
+
+    // Each scanline starts with its filter type (none)
+    let mut raw = Vec::with_capacity(height * (1 + frame.len() / height.max(1) * 2));
+    for row in frame.axis_iter(Axis(0)) {
+        raw.push(0);
+        for sample in row.iter() {
+            sample.extend_be_bytes(&mut raw);
+        }
+    }
+
+    // A zlib stream of stored (uncompressed) deflate blocks
+    let mut zlib = Vec::with_capacity(raw.len() + raw.len() / 65535 * 5 + 11);
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = raw.chunks(usize::from(u16::MAX)).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(u8::from(blocks.peek().is_none()));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+    write_png_chunk(writer, b"IDAT", &zlib)?;
+
+    write_png_chunk(writer, b"IEND", &[])
+}
+
+/// Write a grayscale frame as a binary PGM image, with 8-bit or 16-bit (big-endian) samples.
+///
+/// # Errors
+/// Returns an error if the frame does not have 1 channel, or if it cannot be written.
+pub fn write_pgm<W: Write, T: ImageSample>(writer: &mut W, frame: &Array3<T>) -> io::Result<()> {
+    let (height, width, channels) = frame.dim();
+    if channels != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot write a frame with {channels} channels as a PGM image"),
+        ));
+    }
+
+    write!(writer, "P5\n{width} {height}\n{}\n", T::MAX)?;
+    let mut bytes = Vec::with_capacity(frame.len() * usize::from(T::BIT_DEPTH / 8));
+    for sample in frame.iter() {
+        sample.extend_be_bytes(&mut bytes);
+    }
+    writer.write_all(&bytes)
+}
+
+/// The image format of an [`ImageSequenceWriter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// PNG images, written with [`write_png`]
+    Png,
+
+    /// PGM images, written with [`write_pgm`]
+    Pgm,
+}
+
+/// Writes each frame as a numbered image file in a directory, e.g., `frame_000000.png`
+#[derive(Debug, Clone)]
+pub struct ImageSequenceWriter {
+    directory: PathBuf,
+    prefix: String,
+    format: ImageFormat,
+    next_index: usize,
+}
+
+impl ImageSequenceWriter {
+    /// Create a writer for the given directory, creating the directory if it doesn't exist
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn new<P: AsRef<Path>>(
+        directory: P,
+        prefix: &str,
+        format: ImageFormat,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(ImageSequenceWriter {
+            directory: directory.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+            format,
+            next_index: 0,
+        })
+    }
+
+    /// Get the number of frames written so far
+    #[must_use]
+    pub fn frames_written(&self) -> usize {
+        self.next_index
+    }
+}
+
+impl<T: ImageSample> FrameWriter<T> for ImageSequenceWriter {
+    fn write_frame(&mut self, frame: &Array3<T>) -> io::Result<()> {
+        let extension = match self.format {
+            ImageFormat::Png => "png",
+            ImageFormat::Pgm => "pgm",
+        };
+        let path = self
+            .directory
+            .join(format!("{}{:06}.{extension}", self.prefix, self.next_index));
+        let mut writer = BufWriter::new(File::create(path)?);
+        match self.format {
+            ImageFormat::Png => write_png(&mut writer, frame)?,
+            ImageFormat::Pgm => write_pgm(&mut writer, frame)?,
+        }
+        writer.flush()?;
+        self.next_index += 1;
+        Ok(())
+    }
+}
+
+/// Writes 8-bit frames as a YUV4MPEG2 (Y4M) video. Grayscale frames are written with the `mono`
+/// colorspace, and RGB frames are converted to full-range BT.601 YUV 4:4:4.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    frame_rate: (u32, u32),
+    shape: Option<(usize, usize, usize)>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Create a writer for a video with the given frame rate
+    pub fn new(writer: W, fps: f32) -> Self {
+        Y4mWriter {
+            writer,
+            frame_rate: ((fps * 1000.0).round() as u32, 1000),
+            shape: None,
+        }
+    }
+
+    /// Flush and return the underlying writer
+    ///
+    /// # Errors
+    /// Returns an error if the writer cannot be flushed.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> FrameWriter<u8> for Y4mWriter<W> {
+    fn write_frame(&mut self, frame: &Array3<u8>) -> io::Result<()> {
+        let (height, width, channels) = frame.dim();
+        match self.shape {
+            None => {
+                let colorspace = if image_channels(frame)? == 1 {
+                    "mono"
+                } else {
+                    "444"
+                };
+                writeln!(
+                    self.writer,
+                    "YUV4MPEG2 W{width} H{height} F{}:{} Ip A1:1 C{colorspace} XCOLORRANGE=FULL",
+                    self.frame_rate.0, self.frame_rate.1
+                )?;
+                self.shape = Some(frame.dim());
+            }
+            Some(shape) if shape != frame.dim() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "all frames of a Y4M video must have the same shape",
+                ));
+            }
+            Some(_) => {}
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+        if channels == 1 {
+            let bytes: Vec<u8> = frame.iter().copied().collect();
+            return self.writer.write_all(&bytes);
+        }
+
+        let mut planes = vec![Vec::with_capacity(height * width); 3];
+        for px in frame.lanes(Axis(2)) {
+            let (y, u, v) = rgb_to_yuv(px[0], px[1], px[2]);
+            planes[0].push(y);
+            planes[1].push(u);
+            planes[2].push(v);
+        }
+        for plane in planes {
+            self.writer.write_all(&plane)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert a full-range RGB pixel to full-range BT.601 YUV
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (f32::from(r), f32::from(g), f32::from(b));
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = (b - y) * 0.564 + 128.0;
+    let v = (r - y) * 0.713 + 128.0;
+    let clamp = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    (clamp(y), clamp(u), clamp(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_chunks() {
+        let chunks = vec![
+            Array3::from_elem((2, 3, 1), Some(1.0_f32)),
+            Array3::from_elem((1, 3, 1), None),
+        ];
+        let frame = assemble_frame(&chunks);
+        assert_eq!(frame.dim(), (3, 3, 1));
+        assert_eq!(frame[[1, 2, 0]], 1.0);
+        assert_eq!(frame[[2, 0, 0]], 0.0);
+    }
+
+    #[test]
+    fn pfm_rows_bottom_up() {
+        let mut frame = Array3::<f32>::zeros((2, 1, 1));
+        frame[[1, 0, 0]] = 2.5;
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, &frame).unwrap();
+
+        let header = b"Pf\n1 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(
+            &bytes[header.len()..header.len() + 4],
+            &2.5_f32.to_le_bytes()
+        );
+        assert_eq!(bytes.len(), header.len() + 8);
+
+        assert!(write_pfm(&mut bytes, &Array3::<f32>::zeros((1, 1, 2))).is_err());
+    }
+
+    #[test]
+    fn exr_layout() {
+        let mut frame = Array3::<f64>::zeros((2, 3, 3));
+        frame[[1, 2, 0]] = 4.0; // Red, at the end of the last scanline
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, &frame).unwrap();
+
+        assert_eq!(&bytes[..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // The offset table follows the header, and the file ends with the last block
+        let block_size = 8 + 3 * 3 * 4;
+        let last_offset = bytes.len() - block_size;
+        let table_end = last_offset - block_size;
+        let table = &bytes[table_end - 16..table_end];
+        assert_eq!(
+            u64::from_le_bytes(table[8..].try_into().unwrap()),
+            last_offset as u64
+        );
+        assert_eq!(&bytes[last_offset..last_offset + 4], &1_i32.to_le_bytes());
+        assert_eq!(&bytes[bytes.len() - 4..], &4.0_f32.to_le_bytes());
+    }
+
+    #[test]
+    fn npy_header() {
+        let array = ndarray::Array4::<f32>::ones((2, 3, 4, 1));
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &array).unwrap();
+
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(
+            header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3, 4, 1), }")
+        );
+        assert!(header.ends_with('\n'));
+        assert_eq!(bytes.len(), 10 + header_len + 24 * 4);
+    }
+
+    #[test]
+    fn png_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn png_layout() {
+        let frame = Array3::<u16>::from_elem((2, 3, 1), 0x1234);
+        let mut bytes = Vec::new();
+        write_png(&mut bytes, &frame).unwrap();
+
+        assert_eq!(
+            &bytes[..8],
+            &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]
+        );
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert_eq!(&bytes[16..20], &3_u32.to_be_bytes());
+        assert_eq!(&bytes[20..24], &2_u32.to_be_bytes());
+        assert_eq!(bytes[24], 16);
+
+        // IDAT holds the zlib header, one stored block, and the Adler-32 checksum
+        let raw_len = 2 * (1 + 3 * 2);
+        assert_eq!(
+            &bytes[33..37],
+            &((2 + 5 + raw_len + 4) as u32).to_be_bytes()
+        );
+        assert_eq!(&bytes[37..41], b"IDAT");
+        assert_eq!(&bytes[41..48], &[0x78, 0x01, 1, 14, 0, !14, 0xFF]);
+        assert_eq!(&bytes[48..51], &[0, 0x12, 0x34]);
+        assert_eq!(&bytes[bytes.len() - 8..bytes.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn pgm_16_bit() {
+        let frame = Array3::<u16>::from_elem((1, 2, 1), 0x0102);
+        let mut bytes = Vec::new();
+        write_pgm(&mut bytes, &frame).unwrap();
+        assert_eq!(bytes, b"P5\n2 1\n65535\n\x01\x02\x01\x02");
+        assert!(write_pgm(&mut bytes, &Array3::<u16>::zeros((1, 1, 3))).is_err());
+    }
+
+    #[test]
+    fn y4m_frames() {
+        let mut writer = Y4mWriter::new(Vec::new(), 30.0);
+        writer
+            .write_frame(&Array3::from_elem((1, 2, 3), 255_u8))
+            .unwrap();
+        assert!(writer.write_frame(&Array3::zeros((2, 2, 3))).is_err());
+        let bytes = writer.into_inner().unwrap();
+
+        let header = b"YUV4MPEG2 W2 H1 F30000:1000 Ip A1:1 C444 XCOLORRANGE=FULL\nFRAME\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(&bytes[header.len()..], &[255, 255, 128, 128, 128, 128]);
+    }
+}
@@ -14,6 +14,9 @@ pub mod transcoder; // Have to enable the 'transcoder' feature. Requires OpenCV
 /// A module for utilities which may be common between programs
 pub mod utils;
 
+/// Event-based computer vision on arbitrary ADΔER event streams
+pub mod vision;
+
 pub extern crate adder_codec_core;
 
 #[cfg(feature = "opencv")]
@@ -27,6 +27,7 @@ use std::thread;
 
 use adder_codec_core::codec::{CodecError, EncoderOptions, EncoderType};
 use adder_codec_core::{Event, PlaneSize, SourceCamera, SourceType, TimeMode};
+use serde::{Deserialize, Serialize};
 
 use crate::framer::scale_intensity::{FrameValue, SaeTime};
 use crate::transcoder::event_pixel_tree::Intensity32;
@@ -36,7 +37,7 @@ use tokio::runtime::Runtime;
 use video_rs_adder_dep::Frame;
 
 /// The EDI reconstruction mode, determining how intensities are integrated for the ADΔER model
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum TranscoderMode {
     /// Perform a framed EDI reconstruction at a given (constant) frame rate. Each frame is
     /// integrated in the ADΔER model with a [Framed](crate::transcoder::source::framed::Framed) source.
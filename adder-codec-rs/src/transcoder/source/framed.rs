@@ -1,7 +1,7 @@
 use crate::transcoder::source::video::SourceError;
 use crate::transcoder::source::video::Video;
 use crate::transcoder::source::video::{Source, VideoBuilder};
-use adder_codec_core::Mode::FramePerfect;
+use adder_codec_core::Mode::{Continuous, FramePerfect};
 use adder_codec_core::{DeltaT, Event, PixelMultiMode, PlaneSize, SourceCamera, TimeMode};
 
 use crate::utils::viz::ShowFeatureMode;
@@ -19,6 +19,21 @@ use std::path::PathBuf;
 use chrono::Local;
 use video_rs_adder_dep::{self, Decoder, Frame, Locator, Options, Resize};
 
+/// How the integration span of each input frame is determined
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum FrameTiming {
+    /// Every frame spans exactly `ref_time` ticks (constant frame rate)
+    #[default]
+    Constant,
+
+    /// Each frame spans the time until the next frame's presentation timestamp in the container
+    ContainerPts,
+
+    /// Each frame spans the time until the next of these timestamps, in seconds. There is one
+    /// timestamp per frame of the input video, starting from the video's first frame.
+    Timestamps(Vec<f64>),
+}
+
 /// Attributes of a framed video -> ADΔER transcode
 pub struct Framed<W: Write + 'static> {
     cap: Decoder,
@@ -36,6 +51,16 @@ pub struct Framed<W: Write + 'static> {
     /// Whether the input video is color
     color_input: bool,
 
+    /// How the integration span of each frame is determined
+    timing: FrameTiming,
+
+    /// The next decoded frame and its timestamp in seconds, held back until the timestamp of the
+    /// frame after it is known
+    lookahead: Option<(f64, Frame)>,
+
+    /// Number of frames consumed since `frame_idx_start`
+    frames_consumed: usize,
+
     pub(crate) video: Video<W>,
 }
 unsafe impl<W: Write> Sync for Framed<W> {}
@@ -74,6 +99,9 @@ impl<W: Write + 'static> Framed<W> {
             source_fps,
             scale,
             color_input,
+            timing: FrameTiming::Constant,
+            lookahead: None,
+            frames_consumed: 0,
             video,
         })
     }
@@ -91,6 +119,74 @@ impl<W: Write + 'static> Framed<W> {
         Ok(self)
     }
 
+    /// Set how the integration span of each frame is determined. With anything other than
+    /// [`FrameTiming::Constant`], events are no longer aligned to multiples of `ref_time`, so the
+    /// source switches to continuous integration. `ref_time` then serves as the nominal frame
+    /// duration, used for the last frame and for any frame without a usable timestamp.
+    pub fn frame_timing(mut self, timing: FrameTiming) -> Result<Self, SourceError> {
+        if let FrameTiming::Timestamps(timestamps) = &timing {
+            if timestamps.windows(2).any(|pair| pair[1] <= pair[0]) {
+                return Err(SourceError::BadParams(
+                    "frame timestamps must be strictly increasing".to_string(),
+                ));
+            }
+        }
+
+        self.video.state.params.pixel_tree_mode = if timing == FrameTiming::Constant {
+            FramePerfect
+        } else {
+            Continuous
+        };
+        self.timing = timing;
+        self.lookahead = None;
+        Ok(self)
+    }
+
+    /// Decode the next frame, along with the number of seconds it spans (if known)
+    fn decode_timed(&mut self) -> Result<(Frame, Option<f64>), SourceError> {
+        let (frame, duration) = match &self.timing {
+            FrameTiming::Constant => {
+                let (_, frame) = self.cap.decode()?;
+                (frame, None)
+            }
+            FrameTiming::ContainerPts => {
+                let (pts, frame) = match self.lookahead.take() {
+                    Some(next) => next,
+                    None => {
+                        let (pts, frame) = self.cap.decode()?;
+                        (pts.as_secs_f64(), frame)
+                    }
+                };
+
+                // The last frame has no successor, so it falls back to the nominal duration
+                match self.cap.decode() {
+                    Ok((next_pts, next_frame)) => {
+                        let next_pts = next_pts.as_secs_f64();
+                        self.lookahead = Some((next_pts, next_frame));
+                        (frame, Some(next_pts - pts))
+                    }
+                    Err(
+                        video_rs_adder_dep::Error::ReadExhausted
+                        | video_rs_adder_dep::Error::DecodeExhausted,
+                    ) => (frame, None),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            FrameTiming::Timestamps(timestamps) => {
+                let (_, frame) = self.cap.decode()?;
+                let idx = self.frame_idx_start as usize + self.frames_consumed;
+                let duration = match (timestamps.get(idx), timestamps.get(idx + 1)) {
+                    (Some(t), Some(next_t)) => Some(next_t - t),
+                    _ => None,
+                };
+                (frame, duration)
+            }
+        };
+        self.frames_consumed += 1;
+
+        Ok((frame, duration.filter(|secs| *secs > 0.0)))
+    }
+
     /// Automatically derive the ticks per second from the source FPS and `ref_time`
     pub fn auto_time_parameters(
         mut self,
@@ -123,19 +219,25 @@ impl<W: Write + 'static> Framed<W> {
 
 impl<W: Write + 'static> Source<W> for Framed<W> {
     /// Get pixel-wise intensities directly from source frame, and integrate them with
-    /// `ref_time` (the number of ticks each frame is said to span)
+    /// `ref_time` (the number of ticks each frame is said to span), or with the frame's real
+    /// duration if the source has variable frame timing
     fn consume(
         &mut self,
         view_interval: u32,
         thread_pool: &ThreadPool,
     ) -> Result<Vec<Vec<Event>>, SourceError> {
-        let (_, frame) = self.cap.decode()?;
+        let (frame, duration) = self.decode_timed()?;
         self.input_frame = handle_color(frame, self.color_input)?;
 
+        let ref_time = self.video.state.params.ref_time as f32;
+        let time_spanned = duration.map_or(ref_time, |secs| {
+            (secs * self.video.state.tps as f64).max(1.0) as f32
+        });
+
         let res = thread_pool.install(|| {
-            self.video.integrate_matrix(
+            self.video.integrate_matrix_scaled(
                 self.input_frame.clone(),
-                self.video.state.params.ref_time as f32,
+                time_spanned,
                 view_interval,
             )
         });
@@ -153,6 +255,7 @@ impl<W: Write + 'static> Source<W> for Framed<W> {
                         mse: Some(0.0),
                         psnr: Some(0.0),
                         ssim: None,
+                        ms_ssim: None,
                     });
 
                 let metrics = metrics.unwrap();
@@ -0,0 +1,456 @@
+use crate::transcoder::source::video::SourceError;
+use crate::transcoder::source::video::Video;
+use crate::transcoder::source::video::{Source, VideoBuilder};
+use adder_codec_core::Mode::{self, FramePerfect};
+use adder_codec_core::{
+    AbsoluteT, DeltaT, Event, PixelMultiMode, PlaneSize, SourceCamera, TimeMode,
+};
+
+use crate::utils::viz::ShowFeatureMode;
+use adder_codec_core::codec::{EncoderOptions, EncoderType};
+
+use ndarray::Array3;
+use rayon::ThreadPool;
+use std::collections::VecDeque;
+use std::io::Write;
+
+#[cfg(feature = "feature-logging")]
+use chrono::Local;
+use video_rs_adder_dep::Frame;
+
+/// A sample type for frames pushed into an [`InMemory`] source. Samples are mapped onto the
+/// 8-bit intensity scale that the transcoder operates on, without rounding.
+pub trait IntensitySample: Copy {
+    /// Map the sample onto the range [0, 255]
+    fn to_intensity(self) -> f32;
+}
+
+impl IntensitySample for u8 {
+    fn to_intensity(self) -> f32 {
+        f32::from(self)
+    }
+}
+
+impl IntensitySample for u16 {
+    fn to_intensity(self) -> f32 {
+        f32::from(self) / 257.0
+    }
+}
+
+/// Samples are expected to be normalized to [0, 1]
+impl IntensitySample for f32 {
+    fn to_intensity(self) -> f32 {
+        self * 255.0
+    }
+}
+
+/// A frame waiting to be integrated, with its intensities already mapped to the 8-bit scale
+struct QueuedFrame {
+    intensities: Array3<f32>,
+    timestamp: Option<AbsoluteT>,
+    exposure: DeltaT,
+}
+
+/// Attributes of an in-memory framed video -> ADΔER transcode. The caller pushes each frame,
+/// along with its timing, and then calls [`Source::consume`] to integrate it.
+pub struct InMemory<W: Write + 'static> {
+    queue: VecDeque<QueuedFrame>,
+    pub(crate) input_frame: Frame,
+
+    /// The intensities of the last integrated frame, held across any gap before the next frame
+    last_intensities: Option<Array3<f32>>,
+
+    /// The timestamp of the first frame pushed. All timestamps are relative to this one.
+    time_origin: Option<AbsoluteT>,
+
+    /// The tick (relative to `time_origin`) where the last integrated frame's exposure ended
+    running_t: AbsoluteT,
+
+    pub(crate) video: Video<W>,
+}
+unsafe impl<W: Write> Sync for InMemory<W> {}
+
+impl<W: Write + 'static> InMemory<W> {
+    /// Create a new `InMemory` source for frames of the given size. Pushed frames must have the
+    /// shape `(height, width, channels)`. Like [`Framed`](super::framed::Framed), the source
+    /// integrates in [`Mode::FramePerfect`] by default.
+    pub fn new(plane: PlaneSize) -> Result<InMemory<W>, SourceError> {
+        let video = Video::new(plane, FramePerfect, None)?;
+
+        Ok(InMemory {
+            queue: VecDeque::new(),
+            input_frame: Frame::zeros((plane.h_usize(), plane.w_usize(), plane.c_usize())),
+            last_intensities: None,
+            time_origin: None,
+            running_t: 0,
+            video,
+        })
+    }
+
+    /// Set how pixels integrate across frames. [`Mode::FramePerfect`] assumes every frame spans
+    /// `ref_time`, so use [`Mode::Continuous`] if frames have other exposures or gaps between
+    /// them, as [`Framed`](super::framed::Framed) does for variable frame timing.
+    pub fn pixel_tree_mode(mut self, mode: Mode) -> Self {
+        self.video.state.params.pixel_tree_mode = mode;
+        self
+    }
+
+    /// Queue a frame to be integrated by the next call to [`Source::consume`].
+    ///
+    /// # Arguments
+    ///
+    /// * `frame`: the frame intensities, with shape `(height, width, channels)`
+    /// * `timestamp`: the tick at which the frame's exposure starts. If `None`, the frame starts
+    /// where the previous frame's exposure ended. If there is a gap since the previous frame's
+    /// exposure, the previous frame's intensities are held over the gap.
+    /// * `exposure`: the number of ticks the frame spans. If `None`, the frame spans `ref_time`.
+    ///
+    /// # Errors
+    /// Returns an error if the frame has the wrong shape, or if its timestamp precedes the end of
+    /// the previous frame.
+    pub fn push_frame<T: IntensitySample>(
+        &mut self,
+        frame: &Array3<T>,
+        timestamp: Option<AbsoluteT>,
+        exposure: Option<DeltaT>,
+    ) -> Result<(), SourceError> {
+        let plane = self.video.state.plane;
+        if frame.dim() != (plane.h_usize(), plane.w_usize(), plane.c_usize()) {
+            return Err(SourceError::BadParams(format!(
+                "frame shape {:?} does not match the source plane {}x{}x{}",
+                frame.shape(),
+                plane.h(),
+                plane.w(),
+                plane.c()
+            )));
+        }
+
+        let exposure = exposure.unwrap_or(self.video.state.params.ref_time);
+        if exposure == 0 {
+            return Err(SourceError::BadParams(
+                "frame exposure must be at least one tick".to_string(),
+            ));
+        }
+
+        if let Some(timestamp) = timestamp {
+            // Once the queue has drained, the previous frame is the last one integrated
+            let last_end = match self.queue.back() {
+                Some(queued) => queued.timestamp.map(|t| t + queued.exposure),
+                None => self.time_origin.map(|origin| origin + self.running_t),
+            };
+            if last_end.map_or(false, |last_end| timestamp < last_end) {
+                return Err(SourceError::BadParams(
+                    "frame timestamp precedes the end of the previous frame".to_string(),
+                ));
+            }
+        }
+
+        self.queue.push_back(QueuedFrame {
+            intensities: frame.mapv(T::to_intensity),
+            timestamp,
+            exposure,
+        });
+        Ok(())
+    }
+
+    /// Get the number of frames waiting to be integrated
+    pub fn queued_frames(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Get the number of ticks each frame is said to span, by default
+    pub fn get_ref_time(&self) -> u32 {
+        self.video.state.params.ref_time
+    }
+
+    /// Get the most recently integrated frame, on the 8-bit scale
+    pub fn get_last_input_frame(&self) -> &Frame {
+        &self.input_frame
+    }
+}
+
+impl<W: Write + 'static> Source<W> for InMemory<W> {
+    /// Integrate the oldest queued frame over its exposure. Returns [`SourceError::BufferEmpty`]
+    /// if no frame has been pushed.
+    fn consume(
+        &mut self,
+        view_interval: u32,
+        thread_pool: &ThreadPool,
+    ) -> Result<Vec<Vec<Event>>, SourceError> {
+        let queued = match self.queue.pop_front() {
+            None => return Err(SourceError::BufferEmpty),
+            Some(queued) => queued,
+        };
+
+        let start_t = match queued.timestamp {
+            None => self.running_t,
+            Some(timestamp) => {
+                let origin = *self.time_origin.get_or_insert(timestamp);
+                timestamp.saturating_sub(origin).max(self.running_t)
+            }
+        };
+        if self.time_origin.is_none() {
+            self.time_origin = Some(0);
+        }
+
+        let ref_time = self.video.state.params.ref_time as f32;
+        let mut events = match self.last_intensities.take() {
+            Some(last) if start_t > self.running_t => {
+                // Hold the last frame's intensities over the gap between exposures
+                let gap = (start_t - self.running_t) as f32;
+                thread_pool.install(|| {
+                    self.video
+                        .integrate_intensities(last, gap, gap / ref_time, view_interval)
+                })?
+            }
+            _ => vec![],
+        };
+
+        let exposure = queued.exposure as f32;
+        let frame_events = thread_pool.install(|| {
+            self.video.integrate_intensities(
+                queued.intensities.clone(),
+                exposure,
+                exposure / ref_time,
+                view_interval,
+            )
+        })?;
+
+        if events.is_empty() {
+            events = frame_events;
+        } else {
+            for (chunk, frame_chunk) in events.iter_mut().zip(frame_events) {
+                chunk.extend(frame_chunk);
+            }
+        }
+
+        self.running_t = start_t + queued.exposure;
+        self.input_frame = queued.intensities.mapv(|v| v as u8);
+        self.last_intensities = Some(queued.intensities);
+
+        Ok(events)
+    }
+
+    fn crf(&mut self, crf: u8) {
+        self.video.update_crf(crf);
+    }
+
+    fn get_video_mut(&mut self) -> &mut Video<W> {
+        &mut self.video
+    }
+
+    fn get_video_ref(&self) -> &Video<W> {
+        &self.video
+    }
+
+    fn get_video(self) -> Video<W> {
+        self.video
+    }
+
+    fn get_input(&self) -> Option<&Frame> {
+        Some(self.get_last_input_frame())
+    }
+
+    fn get_running_input_bitrate(&self) -> f64 {
+        let video = self.get_video_ref();
+        video.get_tps() as f64 / video.get_ref_time() as f64
+            * video.state.plane.volume() as f64
+            * 8.0
+    }
+}
+
+impl<W: Write + 'static> VideoBuilder<W> for InMemory<W> {
+    fn contrast_thresholds(mut self, c_thresh_pos: u8, _c_thresh_neg: u8) -> Self {
+        self.video = self.video.c_thresh_pos(c_thresh_pos);
+        self
+    }
+
+    fn crf(mut self, crf: u8) -> Self {
+        self.video.update_crf(crf);
+        self
+    }
+
+    fn quality_manual(
+        mut self,
+        c_thresh_baseline: u8,
+        c_thresh_max: u8,
+        delta_t_max_multiplier: u32,
+        c_increase_velocity: u8,
+        feature_c_radius_denom: f32,
+    ) -> Self {
+        self.video.update_quality_manual(
+            c_thresh_baseline,
+            c_thresh_max,
+            delta_t_max_multiplier,
+            c_increase_velocity,
+            feature_c_radius_denom,
+        );
+        self
+    }
+
+    fn c_thresh_pos(mut self, c_thresh_pos: u8) -> Self {
+        self.video = self.video.c_thresh_pos(c_thresh_pos);
+        self
+    }
+
+    fn c_thresh_neg(mut self, c_thresh_neg: u8) -> Self {
+        self.video = self.video.c_thresh_neg(c_thresh_neg);
+        self
+    }
+
+    fn chunk_rows(mut self, chunk_rows: usize) -> Self {
+        self.video = self.video.chunk_rows(chunk_rows);
+        self
+    }
+
+    fn time_parameters(
+        mut self,
+        tps: DeltaT,
+        ref_time: DeltaT,
+        delta_t_max: DeltaT,
+        time_mode: Option<TimeMode>,
+    ) -> Result<Self, SourceError> {
+        if delta_t_max % ref_time == 0 {
+            self.video = self
+                .video
+                .time_parameters(tps, ref_time, delta_t_max, time_mode)?;
+        } else {
+            eprintln!("delta_t_max must be a multiple of ref_time");
+        }
+        Ok(self)
+    }
+
+    fn write_out(
+        mut self,
+        source_camera: SourceCamera,
+        time_mode: TimeMode,
+        pixel_multi_mode: PixelMultiMode,
+        adu_interval: Option<usize>,
+        encoder_type: EncoderType,
+        encoder_options: EncoderOptions,
+        write: W,
+    ) -> Result<Box<Self>, SourceError> {
+        self.video = self.video.write_out(
+            Some(source_camera),
+            Some(time_mode),
+            Some(pixel_multi_mode),
+            adu_interval,
+            encoder_type,
+            encoder_options,
+            write,
+        )?;
+        Ok(Box::new(self))
+    }
+
+    fn show_display(mut self, show_display: bool) -> Self {
+        self.video = self.video.show_display(show_display);
+        self
+    }
+
+    fn detect_features(mut self, detect_features: bool, show_features: ShowFeatureMode) -> Self {
+        self.video = self.video.detect_features(detect_features, show_features);
+        self
+    }
+
+    #[cfg(feature = "feature-logging")]
+    fn log_path(mut self, name: String) -> Self {
+        let date_time = Local::now();
+        let formatted = format!("{}_{}.log", name, date_time.format("%d_%m_%Y_%H_%M_%S"));
+        let log_handle = std::fs::File::create(formatted).ok();
+        self.video.state.feature_log_handle = log_handle;
+
+        // Write the plane size to the log file
+        if let Some(handle) = &mut self.video.state.feature_log_handle {
+            writeln!(
+                handle,
+                "{}x{}x{}",
+                self.video.state.plane.w(),
+                self.video.state.plane.h(),
+                self.video.state.plane.c()
+            )
+            .unwrap();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+
+    #[test]
+    fn intensity_samples() {
+        assert_eq!(255_u8.to_intensity(), 255.0);
+        assert_eq!(u16::MAX.to_intensity(), 255.0);
+        assert_eq!(0.5_f32.to_intensity(), 127.5);
+    }
+
+    #[test]
+    fn push_and_consume() {
+        let plane = PlaneSize::new(4, 3, 1).unwrap();
+        let mut source: InMemory<Vec<u8>> = InMemory::new(plane).unwrap();
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+        assert!(source
+            .push_frame(&Array3::<u8>::zeros((4, 3, 1)), None, None)
+            .is_err());
+        assert!(source
+            .push_frame(&Array3::<u8>::zeros((3, 4, 1)), None, Some(0))
+            .is_err());
+
+        source
+            .push_frame(&Array3::from_elem((3, 4, 1), 100_u8), Some(1000), None)
+            .unwrap();
+        source
+            .push_frame(
+                &Array3::from_elem((3, 4, 1), 0.5_f32),
+                Some(1600),
+                Some(100),
+            )
+            .unwrap();
+        assert!(source
+            .push_frame(&Array3::<u8>::zeros((3, 4, 1)), Some(1650), None)
+            .is_err());
+        assert_eq!(source.queued_frames(), 2);
+
+        source.consume(1, &pool).unwrap();
+        assert_eq!(source.running_t, source.get_ref_time());
+        assert_eq!(source.get_last_input_frame()[[0, 0, 0]], 100);
+
+        source.consume(1, &pool).unwrap();
+        assert_eq!(source.running_t, 700);
+        assert_eq!(source.get_last_input_frame()[[0, 0, 0]], 127);
+
+        assert!(matches!(
+            source.consume(1, &pool),
+            Err(SourceError::BufferEmpty)
+        ));
+    }
+
+    #[test]
+    fn push_after_drain() {
+        let plane = PlaneSize::new(4, 3, 1).unwrap();
+        let mut source: InMemory<Vec<u8>> = InMemory::new(plane).unwrap();
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+        source
+            .push_frame(&Array3::from_elem((3, 4, 1), 100_u8), Some(1000), None)
+            .unwrap();
+        source.consume(1, &pool).unwrap();
+        assert_eq!(source.queued_frames(), 0);
+
+        let ref_time = source.get_ref_time();
+        assert!(matches!(
+            source.push_frame(
+                &Array3::<u8>::zeros((3, 4, 1)),
+                Some(1000 + ref_time - 1),
+                None
+            ),
+            Err(SourceError::BadParams(_))
+        ));
+        source
+            .push_frame(&Array3::<u8>::zeros((3, 4, 1)), Some(1000 + ref_time), None)
+            .unwrap();
+    }
+}
@@ -5,6 +5,12 @@ pub mod davis;
 /// Tools for transcoding from a framed video source to ADΔER
 pub mod framed;
 
+/// Tools for transcoding from a Y4M or raw planar YUV video source to ADΔER, without ffmpeg
+pub mod yuv;
+
+/// Tools for transcoding frames supplied directly by the caller to ADΔER
+pub mod in_memory;
+
 /// Common functions and structs for all transcoder sources
 pub mod video;
 pub mod prophesee;
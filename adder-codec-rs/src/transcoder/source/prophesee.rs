@@ -10,9 +10,10 @@ use adder_codec_core::Mode::Continuous;
 use adder_codec_core::{
     DeltaT, Event, PixelMultiMode, PlaneSize, SourceCamera, SourceType, TimeMode,
 };
-use ndarray::Array3;
+use ndarray::{Array3, Axis};
 use rayon::ThreadPool;
 use serde::Deserialize;
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
@@ -25,12 +26,28 @@ use video_rs_adder_dep::Frame;
 /// The temporal granularity of the source (ticks per second)
 const PROPHESEE_SOURCE_TPS: u32 = 1000000;
 
+/// The default contrast threshold of the camera, in log intensity. A fixed assumption, unless it is
+/// configured or estimated.
+const DEFAULT_CAMERA_THETA: f64 = 0.05;
+
+/// The span of log intensity which maps onto the 8-bit intensity scale
+const LN_INTENSITY_RANGE: f64 = std::f64::consts::LN_2;
+
+/// The percentile of per-pixel log intensity excursions which is assumed to span the whole
+/// intensity range, when estimating the contrast threshold
+const THETA_ESTIMATE_PERCENTILE: f64 = 0.99;
+
 /// Attributes of a framed video -> ADΔER transcode
 pub struct Prophesee<W: Write> {
     pub(crate) video: Video<W>,
 
     input_reader: BufReader<File>,
 
+    /// The position in the file where the event data starts
+    data_start: u64,
+
+    decoder: EventDecoder,
+
     // We scale up the input timestamps by this amount for accuracy under ADDER.
     // For example, with time_change = 255, a timestamp of 12 in the source becomes 3060
     // ADDER ticks
@@ -45,10 +62,14 @@ pub struct Prophesee<W: Write> {
     /// The log-space last intensity value for each pixel
     pub dvs_last_ln_val: Array3<f64>,
 
-    camera_theta: f64,
+    /// The log intensity change signalled by a positive event
+    camera_theta_pos: f64,
+
+    /// The log intensity change signalled by a negative event
+    camera_theta_neg: f64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 struct DvsEvent {
     t: u32,
     x: u16,
@@ -59,15 +80,17 @@ struct DvsEvent {
 unsafe impl<W: Write> Sync for Prophesee<W> {}
 
 impl<W: Write + 'static> Prophesee<W> {
-    /// Create a new `Prophesee` transcoder
+    /// Create a new `Prophesee` transcoder, for a `.dat` file or a `.raw` file in the EVT 2.0 or
+    /// EVT 3.0 encoding
     pub fn new(ref_time: u32, input_filename: String) -> Result<Self, Box<dyn Error>> {
         let source = File::open(PathBuf::from(input_filename))?;
         let mut input_reader = BufReader::new(source);
 
         // Parse header
-        let (_, _, _, size) = parse_header(&mut input_reader).unwrap();
+        let header = parse_header(&mut input_reader)?;
+        let data_start = input_reader.stream_position()?;
 
-        let plane = PlaneSize::new(size.1 as u16, size.0 as u16, 1)?;
+        let plane = PlaneSize::new(header.width as u16, header.height as u16, 1)?;
 
         let mut video = Video::new(plane, Continuous, None)?
             .chunk_rows(1)
@@ -108,15 +131,140 @@ impl<W: Write + 'static> Prophesee<W> {
         let prophesee_source = Prophesee {
             video,
             input_reader,
+            data_start,
+            decoder: EventDecoder::new(header.format),
             num_dvs_events: 0,
             running_t: 0,
             dvs_last_timestamps,
             dvs_last_ln_val,
-            camera_theta: 0.05, // A fixed assumption
+            camera_theta_pos: DEFAULT_CAMERA_THETA,
+            camera_theta_neg: DEFAULT_CAMERA_THETA,
         };
 
         Ok(prophesee_source)
     }
+
+    /// Get the encoding of the input events
+    pub fn format(&self) -> PropheseeFormat {
+        self.decoder.format
+    }
+
+    /// Set the contrast thresholds of the camera: the change in log intensity signalled by a
+    /// positive and a negative event, respectively
+    pub fn camera_theta(mut self, theta_pos: f64, theta_neg: f64) -> Result<Self, SourceError> {
+        if !(theta_pos > 0.0 && theta_neg > 0.0) {
+            return Err(SourceError::BadParams(
+                "camera contrast thresholds must be positive".to_string(),
+            ));
+        }
+        self.camera_theta_pos = theta_pos;
+        self.camera_theta_neg = theta_neg;
+        Ok(self)
+    }
+
+    /// Get the contrast thresholds of the camera, for positive and negative events
+    pub fn get_camera_theta(&self) -> (f64, f64) {
+        (self.camera_theta_pos, self.camera_theta_neg)
+    }
+
+    /// Estimate the contrast thresholds of the camera from the first `max_events` events of the
+    /// input, then rewind the input. The ratio of the thresholds is chosen so that each polarity
+    /// contributes the same total change in log intensity, and their scale is chosen so that
+    /// nearly all pixels stay within the intensity range.
+    pub fn estimate_camera_theta(mut self, max_events: usize) -> Result<Self, SourceError> {
+        let mut decoder = EventDecoder::new(self.decoder.format);
+        let mut dvs_events = Vec::new();
+        while dvs_events.len() < max_events {
+            match decoder.next_event(&mut self.input_reader) {
+                Ok(dvs_event) => dvs_events.push(dvs_event),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.input_reader.seek(SeekFrom::Start(self.data_start))?;
+
+        let (theta_pos, theta_neg) = estimate_theta(&dvs_events, self.video.state.plane)
+            .ok_or_else(|| {
+                SourceError::BadParams(
+                    "not enough events of each polarity to estimate the contrast thresholds"
+                        .to_string(),
+                )
+            })?;
+        self.camera_theta(theta_pos, theta_neg)
+    }
+
+    /// Set the intensities that each pixel starts at, on the 8-bit scale, such as a paired APS
+    /// frame. The frame must have the shape `(height, width, 1)`.
+    pub fn initial_intensities(mut self, frame: &Array3<u8>) -> Result<Self, SourceError> {
+        let plane = self.video.state.plane;
+        if frame.dim() != (plane.h_usize(), plane.w_usize(), 1) {
+            return Err(SourceError::BadParams(format!(
+                "initial frame shape {:?} does not match the sensor size {}x{}",
+                frame.shape(),
+                plane.w(),
+                plane.h()
+            )));
+        }
+
+        self.dvs_last_ln_val = frame.mapv(|v| (f64::from(v) / 255.0).ln_1p());
+        self.video.state.running_intensities = frame.clone();
+        self.video.display_frame_features = frame.clone();
+        Ok(self)
+    }
+
+    /// Set the intensities that each pixel starts at from a grayscale image file (e.g., a PNG)
+    /// with the same size as the sensor
+    pub fn initial_intensities_from_image<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<Self, SourceError> {
+        let image = ndarray_image::open_gray_image(path)
+            .map_err(|e| SourceError::BadParams(format!("could not open initial frame: {e}")))?;
+        let frame = image.insert_axis(Axis(2));
+        self.initial_intensities(&frame)
+    }
+}
+
+/// Estimate the contrast thresholds (for positive and negative events) from a sample of events.
+/// Returns `None` if the sample has no events of either polarity within the plane.
+fn estimate_theta(dvs_events: &[DvsEvent], plane: PlaneSize) -> Option<(f64, f64)> {
+    let num_pos = dvs_events.iter().filter(|e| e.p == 1).count();
+    let num_neg = dvs_events.len() - num_pos;
+    if num_pos == 0 || num_neg == 0 {
+        return None;
+    }
+
+    // Each negative event is worth this many positive events
+    let ratio = num_pos as f64 / num_neg as f64;
+
+    // Walk each pixel's log intensity in units of the positive threshold, tracking its extent
+    let mut walks: Array3<(f64, f64, f64)> =
+        Array3::from_elem((plane.h_usize(), plane.w_usize(), 1), (0.0, 0.0, 0.0));
+    for dvs_event in dvs_events {
+        let Some((current, min, max)) =
+            walks.get_mut([dvs_event.y as usize, dvs_event.x as usize, 0])
+        else {
+            continue;
+        };
+        *current += if dvs_event.p == 1 { 1.0 } else { -ratio };
+        *min = min.min(*current);
+        *max = max.max(*current);
+    }
+
+    let mut excursions: Vec<f64> = walks
+        .iter()
+        .map(|(_, min, max)| max - min)
+        .filter(|excursion| *excursion > 0.0)
+        .collect();
+    if excursions.is_empty() {
+        return None;
+    }
+    excursions.sort_by(f64::total_cmp);
+    let excursion =
+        excursions[((excursions.len() - 1) as f64 * THETA_ESTIMATE_PERCENTILE).round() as usize];
+
+    let theta_pos = LN_INTENSITY_RANGE / excursion;
+    Some((theta_pos, theta_pos * ratio))
 }
 
 impl<W: Write + 'static + std::marker::Send> Source<W> for Prophesee<W> {
@@ -156,7 +304,7 @@ impl<W: Write + 'static + std::marker::Send> Source<W> for Prophesee<W> {
         loop {
             // TODO: integrate to fill in the rest of time once the eof is reached
 
-            dvs_event = match decode_event(&mut self.input_reader) {
+            dvs_event = match self.decoder.next_event(&mut self.input_reader) {
                 Ok(dvs_event) => {
                     if dvs_event.t > self.running_t {
                         self.running_t = dvs_event.t;
@@ -185,6 +333,10 @@ impl<W: Write + 'static + std::marker::Send> Source<W> for Prophesee<W> {
             let p = dvs_event.p as usize;
             let t = dvs_event.t;
 
+            if y >= self.video.state.plane.h_usize() || x >= self.video.state.plane.w_usize() {
+                continue;
+            }
+
             // Get the last timestamp for this pixel
             let last_t = self.dvs_last_timestamps[[y, x, 0]];
 
@@ -223,8 +375,8 @@ impl<W: Write + 'static + std::marker::Send> Source<W> for Prophesee<W> {
 
             // Get the new ln intensity
             let mut new_ln_val = match p {
-                0 => last_ln_val - self.camera_theta,
-                1 => last_ln_val + self.camera_theta,
+                0 => last_ln_val - self.camera_theta_neg,
+                1 => last_ln_val + self.camera_theta_pos,
                 _ => panic!("Invalid polarity"),
             };
 
@@ -364,75 +516,280 @@ fn end_events<W: Write + 'static + std::marker::Send>(prophesee: &mut Prophesee<
     }
 }
 
-fn parse_header(file: &mut BufReader<File>) -> io::Result<(u64, u8, u8, (u32, u32))> {
-    file.seek(SeekFrom::Start(0))?; // Seek to the beginning of the file
-    let mut bod = 0;
-    let mut end_of_header = false;
-    let mut num_comment_line = 0;
-    let mut size = [None, None];
-
-    // Parse header
-    while !end_of_header {
-        bod = file.seek(SeekFrom::Current(0))?; // Get the current position
-        let mut line = Vec::new(); // Change to Vec<u8>
-        file.read_until(b'\n', &mut line)?; // Read until newline as binary data
-        if line.is_empty() || line[0] != b'%' {
-            end_of_header = true;
-        } else {
-            let mut words: Vec<&[u8]> = line.split(|&x| x == b' ' || x == b'\t').collect(); // Use &[u8] instead of &str
-
-            if words.len() > 1 {
-                match words[1] {
-                    b"Height" => {
-                        size[0] = line_to_hw(words);
-                    }
-                    b"Width" => {
-                        size[1] = line_to_hw(words);
+/// The encoding of the CD events in a Prophesee recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropheseeFormat {
+    /// A `.dat` file of 8-byte records (a 32-bit timestamp and a packed address)
+    Dat,
+
+    /// A `.raw` file in the EVT 2.0 encoding (32-bit words)
+    Evt2,
+
+    /// A `.raw` file in the EVT 3.0 encoding (16-bit words, with vectorized events)
+    Evt3,
+}
+
+impl FromStr for PropheseeFormat {
+    type Err = SourceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "EVT2" | "2.0" => Ok(PropheseeFormat::Evt2),
+            "EVT3" | "3.0" => Ok(PropheseeFormat::Evt3),
+            other => Err(SourceError::BadParams(format!(
+                "unsupported Prophesee event format: {other}"
+            ))),
+        }
+    }
+}
+
+/// The fields of a Prophesee file header that the transcoder needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PropheseeHeader {
+    format: PropheseeFormat,
+    width: u32,
+    height: u32,
+}
+
+/// Parse the `%`-prefixed header of a Prophesee recording, leaving `file` positioned at the
+/// start of the event data. Without a `format` or `evt` line, the file is assumed to be a `.dat`
+/// file, which carries a 2-byte event type and size after its header.
+fn parse_header<R: BufRead + Seek>(file: &mut R) -> Result<PropheseeHeader, SourceError> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut format = None;
+    let mut width = None;
+    let mut height = None;
+
+    loop {
+        let bod = file.stream_position()?;
+        let mut line = Vec::new();
+        file.read_until(b'\n', &mut line)?;
+        if line.first() != Some(&b'%') {
+            file.seek(SeekFrom::Start(bod))?;
+            break;
+        }
+
+        // The header is ASCII, but be lenient with anything else in the comments
+        let line = String::from_utf8_lossy(&line[1..]);
+        let mut words = line.split_whitespace();
+        let (Some(key), value) = (words.next(), words.next()) else {
+            continue;
+        };
+        match (key, value) {
+            ("end", _) => break,
+            // e.g., `% format EVT3;height=720;width=1280`
+            ("format", Some(value)) => {
+                let mut fields = value.split(';');
+                format = fields.next().map(PropheseeFormat::from_str).transpose()?;
+                for field in fields {
+                    match field.split_once('=') {
+                        Some(("height", h)) => height = h.parse().ok(),
+                        Some(("width", w)) => width = w.parse().ok(),
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
-            num_comment_line += 1;
+            // e.g., `% evt 3.0`
+            ("evt", Some(value)) => format = Some(PropheseeFormat::from_str(value)?),
+            // e.g., `% geometry 1280x720`
+            ("geometry", Some(value)) => {
+                if let Some((w, h)) = value.split_once('x') {
+                    width = w.parse().ok();
+                    height = h.parse().ok();
+                }
+            }
+            ("Width", Some(value)) => width = value.parse().ok(),
+            ("Height", Some(value)) => height = value.parse().ok(),
+            _ => {}
         }
     }
 
-    // Parse data
-    file.seek(SeekFrom::Start(bod))?; // Seek back to the position after the header
-    let (ev_type, ev_size) = if num_comment_line > 0 {
-        // Read event type and size
-        let mut buf = [0; 2]; // Adjust the buffer size based on your data size
-        file.read_exact(&mut buf)?;
-        let ev_type = buf[0];
-        let ev_size = buf[1];
-
-        (ev_type, ev_size)
-    } else {
-        (0, 0) // Placeholder values, replace with actual logic
+    let format = match format {
+        Some(format) => format,
+        None => {
+            // Read the event type and size of a .dat file
+            let mut buf = [0; 2];
+            file.read_exact(&mut buf)?;
+            if buf[1] != 8 {
+                return Err(SourceError::BadParams(format!(
+                    "unsupported Prophesee .dat event size: {} bytes",
+                    buf[1]
+                )));
+            }
+            PropheseeFormat::Dat
+        }
     };
-    bod = file.seek(SeekFrom::Current(0))?;
-    Ok((
-        bod,
-        ev_type,
-        ev_size,
-        (size[0].unwrap_or(70), size[1].unwrap_or(100)),
-    ))
+
+    match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok(PropheseeHeader {
+            format,
+            width,
+            height,
+        }),
+        _ => Err(SourceError::BadParams(
+            "Prophesee header does not specify the sensor geometry".to_string(),
+        )),
+    }
+}
+
+/// Decodes CD events from the body of a Prophesee recording, one at a time
+#[derive(Debug)]
+struct EventDecoder {
+    format: PropheseeFormat,
+
+    /// Events decoded from a vectorized word, but not yet returned
+    pending: VecDeque<DvsEvent>,
+
+    /// Whether a time-high event has been seen yet. EVT events before the first one have no
+    /// time base, so they are dropped.
+    time_base_seen: bool,
+    time_high: u64,
+    time_low: u64,
+
+    /// The number of times the 24-bit EVT 3.0 timestamp has wrapped around
+    time_wraps: u64,
+
+    evt3_y: u16,
+    evt3_base_x: u16,
+    evt3_polarity: u8,
 }
 
-fn line_to_hw(words: Vec<&[u8]>) -> Option<u32> {
-    let mut word = words.get(2).unwrap();
-    let mut new_word = *word;
-    if *word.last().unwrap() == '\n' as u8 {
-        // Remove the trailing newline
-        new_word = &word[..word.len() - 1];
+impl EventDecoder {
+    fn new(format: PropheseeFormat) -> Self {
+        Self {
+            format,
+            pending: VecDeque::new(),
+            time_base_seen: false,
+            time_high: 0,
+            time_low: 0,
+            time_wraps: 0,
+            evt3_y: 0,
+            evt3_base_x: 0,
+            evt3_polarity: 0,
+        }
+    }
+
+    /// Read words until the next CD event is decoded
+    fn next_event<R: Read>(&mut self, reader: &mut R) -> io::Result<DvsEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            match self.format {
+                PropheseeFormat::Dat => return decode_dat_event(reader),
+                PropheseeFormat::Evt2 => {
+                    let mut buf = [0; 4];
+                    reader.read_exact(&mut buf)?;
+                    self.decode_evt2_word(u32::from_le_bytes(buf))?;
+                }
+                PropheseeFormat::Evt3 => {
+                    let mut buf = [0; 2];
+                    reader.read_exact(&mut buf)?;
+                    self.decode_evt3_word(u16::from_le_bytes(buf))?;
+                }
+            }
+        }
+    }
+
+    fn decode_evt2_word(&mut self, word: u32) -> io::Result<()> {
+        match word >> 28 {
+            // CD_OFF, CD_ON
+            p @ (0x0 | 0x1) => {
+                if self.time_base_seen {
+                    let t = (self.time_high << 6) | u64::from((word >> 22) & 0x3F);
+                    self.pending.push_back(DvsEvent {
+                        t: event_timestamp(t)?,
+                        x: ((word >> 11) & 0x7FF) as u16,
+                        y: (word & 0x7FF) as u16,
+                        p: p as u8,
+                    });
+                }
+            }
+            // EVT_TIME_HIGH
+            0x8 => {
+                self.time_high = u64::from(word & 0x0FFF_FFFF);
+                self.time_base_seen = true;
+            }
+            // Triggers, monitoring, and continuations carry no CD events
+            _ => {}
+        }
+        Ok(())
     }
-    std::str::from_utf8(new_word)
-        .ok()
-        .and_then(|s| s.parse().ok())
+
+    fn decode_evt3_word(&mut self, word: u16) -> io::Result<()> {
+        let payload = word & 0x0FFF;
+        match word >> 12 {
+            // EVT_ADDR_Y
+            0x0 => self.evt3_y = payload & 0x7FF,
+            // EVT_ADDR_X
+            0x2 => {
+                self.evt3_polarity = (payload >> 11) as u8;
+                self.push_evt3_event(payload & 0x7FF)?;
+            }
+            // VECT_BASE_X
+            0x3 => {
+                self.evt3_polarity = (payload >> 11) as u8;
+                self.evt3_base_x = payload & 0x7FF;
+            }
+            // VECT_12
+            0x4 => self.push_evt3_vector(payload, 12)?,
+            // VECT_8
+            0x5 => self.push_evt3_vector(payload & 0xFF, 8)?,
+            // EVT_TIME_LOW
+            0x6 => self.time_low = u64::from(payload),
+            // EVT_TIME_HIGH
+            0x8 => {
+                let time_high = u64::from(payload);
+                if self.time_base_seen && time_high < self.time_high {
+                    self.time_wraps += 1;
+                }
+                self.time_high = time_high;
+                self.time_base_seen = true;
+            }
+            // Triggers, monitoring, and continuations carry no CD events
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn push_evt3_vector(&mut self, mask: u16, len: u16) -> io::Result<()> {
+        for i in 0..len {
+            if mask & (1 << i) != 0 {
+                self.push_evt3_event(self.evt3_base_x + i)?;
+            }
+        }
+        self.evt3_base_x += len;
+        Ok(())
+    }
+
+    fn push_evt3_event(&mut self, x: u16) -> io::Result<()> {
+        if self.time_base_seen {
+            let t = (self.time_wraps << 24) | (self.time_high << 12) | self.time_low;
+            self.pending.push_back(DvsEvent {
+                t: event_timestamp(t)?,
+                x,
+                y: self.evt3_y,
+                p: self.evt3_polarity,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Narrow a decoded microsecond timestamp to the 32 bits the transcoder tracks. Fails for
+/// recordings longer than about 71.6 minutes, rather than wrapping around to 0.
+fn event_timestamp(t: u64) -> io::Result<u32> {
+    u32::try_from(t).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("event timestamp {t} µs is too large; recordings longer than about 71 minutes are not supported"),
+        )
+    })
 }
 
-fn decode_event(reader: &mut BufReader<File>) -> io::Result<(DvsEvent)> {
+fn decode_dat_event<R: Read>(reader: &mut R) -> io::Result<DvsEvent> {
     // Read one record
-    let mut buffer = [0; 8]; // Adjust this size to match your record size
+    let mut buffer = [0; 8];
     reader.read_exact(&mut buffer)?;
 
     // Interpret the bytes as 't' and 'data'
@@ -543,3 +900,149 @@ impl<W: Write + 'static> VideoBuilder<W> for Prophesee<W> {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_evt3_header() {
+        let mut data =
+            b"% camera_integrator_name Prophesee\n% format EVT3;height=720;width=1280\n% end\n"
+                .to_vec();
+        let data_start = data.len() as u64;
+        data.extend_from_slice(&[0x25, 0x80]);
+        let mut reader = Cursor::new(data);
+        let header = parse_header(&mut reader).unwrap();
+        assert_eq!(
+            header,
+            PropheseeHeader {
+                format: PropheseeFormat::Evt3,
+                width: 1280,
+                height: 720
+            }
+        );
+        // The first data byte happens to be '%', but the header ended at `% end`
+        assert_eq!(reader.position(), data_start);
+    }
+
+    #[test]
+    fn parse_legacy_headers() {
+        let mut reader = Cursor::new(b"% evt 2.0\n% geometry 640x480\n".to_vec());
+        let header = parse_header(&mut reader).unwrap();
+        assert_eq!(header.format, PropheseeFormat::Evt2);
+        assert_eq!((header.width, header.height), (640, 480));
+
+        let mut data = b"% Height 480\n% Width 640\n".to_vec();
+        data.extend_from_slice(&[0x0C, 8]);
+        let header = parse_header(&mut Cursor::new(data)).unwrap();
+        assert_eq!(header.format, PropheseeFormat::Dat);
+
+        let mut reader = Cursor::new(b"% evt 2.1\n% geometry 640x480\n".to_vec());
+        assert!(matches!(
+            parse_header(&mut reader),
+            Err(SourceError::BadParams(_))
+        ));
+
+        let mut reader = Cursor::new(b"% evt 3.0\n".to_vec());
+        assert!(matches!(
+            parse_header(&mut reader),
+            Err(SourceError::BadParams(_))
+        ));
+    }
+
+    #[test]
+    fn decode_evt2() {
+        let words: [u32; 3] = [
+            0x1000_0000 | (3 << 22) | (10 << 11) | 20, // CD_ON before any time base
+            0x8000_0002,                               // EVT_TIME_HIGH
+            (5 << 22) | (10 << 11) | 20,               // CD_OFF
+        ];
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let mut reader = Cursor::new(bytes);
+        let mut decoder = EventDecoder::new(PropheseeFormat::Evt2);
+        assert_eq!(
+            decoder.next_event(&mut reader).unwrap(),
+            DvsEvent {
+                t: (2 << 6) | 5,
+                x: 10,
+                y: 20,
+                p: 0
+            }
+        );
+        assert!(decoder.next_event(&mut reader).is_err());
+    }
+
+    #[test]
+    fn decode_evt3() {
+        let words: [u16; 7] = [
+            0x8001,               // EVT_TIME_HIGH
+            0x6002,               // EVT_TIME_LOW
+            0x0007,               // EVT_ADDR_Y
+            0x2800 | 100,         // EVT_ADDR_X, positive polarity
+            0x3000 | 200,         // VECT_BASE_X, negative polarity
+            0x4000 | 0b1000_0001, // VECT_12
+            0x5001,               // VECT_8
+        ];
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let mut reader = Cursor::new(bytes);
+        let mut decoder = EventDecoder::new(PropheseeFormat::Evt3);
+        let t = (1 << 12) | 2;
+        let expected = [(100, 1), (200, 0), (207, 0), (212, 0)];
+        for (x, p) in expected {
+            assert_eq!(
+                decoder.next_event(&mut reader).unwrap(),
+                DvsEvent { t, x, y: 7, p }
+            );
+        }
+        assert!(decoder.next_event(&mut reader).is_err());
+    }
+
+    #[test]
+    fn evt3_time_wraps() {
+        let mut decoder = EventDecoder::new(PropheseeFormat::Evt3);
+        decoder.decode_evt3_word(0x8FFF).unwrap();
+        decoder.decode_evt3_word(0x8000).unwrap();
+        decoder.decode_evt3_word(0x2000).unwrap();
+        assert_eq!(decoder.pending.pop_front().unwrap().t, 1 << 24);
+    }
+
+    #[test]
+    fn timestamp_overflow() {
+        let mut decoder = EventDecoder::new(PropheseeFormat::Evt2);
+        decoder.decode_evt2_word(0x8400_0000).unwrap();
+        let err = decoder.decode_evt2_word(10 << 11).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut decoder = EventDecoder::new(PropheseeFormat::Evt3);
+        decoder.decode_evt3_word(0x8FFF).unwrap();
+        decoder.time_wraps = 255;
+        decoder.decode_evt3_word(0x6FFF).unwrap();
+        decoder.decode_evt3_word(0x2000).unwrap();
+        assert_eq!(decoder.pending.pop_front().unwrap().t, u32::MAX);
+        decoder.time_wraps = 256;
+        assert!(decoder.decode_evt3_word(0x2000).is_err());
+    }
+
+    #[test]
+    fn estimate_theta_from_walks() {
+        let plane = PlaneSize::new(2, 1, 1).unwrap();
+        let event = |x, p| DvsEvent { t: 0, x, y: 0, p };
+
+        // Pixel 0 rises by 4 positive events, then falls by 2 negative events. Pixel 1 only
+        // falls by 2 negative events.
+        let mut dvs_events = vec![event(0, 1); 4];
+        dvs_events.extend(vec![event(0, 0); 2]);
+        dvs_events.extend(vec![event(1, 0); 2]);
+        let (theta_pos, theta_neg) = estimate_theta(&dvs_events, plane).unwrap();
+        assert_eq!(theta_neg, theta_pos);
+        assert_eq!(theta_pos, LN_INTENSITY_RANGE / 4.0);
+
+        assert!(estimate_theta(&dvs_events[..4], plane).is_none());
+
+        // Events outside the plane are skipped
+        let outside = vec![event(5, 1), event(5, 0)];
+        assert!(estimate_theta(&outside, plane).is_none());
+    }
+}
@@ -121,6 +121,25 @@ pub enum SourceError {
     IoError(#[from] std::io::Error),
 }
 
+impl SourceError {
+    /// Whether the error marks the end of the source's input, rather than a failure. Sources
+    /// report the end as an empty buffer, an exhausted video decoder, or an input file read to
+    /// its end.
+    #[must_use]
+    pub fn is_end_of_input(&self) -> bool {
+        match self {
+            SourceError::BufferEmpty => true,
+            SourceError::VideoError(e) => matches!(
+                e,
+                video_rs_adder_dep::Error::ReadExhausted
+                    | video_rs_adder_dep::Error::DecodeExhausted
+            ),
+            SourceError::IoError(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+            _ => false,
+        }
+    }
+}
+
 #[cfg(feature = "open-cv")]
 impl From<opencv::Error> for SourceError {
     fn from(value: opencv::Error) -> Self {
@@ -668,15 +687,51 @@ impl<W: Write + 'static> Video<W> {
             self.set_initial_d(&matrix);
         }
 
+        // let matrix_f32 = convert_u8_to_f32_simd(&matrix.into_raw_vec());
+        let matrix = matrix.mapv(f32::from);
+
+        self.integrate_intensities(matrix, time_spanned, 1.0, view_interval)
+    }
+
+    /// Integrate a frame whose intensities are normalized to `ref_time` ticks, but which
+    /// actually spans `time_spanned` ticks (e.g., a frame of a variable-frame-rate video).
+    #[allow(clippy::needless_pass_by_value)]
+    pub(crate) fn integrate_matrix_scaled(
+        &mut self,
+        matrix: Frame,
+        time_spanned: f32,
+        view_interval: u32,
+    ) -> Result<Vec<Vec<Event>>, SourceError> {
+        if self.state.in_interval_count == 0 {
+            self.set_initial_d(&matrix);
+        }
+
+        let intensity_scale = time_spanned / self.state.params.ref_time as f32;
+        self.integrate_intensities(
+            matrix.mapv(f32::from),
+            time_spanned,
+            intensity_scale,
+            view_interval,
+        )
+    }
+
+    /// Integrate a matrix of intensities (on the 8-bit scale) which spans `time_spanned` ticks.
+    /// Each intensity is multiplied by `intensity_scale` before integration, so that a source
+    /// can integrate values normalized to `ref_time` over a different span of time.
+    #[allow(clippy::needless_pass_by_value)]
+    pub(crate) fn integrate_intensities(
+        &mut self,
+        matrix: Array3<f32>,
+        time_spanned: f32,
+        intensity_scale: f32,
+        view_interval: u32,
+    ) -> Result<Vec<Vec<Event>>, SourceError> {
         let parameters = *self.encoder.options.crf.get_parameters();
 
         self.state.in_interval_count += 1;
 
         self.state.show_live = self.state.in_interval_count % view_interval == 0;
 
-        // let matrix_f32 = convert_u8_to_f32_simd(&matrix.into_raw_vec());
-        let matrix = matrix.mapv(f32::from);
-
         // TODO: When there's full support for various bit-depth sources, modify this accordingly
         let practical_d_max = fast_math::log2_raw(
             255.0 * (self.state.params.delta_t_max / self.state.params.ref_time) as f32,
@@ -716,7 +771,7 @@ impl<W: Write + 'static> Video<W> {
                         px,
                         base_val,
                         *input as u8,
-                        *input, // In this case, frame val is the same as intensity to integrate
+                        *input * intensity_scale, // Frame val is the same as intensity to integrate, up to the scale
                         time_spanned,
                         &mut buffer,
                         params,
@@ -1325,3 +1380,35 @@ pub trait Source<W: Write> {
 //
 //     result
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adder_codec_core::Mode::FramePerfect;
+
+    /// Integrate a single-pixel frame spanning `ref_time * time_multiple` ticks, and get the event
+    /// it's ready to fire
+    fn integrate_scaled(time_multiple: f32) -> (D, f32) {
+        let plane = PlaneSize::new(1, 1, 1).unwrap();
+        let mut video: Video<Vec<u8>> = Video::new(plane, FramePerfect, None).unwrap();
+        let time_spanned = video.get_ref_time() as f32 * time_multiple;
+        video
+            .integrate_matrix_scaled(Frame::from_elem((1, 1, 1), 100), time_spanned, 1)
+            .unwrap();
+        let event = video.event_pixel_trees[[0, 0, 0]].arena[0]
+            .best_event
+            .unwrap();
+        (event.d, event.delta_t)
+    }
+
+    #[test]
+    fn integrate_matrix_scaled_intensity() {
+        let (d, delta_t) = integrate_scaled(1.0);
+        let (double_d, double_delta_t) = integrate_scaled(2.0);
+
+        // Twice the intensity is integrated over twice the time, so the pixel fires with the same
+        // intensity per tick
+        assert_eq!(double_d, d + 1);
+        assert!((double_delta_t - 2.0 * delta_t).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,751 @@
+use crate::transcoder::source::video::SourceError;
+use crate::transcoder::source::video::Video;
+use crate::transcoder::source::video::{Source, VideoBuilder};
+use adder_codec_core::Mode::FramePerfect;
+use adder_codec_core::{DeltaT, Event, PixelMultiMode, PlaneSize, SourceCamera, TimeMode};
+
+use crate::utils::viz::ShowFeatureMode;
+use adder_codec_core::codec::{EncoderOptions, EncoderType};
+
+use rayon::ThreadPool;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+#[cfg(feature = "feature-logging")]
+use chrono::Local;
+use video_rs_adder_dep::Frame;
+
+/// The magic string at the start of every YUV4MPEG2 file
+const Y4M_MAGIC: &str = "YUV4MPEG2";
+
+/// The maximum length of a Y4M stream or frame header line we're willing to read
+const Y4M_MAX_HEADER_LEN: usize = 1024;
+
+/// The planar pixel layout of a YUV or grayscale source. Only 8-bit samples are supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YuvPixelFormat {
+    /// Luma plane only
+    Gray,
+
+    /// Luma plane, followed by chroma planes subsampled 2x horizontally and vertically (I420)
+    Yuv420,
+
+    /// Luma plane, followed by chroma planes subsampled 2x horizontally
+    Yuv422,
+
+    /// Luma plane, followed by full-resolution chroma planes
+    Yuv444,
+}
+
+impl YuvPixelFormat {
+    /// Parse the colorspace tag of a Y4M stream header (the part after the `C`)
+    fn from_y4m_tag(tag: &str) -> Result<Self, SourceError> {
+        match tag {
+            "420" | "420jpeg" | "420paldv" | "420mpeg2" => Ok(YuvPixelFormat::Yuv420),
+            "422" => Ok(YuvPixelFormat::Yuv422),
+            "444" => Ok(YuvPixelFormat::Yuv444),
+            "mono" => Ok(YuvPixelFormat::Gray),
+            _ => Err(SourceError::BadParams(format!(
+                "unsupported Y4M colorspace `C{tag}`. Only 8-bit 420, 422, 444, and mono are supported"
+            ))),
+        }
+    }
+
+    /// The (horizontal, vertical) chroma subsampling shifts, or `None` if there is no chroma
+    fn chroma_shifts(self) -> Option<(usize, usize)> {
+        match self {
+            YuvPixelFormat::Gray => None,
+            YuvPixelFormat::Yuv420 => Some((1, 1)),
+            YuvPixelFormat::Yuv422 => Some((1, 0)),
+            YuvPixelFormat::Yuv444 => Some((0, 0)),
+        }
+    }
+
+    /// The dimensions of a single chroma plane, or `None` if there is no chroma
+    fn chroma_dims(self, width: usize, height: usize) -> Option<(usize, usize)> {
+        self.chroma_shifts().map(|(shift_x, shift_y)| {
+            (
+                (width + (1 << shift_x) - 1) >> shift_x,
+                (height + (1 << shift_y) - 1) >> shift_y,
+            )
+        })
+    }
+
+    /// The number of bytes in one frame of this format
+    pub fn frame_len(self, width: usize, height: usize) -> usize {
+        match self.chroma_dims(width, height) {
+            None => width * height,
+            Some((chroma_w, chroma_h)) => width * height + 2 * chroma_w * chroma_h,
+        }
+    }
+}
+
+/// The geometry and timing of a headerless raw planar YUV or grayscale file
+#[derive(Debug, Clone, Copy)]
+pub struct YuvLayout {
+    /// Width of the source frames, in pixels
+    pub width: u16,
+
+    /// Height of the source frames, in pixels
+    pub height: u16,
+
+    /// Planar layout of each frame
+    pub pixel_format: YuvPixelFormat,
+
+    /// Frame rate of the source
+    pub fps: f32,
+
+    /// Whether samples use the full 0-255 range. If not, they're limited ("studio") range, with
+    /// luma in 16-235 and chroma in 16-240, and are expanded to full range on conversion.
+    pub full_range: bool,
+}
+
+/// The container of a [`Yuv`] source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum YuvContainer {
+    /// YUV4MPEG2 stream. Each frame is preceded by a `FRAME` header line.
+    Y4m,
+
+    /// Headerless frames stored back to back
+    Raw,
+}
+
+/// Attributes of a Y4M or raw YUV video -> ADΔER transcode. Frames are read and converted
+/// directly, without ffmpeg.
+pub struct Yuv<W: Write + 'static> {
+    reader: BufReader<File>,
+    container: YuvContainer,
+
+    /// Byte offset of the first frame in the file
+    data_start: u64,
+
+    /// Geometry and timing of the source frames
+    pub layout: YuvLayout,
+
+    /// Holder for the bytes of the most recently read source frame
+    frame_buffer: Vec<u8>,
+    pub(crate) input_frame: Frame,
+
+    /// Index of the first frame to be read from the input video
+    pub frame_idx_start: u32,
+
+    /// FPS of the input video. Set automatically from the Y4M header or the [`YuvLayout`]
+    pub source_fps: f32,
+
+    /// Scale of the input video. Input frames are resized to this scale before transcoding.
+    pub scale: f64,
+
+    /// Whether the input video is color
+    color_input: bool,
+
+    pub(crate) video: Video<W>,
+}
+unsafe impl<W: Write> Sync for Yuv<W> {}
+
+impl<W: Write + 'static> Yuv<W> {
+    /// Create a new `Yuv` source from a YUV4MPEG2 (`.y4m`) file
+    pub fn new(
+        input_filename: String,
+        color_input: bool,
+        scale: f64,
+    ) -> Result<Yuv<W>, SourceError> {
+        let mut reader = BufReader::new(File::open(PathBuf::from(input_filename))?);
+        let layout = parse_y4m_header(&mut reader)?;
+        let data_start = reader.stream_position()?;
+        Self::from_reader(
+            reader,
+            YuvContainer::Y4m,
+            data_start,
+            layout,
+            color_input,
+            scale,
+        )
+    }
+
+    /// Create a new `Yuv` source from a headerless raw planar YUV or grayscale file
+    pub fn new_raw(
+        input_filename: String,
+        layout: YuvLayout,
+        color_input: bool,
+        scale: f64,
+    ) -> Result<Yuv<W>, SourceError> {
+        let reader = BufReader::new(File::open(PathBuf::from(input_filename))?);
+        Self::from_reader(reader, YuvContainer::Raw, 0, layout, color_input, scale)
+    }
+
+    fn from_reader(
+        reader: BufReader<File>,
+        container: YuvContainer,
+        data_start: u64,
+        layout: YuvLayout,
+        color_input: bool,
+        scale: f64,
+    ) -> Result<Yuv<W>, SourceError> {
+        if layout.fps <= 0.0 {
+            return Err(SourceError::BadParams(
+                "source frame rate must be positive".to_string(),
+            ));
+        }
+        let width = ((layout.width as f64) * scale) as u16;
+        let height = ((layout.height as f64) * scale) as u16;
+
+        let plane = PlaneSize::new(width, height, if color_input { 3 } else { 1 })?;
+
+        let video = Video::new(plane, FramePerfect, None)?;
+
+        Ok(Yuv {
+            reader,
+            container,
+            data_start,
+            layout,
+            frame_buffer: vec![
+                0;
+                layout
+                    .pixel_format
+                    .frame_len(layout.width as usize, layout.height as usize)
+            ],
+            input_frame: Frame::zeros((plane.h_usize(), plane.w_usize(), plane.c_usize())),
+            frame_idx_start: 0,
+            source_fps: layout.fps,
+            scale,
+            color_input,
+            video,
+        })
+    }
+
+    /// Set the start frame of the source
+    pub fn frame_start(mut self, frame_idx_start: u32) -> Result<Self, SourceError> {
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        for _ in 0..frame_idx_start {
+            match self.read_frame_bytes() {
+                Ok(()) => {}
+                Err(SourceError::BufferEmpty) => {
+                    return Err(SourceError::StartOutOfBounds(frame_idx_start))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.frame_idx_start = frame_idx_start;
+        Ok(self)
+    }
+
+    /// Automatically derive the ticks per second from the source FPS and `ref_time`
+    pub fn auto_time_parameters(
+        mut self,
+        ref_time: DeltaT,
+        delta_t_max: DeltaT,
+        time_mode: Option<TimeMode>,
+    ) -> Result<Self, SourceError> {
+        if delta_t_max % ref_time == 0 {
+            let tps = (ref_time as f32 * self.source_fps) as DeltaT;
+            self.video = self
+                .video
+                .time_parameters(tps, ref_time, delta_t_max, time_mode)?;
+        } else {
+            return Err(SourceError::BadParams(
+                "delta_t_max must be a multiple of ref_time".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Get the number of ticks each frame is said to span
+    pub fn get_ref_time(&self) -> u32 {
+        self.video.state.params.ref_time
+    }
+
+    /// Get the most recent source frame, after scaling and color conversion
+    pub fn get_last_input_frame(&self) -> &Frame {
+        &self.input_frame
+    }
+
+    /// Read the bytes of the next source frame into `frame_buffer`
+    fn read_frame_bytes(&mut self) -> Result<(), SourceError> {
+        if self.container == YuvContainer::Y4m {
+            let mut line = Vec::new();
+            let read = self
+                .reader
+                .by_ref()
+                .take(Y4M_MAX_HEADER_LEN as u64)
+                .read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Err(SourceError::BufferEmpty);
+            }
+            if !line.starts_with(b"FRAME") || line.last() != Some(&b'\n') {
+                return Err(SourceError::BadParams(
+                    "malformed Y4M frame header".to_string(),
+                ));
+            }
+        }
+
+        match self.reader.read_exact(&mut self.frame_buffer) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(SourceError::BufferEmpty),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<W: Write + 'static> Source<W> for Yuv<W> {
+    /// Read the next source frame, convert it to the plane's color format and scale, and integrate
+    /// it with `ref_time` (the number of ticks each frame is said to span)
+    fn consume(
+        &mut self,
+        view_interval: u32,
+        thread_pool: &ThreadPool,
+    ) -> Result<Vec<Vec<Event>>, SourceError> {
+        self.read_frame_bytes()?;
+        convert_yuv_frame(
+            &self.frame_buffer,
+            &self.layout,
+            self.color_input,
+            &mut self.input_frame,
+        );
+
+        thread_pool.install(|| {
+            self.video.integrate_matrix(
+                self.input_frame.clone(),
+                self.video.state.params.ref_time as f32,
+                view_interval,
+            )
+        })
+    }
+
+    fn crf(&mut self, crf: u8) {
+        self.video.update_crf(crf);
+    }
+
+    fn get_video_mut(&mut self) -> &mut Video<W> {
+        &mut self.video
+    }
+
+    fn get_video_ref(&self) -> &Video<W> {
+        &self.video
+    }
+
+    fn get_video(self) -> Video<W> {
+        self.video
+    }
+
+    fn get_input(&self) -> Option<&Frame> {
+        Some(self.get_last_input_frame())
+    }
+
+    fn get_running_input_bitrate(&self) -> f64 {
+        let video = self.get_video_ref();
+        video.get_tps() as f64 / video.get_ref_time() as f64
+            * video.state.plane.volume() as f64
+            * 8.0
+    }
+}
+
+/// Parse the stream header of a YUV4MPEG2 file, leaving the reader at the first frame header
+fn parse_y4m_header(reader: &mut BufReader<File>) -> Result<YuvLayout, SourceError> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(Y4M_MAX_HEADER_LEN as u64)
+        .read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(SourceError::BadParams(
+            "Y4M header is truncated or too long".to_string(),
+        ));
+    }
+    let line = std::str::from_utf8(&line)
+        .map_err(|_| SourceError::BadParams("Y4M header is not valid text".to_string()))?;
+
+    let mut words = line.trim_end().split(' ');
+    if words.next() != Some(Y4M_MAGIC) {
+        return Err(SourceError::BadParams("not a YUV4MPEG2 file".to_string()));
+    }
+
+    let mut width = None;
+    let mut height = None;
+    let mut fps = None;
+    let mut pixel_format = YuvPixelFormat::Yuv420;
+
+    // Y4M is limited range unless the header says otherwise
+    let mut full_range = false;
+    for word in words {
+        let (tag, value) = match word.char_indices().nth(1) {
+            Some((idx, _)) => word.split_at(idx),
+            None => continue,
+        };
+        match tag {
+            "W" => width = value.parse::<u16>().ok(),
+            "H" => height = value.parse::<u16>().ok(),
+            "F" => {
+                fps = value.split_once(':').and_then(|(num, den)| {
+                    match (num.parse::<f32>(), den.parse::<f32>()) {
+                        (Ok(num), Ok(den)) if den > 0.0 => Some(num / den),
+                        _ => None,
+                    }
+                })
+            }
+            "C" => pixel_format = YuvPixelFormat::from_y4m_tag(value)?,
+            "X" => {
+                if let Some(range) = value.strip_prefix("COLORRANGE=") {
+                    full_range = range.eq_ignore_ascii_case("FULL");
+                }
+            }
+            "I" if value != "p" && value != "?" => {
+                return Err(SourceError::BadParams(
+                    "interlaced Y4M sources are not supported".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    match (width, height, fps) {
+        (Some(width), Some(height), Some(fps)) => Ok(YuvLayout {
+            width,
+            height,
+            pixel_format,
+            fps,
+            full_range,
+        }),
+        _ => Err(SourceError::BadParams(
+            "Y4M header must specify the width, height, and frame rate".to_string(),
+        )),
+    }
+}
+
+/// Convert one planar YUV frame to a gray or RGB [`Frame`], resizing it (nearest neighbor) to the
+/// dimensions of `output`
+fn convert_yuv_frame(data: &[u8], layout: &YuvLayout, color: bool, output: &mut Frame) {
+    let (width, height) = (layout.width as usize, layout.height as usize);
+    let (out_height, out_width) = (output.shape()[0], output.shape()[1]);
+    let luma = &data[..width * height];
+
+    let chroma = layout
+        .pixel_format
+        .chroma_shifts()
+        .zip(layout.pixel_format.chroma_dims(width, height))
+        .map(|(shifts, (chroma_w, chroma_h))| {
+            let u_start = width * height;
+            let v_start = u_start + chroma_w * chroma_h;
+            (
+                shifts,
+                chroma_w,
+                &data[u_start..v_start],
+                &data[v_start..v_start + chroma_w * chroma_h],
+            )
+        });
+
+    for y in 0..out_height {
+        let src_y = y * height / out_height;
+        for x in 0..out_width {
+            let src_x = x * width / out_width;
+            let luma_val = luma[src_y * width + src_x];
+            if !color {
+                output[[y, x, 0]] = if layout.full_range {
+                    luma_val
+                } else {
+                    expand_luma(f32::from(luma_val)).round().clamp(0.0, 255.0) as u8
+                };
+                continue;
+            }
+
+            let (u, v) = match chroma {
+                None => (128.0, 128.0),
+                Some(((shift_x, shift_y), chroma_w, u_plane, v_plane)) => {
+                    let idx = (src_y >> shift_y) * chroma_w + (src_x >> shift_x);
+                    (f32::from(u_plane[idx]), f32::from(v_plane[idx]))
+                }
+            };
+            let (r, g, b) = if layout.full_range {
+                yuv_to_rgb(f32::from(luma_val), u, v)
+            } else {
+                yuv_to_rgb(
+                    expand_luma(f32::from(luma_val)),
+                    expand_chroma(u),
+                    expand_chroma(v),
+                )
+            };
+            output[[y, x, 0]] = r;
+            output[[y, x, 1]] = g;
+            output[[y, x, 2]] = b;
+        }
+    }
+}
+
+/// Expand a limited-range (16-235) luma sample to full range
+#[inline]
+fn expand_luma(y: f32) -> f32 {
+    (y - 16.0) * 255.0 / 219.0
+}
+
+/// Expand a limited-range (16-240) chroma sample to full range
+#[inline]
+fn expand_chroma(c: f32) -> f32 {
+    (c - 128.0) * 255.0 / 224.0 + 128.0
+}
+
+/// Full-range BT.601 YCbCr to RGB conversion
+#[inline]
+fn yuv_to_rgb(y: f32, u: f32, v: f32) -> (u8, u8, u8) {
+    let u = u - 128.0;
+    let v = v - 128.0;
+    (
+        (y + 1.402 * v).round().clamp(0.0, 255.0) as u8,
+        (y - 0.344_136 * u - 0.714_136 * v)
+            .round()
+            .clamp(0.0, 255.0) as u8,
+        (y + 1.772 * u).round().clamp(0.0, 255.0) as u8,
+    )
+}
+
+impl<W: Write + 'static> VideoBuilder<W> for Yuv<W> {
+    fn contrast_thresholds(mut self, c_thresh_pos: u8, _c_thresh_neg: u8) -> Self {
+        self.video = self.video.c_thresh_pos(c_thresh_pos);
+        self
+    }
+
+    fn crf(mut self, crf: u8) -> Self {
+        self.video.update_crf(crf);
+        self
+    }
+
+    fn quality_manual(
+        mut self,
+        c_thresh_baseline: u8,
+        c_thresh_max: u8,
+        delta_t_max_multiplier: u32,
+        c_increase_velocity: u8,
+        feature_c_radius_denom: f32,
+    ) -> Self {
+        self.video.update_quality_manual(
+            c_thresh_baseline,
+            c_thresh_max,
+            delta_t_max_multiplier,
+            c_increase_velocity,
+            feature_c_radius_denom,
+        );
+        self
+    }
+
+    fn c_thresh_pos(mut self, c_thresh_pos: u8) -> Self {
+        self.video = self.video.c_thresh_pos(c_thresh_pos);
+        self
+    }
+
+    fn c_thresh_neg(mut self, c_thresh_neg: u8) -> Self {
+        self.video = self.video.c_thresh_neg(c_thresh_neg);
+        self
+    }
+
+    fn chunk_rows(mut self, chunk_rows: usize) -> Self {
+        self.video = self.video.chunk_rows(chunk_rows);
+        self
+    }
+
+    fn time_parameters(
+        mut self,
+        tps: DeltaT,
+        ref_time: DeltaT,
+        delta_t_max: DeltaT,
+        time_mode: Option<TimeMode>,
+    ) -> Result<Self, SourceError> {
+        if delta_t_max % ref_time == 0 {
+            self.video = self
+                .video
+                .time_parameters(tps, ref_time, delta_t_max, time_mode)?;
+        } else {
+            eprintln!("delta_t_max must be a multiple of ref_time");
+        }
+        Ok(self)
+    }
+
+    fn write_out(
+        mut self,
+        source_camera: SourceCamera,
+        time_mode: TimeMode,
+        pixel_multi_mode: PixelMultiMode,
+        adu_interval: Option<usize>,
+        encoder_type: EncoderType,
+        encoder_options: EncoderOptions,
+        write: W,
+    ) -> Result<Box<Self>, SourceError> {
+        self.video = self.video.write_out(
+            Some(source_camera),
+            Some(time_mode),
+            Some(pixel_multi_mode),
+            adu_interval,
+            encoder_type,
+            encoder_options,
+            write,
+        )?;
+        Ok(Box::new(self))
+    }
+
+    fn show_display(mut self, show_display: bool) -> Self {
+        self.video = self.video.show_display(show_display);
+        self
+    }
+
+    fn detect_features(mut self, detect_features: bool, show_features: ShowFeatureMode) -> Self {
+        self.video = self.video.detect_features(detect_features, show_features);
+        self
+    }
+
+    #[cfg(feature = "feature-logging")]
+    fn log_path(mut self, name: String) -> Self {
+        let date_time = Local::now();
+        let formatted = format!("{}_{}.log", name, date_time.format("%d_%m_%Y_%H_%M_%S"));
+        let log_handle = std::fs::File::create(formatted).ok();
+        self.video.state.feature_log_handle = log_handle;
+
+        // Write the plane size to the log file
+        if let Some(handle) = &mut self.video.state.feature_log_handle {
+            writeln!(
+                handle,
+                "{}x{}x{}",
+                self.video.state.plane.w(),
+                self.video.state.plane.h(),
+                self.video.state.plane.c()
+            )
+            .unwrap();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+
+    /// Write `bytes` to a temporary file and return its path
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_yuv_frame_len() {
+        assert_eq!(YuvPixelFormat::Gray.frame_len(4, 4), 16);
+        assert_eq!(YuvPixelFormat::Yuv420.frame_len(4, 4), 24);
+        assert_eq!(YuvPixelFormat::Yuv420.frame_len(5, 5), 25 + 2 * 9);
+        assert_eq!(YuvPixelFormat::Yuv422.frame_len(4, 4), 32);
+        assert_eq!(YuvPixelFormat::Yuv444.frame_len(4, 4), 48);
+    }
+
+    #[test]
+    fn test_yuv_to_rgb() {
+        assert_eq!(yuv_to_rgb(128.0, 128.0, 128.0), (128, 128, 128));
+        assert_eq!(yuv_to_rgb(255.0, 128.0, 128.0), (255, 255, 255));
+        assert_eq!(yuv_to_rgb(0.0, 128.0, 128.0), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_convert_yuv_frame_scaled() {
+        let layout = YuvLayout {
+            width: 4,
+            height: 2,
+            pixel_format: YuvPixelFormat::Yuv420,
+            fps: 30.0,
+            full_range: true,
+        };
+        let mut data: Vec<u8> = (0..8).map(|i| i * 10).collect();
+        data.extend_from_slice(&[128; 4]);
+
+        let mut gray = Frame::zeros((1, 2, 1));
+        convert_yuv_frame(&data, &layout, false, &mut gray);
+        assert_eq!(gray.into_raw_vec(), vec![0, 20]);
+
+        let mut color = Frame::zeros((2, 4, 3));
+        convert_yuv_frame(&data, &layout, true, &mut color);
+        assert_eq!(color[[1, 3, 0]], 70);
+        assert_eq!(color[[1, 3, 1]], 70);
+        assert_eq!(color[[1, 3, 2]], 70);
+    }
+
+    #[test]
+    fn test_convert_yuv_frame_limited_range() {
+        let layout = YuvLayout {
+            width: 2,
+            height: 2,
+            pixel_format: YuvPixelFormat::Yuv420,
+            fps: 30.0,
+            full_range: false,
+        };
+        let data = [16, 235, 126, 16, 128, 128];
+
+        let mut gray = Frame::zeros((2, 2, 1));
+        convert_yuv_frame(&data, &layout, false, &mut gray);
+        assert_eq!(gray.into_raw_vec(), vec![0, 255, 128, 0]);
+
+        let mut color = Frame::zeros((2, 2, 3));
+        convert_yuv_frame(&data, &layout, true, &mut color);
+        assert_eq!(color[[0, 0, 0]], 0);
+        assert_eq!(color[[0, 1, 1]], 255);
+        assert_eq!(color[[1, 0, 2]], 128);
+    }
+
+    #[test]
+    fn test_y4m_source() {
+        let mut bytes = b"YUV4MPEG2 W4 H2 F30:1 Ip A1:1 C420jpeg XCOLORRANGE=FULL\n".to_vec();
+        bytes.extend_from_slice(b"FRAME\n");
+        bytes.extend((0..8).map(|i| i * 10));
+        bytes.extend_from_slice(&[128; 4]);
+        let path = write_temp("full.y4m", &bytes);
+
+        let mut source: Yuv<Vec<u8>> =
+            Yuv::new(path.to_str().unwrap().to_string(), false, 1.0).unwrap();
+        assert_eq!(source.layout.width, 4);
+        assert_eq!(source.layout.height, 2);
+        assert_eq!(source.layout.pixel_format, YuvPixelFormat::Yuv420);
+        assert_eq!(source.layout.fps, 30.0);
+        assert!(source.layout.full_range);
+
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        source.consume(1, &pool).unwrap();
+        assert_eq!(
+            source.get_last_input_frame().clone().into_raw_vec(),
+            vec![0, 10, 20, 30, 40, 50, 60, 70]
+        );
+        assert!(matches!(
+            source.consume(1, &pool),
+            Err(SourceError::BufferEmpty)
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_y4m_source_limited_range() {
+        let mut bytes = b"YUV4MPEG2 W2 H2 F25:1 C420\n".to_vec();
+        bytes.extend_from_slice(b"FRAME\n");
+        bytes.extend_from_slice(&[16, 235, 126, 16, 128, 128]);
+        let path = write_temp("limited.y4m", &bytes);
+
+        let mut source: Yuv<Vec<u8>> =
+            Yuv::new(path.to_str().unwrap().to_string(), true, 1.0).unwrap();
+        assert_eq!(source.layout.pixel_format, YuvPixelFormat::Yuv420);
+        assert_eq!(source.layout.fps, 25.0);
+        assert!(!source.layout.full_range);
+
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        source.consume(1, &pool).unwrap();
+        let frame = source.get_last_input_frame();
+        assert_eq!(frame[[0, 0, 0]], 0);
+        assert_eq!(frame[[0, 1, 1]], 255);
+        assert_eq!(frame[[1, 0, 2]], 128);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_y4m_header_truncated() {
+        let mut bytes = b"YUV4MPEG2 W2 H2 F25:1 C420 X".to_vec();
+        bytes.resize(Y4M_MAX_HEADER_LEN + 16, b'A');
+        let path = write_temp("truncated.y4m", &bytes);
+
+        let source: Result<Yuv<Vec<u8>>, _> =
+            Yuv::new(path.to_str().unwrap().to_string(), false, 1.0);
+        assert!(matches!(source, Err(SourceError::BadParams(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+}
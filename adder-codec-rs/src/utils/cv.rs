@@ -3,23 +3,25 @@ use crate::transcoder::source::video::SourceError;
 use adder_codec_core::PixelAddress;
 use adder_codec_core::{Coord, PlaneSize};
 use const_for::const_for;
-use ndarray::{Array3, ArrayView, Axis, Ix2};
+use ndarray::{Array2, Array3, ArrayView, Axis, Ix2};
 #[cfg(feature = "open-cv")]
 use opencv::prelude::KeyPointTraitConst;
 use serde::{Deserialize, Serialize};
-#[cfg(feature = "open-cv")]
 use std::collections::HashSet;
 
 use std::error::Error;
 use video_rs_adder_dep::Frame;
 
+/// Optical flow from events, estimated alongside the feature detector
+pub use crate::vision::flow::{FlowVector, PlaneFitFlow};
+
 // TODO: Explore optimal threshold values
 /// The threshold for feature detection
 pub const INTENSITY_THRESHOLD: i16 = 30;
 
 /// Indices for the asynchronous FAST 9_16 algorithm
 #[rustfmt::skip]
-const CIRCLE3: [[isize; 2]; 16] = [
+pub(crate) const CIRCLE3: [[isize; 2]; 16] = [
     [0, 3], [1, 3], [2, 2], [3, 1],
     [3, 0], [3, -1], [2, -2], [1, -3],
     [0, -3], [-1, -3], [-2, -2], [-3, -1],
@@ -232,8 +234,6 @@ pub fn feature_precision_recall_accuracy(
     prediction: &HashSet<Coord>,
     plane: PlaneSize,
 ) -> (f64, f64, f64) {
-    let (mut tp, mut fp, mut tn, mut fnn) = (0, 0, 0, 0);
-
     // Channel of first pred event:
     let channel = match prediction.iter().next() {
         None => None,
@@ -250,16 +250,30 @@ pub fn feature_precision_recall_accuracy(
         ));
     }
 
+    feature_set_precision_recall_accuracy(&gt_hash, prediction, plane)
+}
+
+/// Compare a set of predicted features to a set of ground truth features, such as those from
+/// another detector.
+///
+/// returns: the precision, recall, and accuracy of the prediction
+pub fn feature_set_precision_recall_accuracy(
+    gt: &HashSet<Coord>,
+    prediction: &HashSet<Coord>,
+    plane: PlaneSize,
+) -> (f64, f64, f64) {
+    let (mut tp, mut fp, mut tn, mut fnn) = (0, 0, 0, 0);
+
     for y in 0..plane.h() {
         for x in 0..plane.w() {
             let coord = Coord::new(x, y, None);
             if prediction.contains(&coord) {
-                if gt_hash.contains(&coord) {
+                if gt.contains(&coord) {
                     tp += 1;
                 } else {
                     fp += 1;
                 }
-            } else if gt_hash.contains(&coord) {
+            } else if gt.contains(&coord) {
                 fnn += 1;
             } else {
                 tn += 1;
@@ -284,6 +298,10 @@ pub struct QualityMetrics {
 
     /// Structural similarity index measure
     pub ssim: Option<f64>,
+
+    /// Multi-scale structural similarity index measure
+    #[serde(default)]
+    pub ms_ssim: Option<f64>,
 }
 
 impl Default for QualityMetrics {
@@ -292,6 +310,7 @@ impl Default for QualityMetrics {
             psnr: Some(0.0),
             mse: Some(0.0),
             ssim: None,
+            ms_ssim: None,
         }
     }
 }
@@ -321,6 +340,9 @@ pub fn calculate_quality_metrics(
     if results.ssim.is_some() {
         results.ssim = Some(calculate_ssim(original, reconstructed)?);
     }
+    if results.ms_ssim.is_some() {
+        results.ms_ssim = Some(calculate_ms_ssim(original, reconstructed)?);
+    }
     Ok(results)
 }
 
@@ -399,25 +421,121 @@ fn ssim_for_window(source_window: ArrayView<u8, Ix2>, recon_window: ArrayView<u8
     counter / denominator
 }
 
-fn covariance(
-    window_x: &ArrayView<u8, Ix2>,
+fn covariance<T: Copy + Into<f64>>(
+    window_x: &ArrayView<T, Ix2>,
     mean_x: f64,
-    window_y: &ArrayView<u8, Ix2>,
+    window_y: &ArrayView<T, Ix2>,
     mean_y: f64,
 ) -> f64 {
     window_x
         .iter()
         .zip(window_y.iter())
-        .map(|(x, y)| (*x as f64 - mean_x) * (*y as f64 - mean_y))
+        .map(|(x, y)| ((*x).into() - mean_x) * ((*y).into() - mean_y))
         .sum::<f64>()
 }
 
-fn mean(window: &ArrayView<u8, Ix2>) -> f64 {
-    let sum = window.iter().map(|pixel| *pixel as f64).sum::<f64>();
+fn mean<T: Copy + Into<f64>>(window: &ArrayView<T, Ix2>) -> f64 {
+    let sum = window.iter().map(|pixel| (*pixel).into()).sum::<f64>();
 
     sum / window.len() as f64
 }
 
+/// The weight of each scale's similarity in MS-SSIM, from finest to coarsest
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// Calculate the MS-SSIM score, on the same scale as the SSIM score.
+///
+/// The contrast-structure similarity is measured at up to five scales, halving the resolution of
+/// the images each time, and the luminance similarity is measured at the coarsest scale. Scales
+/// smaller than the SSIM window are skipped, and the weights of the remaining scales are
+/// renormalized.
+fn calculate_ms_ssim(
+    original: &Array3<u8>,
+    reconstructed: &Array3<u8>,
+) -> Result<f64, Box<dyn Error>> {
+    let mut scores = vec![];
+    for channel in 0..original.shape()[2] {
+        let mut image_original = original.index_axis(Axis(2), channel).mapv(f64::from);
+        let mut image_reconstructed = reconstructed.index_axis(Axis(2), channel).mapv(f64::from);
+
+        let mut scales = vec![];
+        for _ in 0..MS_SSIM_WEIGHTS.len() {
+            if image_original.nrows() < DEFAULT_WINDOW_SIZE
+                || image_original.ncols() < DEFAULT_WINDOW_SIZE
+            {
+                break;
+            }
+            scales.push(luminance_and_contrast_structure(
+                &image_original,
+                &image_reconstructed,
+            ));
+            image_original = downsample(&image_original);
+            image_reconstructed = downsample(&image_reconstructed);
+        }
+        if scales.is_empty() {
+            return Err("Images are too small for MS-SSIM".into());
+        }
+
+        let weights = &MS_SSIM_WEIGHTS[..scales.len()];
+        let weight_sum = weights.iter().sum::<f64>();
+        let last = scales.len() - 1;
+        let score = scales
+            .iter()
+            .zip(weights)
+            .enumerate()
+            .map(|(idx, ((luminance, contrast_structure), weight))| {
+                let similarity = if idx == last {
+                    luminance * contrast_structure
+                } else {
+                    *contrast_structure
+                };
+                // Negative correlation is treated as no similarity
+                similarity.max(0.0).powf(weight / weight_sum)
+            })
+            .product::<f64>();
+        scores.push(score);
+    }
+
+    Ok((scores.iter().sum::<f64>() / scores.len() as f64) * 100.0)
+}
+
+/// Get the mean luminance and contrast-structure similarities over all windows of two images
+fn luminance_and_contrast_structure(
+    original: &Array2<f64>,
+    reconstructed: &Array2<f64>,
+) -> (f64, f64) {
+    let windows_original = original.windows((DEFAULT_WINDOW_SIZE, DEFAULT_WINDOW_SIZE));
+    let windows_reconstructed = reconstructed.windows((DEFAULT_WINDOW_SIZE, DEFAULT_WINDOW_SIZE));
+    let samples = (DEFAULT_WINDOW_SIZE * DEFAULT_WINDOW_SIZE - 1) as f64;
+
+    let (mut luminance_sum, mut contrast_structure_sum, mut count) = (0.0, 0.0, 0.0);
+    for (w1, w2) in windows_original
+        .into_iter()
+        .zip(windows_reconstructed.into_iter())
+    {
+        let mean_x = mean(&w1);
+        let mean_y = mean(&w2);
+        let variance_x = covariance(&w1, mean_x, &w1, mean_x) / samples;
+        let variance_y = covariance(&w2, mean_y, &w2, mean_y) / samples;
+        let covariance = covariance(&w1, mean_x, &w2, mean_y) / samples;
+        luminance_sum += (2. * mean_x * mean_y + C1) / (mean_x.powi(2) + mean_y.powi(2) + C1);
+        contrast_structure_sum += (2. * covariance + C2) / (variance_x + variance_y + C2);
+        count += 1.0;
+    }
+    (luminance_sum / count, contrast_structure_sum / count)
+}
+
+/// Halve the resolution of an image by averaging each 2x2 block of pixels
+fn downsample(image: &Array2<f64>) -> Array2<f64> {
+    Array2::from_shape_fn((image.nrows() / 2, image.ncols() / 2), |(y, x)| {
+        (image[[2 * y, 2 * x]]
+            + image[[2 * y, 2 * x + 1]]
+            + image[[2 * y + 1, 2 * x]]
+            + image[[2 * y + 1, 2 * x + 1]])
+            / 4.0
+    })
+}
+
 pub fn clamp_u8(frame_val: &mut f64, last_val_ln: &mut f64) {
     if *frame_val <= 0.0 {
         *frame_val = 0.0;
@@ -427,3 +545,29 @@ pub fn clamp_u8(frame_val: &mut f64, last_val_ln: &mut f64) {
         *last_val_ln = 1.0_f64.ln_1p();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ms_ssim() {
+        let original =
+            Array3::from_shape_fn((64, 64, 1), |(y, x, _)| ((x * 4 + y * 2) % 256) as u8);
+        let metrics = QualityMetrics {
+            ms_ssim: Some(0.0),
+            ..Default::default()
+        };
+
+        let identical = calculate_quality_metrics(&original, &original, metrics).unwrap();
+        assert!((identical.ms_ssim.unwrap() - 100.0).abs() < 1e-9);
+
+        let noisy = original.mapv(|px| px.saturating_add(20));
+        let degraded = calculate_quality_metrics(&original, &noisy, metrics).unwrap();
+        assert!(degraded.ms_ssim.unwrap() < 100.0);
+        assert!(degraded.ms_ssim.unwrap() > 0.0);
+
+        let tiny = Array3::zeros((4, 4, 1));
+        assert!(calculate_quality_metrics(&tiny, &tiny, metrics).is_err());
+    }
+}
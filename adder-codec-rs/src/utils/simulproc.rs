@@ -82,6 +82,12 @@ pub struct SimulProcArgs {
 
     #[clap(long, default_value = "")]
     pub integration_mode: String,
+
+    /// Integrate each frame over its real duration, according to the container timestamps.
+    /// Use this for variable-frame-rate video.
+    #[clap(long, action)]
+    #[serde(default)]
+    pub vfr: bool,
 }
 
 /// A struct for simultaneously transcoding a video source to ADΔER and reconstructing a framed
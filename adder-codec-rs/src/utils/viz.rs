@@ -1,6 +1,7 @@
 use adder_codec_core::{Event, PixelAddress};
 #[cfg(feature = "open-cv")]
 use opencv::core::{Mat, MatTraitConst, MatTraitConstManual};
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs::File;
 use std::io;
@@ -74,7 +75,7 @@ pub async fn download_file(
 }
 
 /// The display mode for visualizing detected features
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum ShowFeatureMode {
     /// Don't show features at all
     Off,
@@ -86,6 +87,39 @@ pub enum ShowFeatureMode {
     Hold,
 }
 
+/// Draw a flow vector on the given `img` as a white line starting at the pixel. The line's length
+/// is the velocity multiplied by `scale`, e.g., the distance moved in `scale` seconds for a
+/// velocity in pixels per second. The line stops at the edge of the image.
+pub fn draw_flow_vector(
+    x: PixelAddress,
+    y: PixelAddress,
+    vx: f32,
+    vy: f32,
+    scale: f32,
+    img: &mut Frame,
+) {
+    let draw_color: u8 = 255;
+    let (height, width, channels) = img.dim();
+    let (dx, dy) = (vx * scale, vy * scale);
+    let steps = dx.abs().max(dy.abs()).ceil() as usize;
+
+    for step in 0..=steps {
+        let fraction = if steps == 0 {
+            0.0
+        } else {
+            step as f32 / steps as f32
+        };
+        let px = (f32::from(x) + dx * fraction).round();
+        let py = (f32::from(y) + dy * fraction).round();
+        if px < 0.0 || py < 0.0 || px as usize >= width || py as usize >= height {
+            break;
+        }
+        for c in 0..channels {
+            img[[py as usize, px as usize, c]] = draw_color;
+        }
+    }
+}
+
 /// Assuming the given event is a feature, draw it on the given `img` as a white cross
 pub fn draw_feature_event(e: &Event, img: &mut Frame) {
     draw_feature_coord(e.coord.x, e.coord.y, img, false)
@@ -0,0 +1,420 @@
+use crate::utils::cv::{CIRCLE3, INTENSITY_THRESHOLD};
+use adder_codec_core::{BigT, Coord, PlaneSize};
+use ndarray::{Array2, Array3};
+
+/// Indices for the outer circle of the event-driven detectors
+#[rustfmt::skip]
+const CIRCLE4: [[isize; 2]; 20] = [
+    [0, 4], [1, 4], [2, 3], [3, 2], [4, 1],
+    [4, 0], [4, -1], [3, -2], [2, -3], [1, -4],
+    [0, -4], [-1, -4], [-2, -3], [-3, -2], [-4, -1],
+    [-4, 0], [-4, 1], [-3, 2], [-2, 3], [-1, 4]
+];
+
+/// Decides whether a pixel is a feature, given the current intensities of the whole image
+pub trait FeatureDetector: Send {
+    /// A short name for the detector, for logging and exported data
+    fn name(&self) -> &'static str;
+
+    /// Check whether the pixel at `coord` is a feature, after it fired an event at time `t`.
+    /// `intensities` has the shape `(height, width, channels)`.
+    ///
+    /// Returns the corner score if the pixel is a feature, where a higher score is a stronger
+    /// corner
+    fn detect(
+        &mut self,
+        coord: Coord,
+        t: BigT,
+        plane: PlaneSize,
+        intensities: &Array3<u8>,
+    ) -> Option<f32>;
+}
+
+/// The FAST corner detector on the running intensities, with a 16-pixel circle of radius 3. With
+/// the default parameters, this is equivalent to [`is_feature`](crate::utils::cv::is_feature).
+#[derive(Debug, Clone, Copy)]
+pub struct Fast {
+    /// How much brighter or darker than the candidate a circle pixel must be
+    pub threshold: i16,
+
+    /// The number of contiguous circle pixels which must all be brighter or all be darker
+    pub streak_size: usize,
+}
+
+impl Default for Fast {
+    fn default() -> Self {
+        Fast {
+            threshold: INTENSITY_THRESHOLD,
+            streak_size: 9,
+        }
+    }
+}
+
+impl FeatureDetector for Fast {
+    fn name(&self) -> &'static str {
+        "fast"
+    }
+
+    fn detect(
+        &mut self,
+        coord: Coord,
+        _t: BigT,
+        plane: PlaneSize,
+        intensities: &Array3<u8>,
+    ) -> Option<f32> {
+        if coord.is_border(plane.w_usize(), plane.h_usize(), 3) || coord.c_usize() != 0 {
+            return None;
+        }
+        let candidate = i16::from(intensities[[coord.y_usize(), coord.x_usize(), 0]]);
+        let diffs: Vec<i16> = CIRCLE3
+            .iter()
+            .map(|[dx, dy]| {
+                let y = (coord.y as isize + dy) as usize;
+                let x = (coord.x as isize + dx) as usize;
+                i16::from(intensities[[y, x, 0]]) - candidate
+            })
+            .collect();
+
+        let darker = longest_circular_run(&diffs, |diff| diff < -self.threshold);
+        let brighter = longest_circular_run(&diffs, |diff| diff > self.threshold);
+        if darker.max(brighter) < self.streak_size {
+            return None;
+        }
+
+        // Score by how far the circle pixels exceed the threshold
+        Some(
+            diffs
+                .iter()
+                .map(|diff| f32::from((diff.abs() - self.threshold).max(0)))
+                .sum(),
+        )
+    }
+}
+
+/// Get the length of the longest run of values satisfying `predicate`, wrapping around the end
+fn longest_circular_run<F: Fn(i16) -> bool>(values: &[i16], predicate: F) -> usize {
+    let mut longest = 0;
+    let mut count = 0;
+    for value in values.iter().chain(values.iter()) {
+        if predicate(*value) {
+            count += 1;
+            longest = longest.max(count);
+        } else {
+            count = 0;
+        }
+    }
+    longest.min(values.len())
+}
+
+/// The measure of cornerness computed from a pixel's structure tensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CornerResponse {
+    /// The Harris response, `det - k * trace^2`
+    Harris {
+        /// The sensitivity parameter, typically 0.04 to 0.06
+        k: f32,
+    },
+
+    /// The Shi-Tomasi response, which is the smaller eigenvalue of the structure tensor
+    ShiTomasi,
+}
+
+/// The Harris or Shi-Tomasi corner detector on the running intensities. Gradients are computed
+/// with Sobel operators on intensities normalized to `[0, 1]`, and the structure tensor is
+/// averaged over a square block around the pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct Harris {
+    /// The corner response to compute
+    pub response: CornerResponse,
+
+    /// The minimum response for a pixel to be a corner
+    pub threshold: f32,
+
+    /// The radius of the block which the structure tensor is averaged over
+    pub block_radius: usize,
+}
+
+impl Harris {
+    /// Create a Harris detector with `k = 0.04`
+    #[must_use]
+    pub fn harris() -> Self {
+        Harris {
+            response: CornerResponse::Harris { k: 0.04 },
+            threshold: 1e-3,
+            block_radius: 1,
+        }
+    }
+
+    /// Create a Shi-Tomasi detector
+    #[must_use]
+    pub fn shi_tomasi() -> Self {
+        Harris {
+            response: CornerResponse::ShiTomasi,
+            threshold: 1e-2,
+            block_radius: 1,
+        }
+    }
+}
+
+impl FeatureDetector for Harris {
+    fn name(&self) -> &'static str {
+        match self.response {
+            CornerResponse::Harris { .. } => "harris",
+            CornerResponse::ShiTomasi => "shi_tomasi",
+        }
+    }
+
+    fn detect(
+        &mut self,
+        coord: Coord,
+        _t: BigT,
+        plane: PlaneSize,
+        intensities: &Array3<u8>,
+    ) -> Option<f32> {
+        let border = self.block_radius + 1;
+        if coord.is_border(plane.w_usize(), plane.h_usize(), border) || coord.c_usize() != 0 {
+            return None;
+        }
+
+        let value = |y: usize, x: usize| f32::from(intensities[[y, x, 0]]) / 255.0;
+        let (mut ixx, mut iyy, mut ixy) = (0.0, 0.0, 0.0);
+        let r = self.block_radius;
+        for y in coord.y_usize() - r..=coord.y_usize() + r {
+            for x in coord.x_usize() - r..=coord.x_usize() + r {
+                let gx = (value(y - 1, x + 1) + 2.0 * value(y, x + 1) + value(y + 1, x + 1)
+                    - value(y - 1, x - 1)
+                    - 2.0 * value(y, x - 1)
+                    - value(y + 1, x - 1))
+                    / 8.0;
+                let gy = (value(y + 1, x - 1) + 2.0 * value(y + 1, x) + value(y + 1, x + 1)
+                    - value(y - 1, x - 1)
+                    - 2.0 * value(y - 1, x)
+                    - value(y - 1, x + 1))
+                    / 8.0;
+                ixx += gx * gx;
+                iyy += gy * gy;
+                ixy += gx * gy;
+            }
+        }
+        let count = ((2 * r + 1) * (2 * r + 1)) as f32;
+        let (ixx, iyy, ixy) = (ixx / count, iyy / count, ixy / count);
+
+        let det = ixx * iyy - ixy * ixy;
+        let trace = ixx + iyy;
+        let response = match self.response {
+            CornerResponse::Harris { k } => det - k * trace * trace,
+            CornerResponse::ShiTomasi => {
+                let half_trace = trace / 2.0;
+                half_trace - (half_trace * half_trace - det).max(0.0).sqrt()
+            }
+        };
+        (response >= self.threshold).then_some(response)
+    }
+}
+
+/// The algorithm used by an [`EventCorner`] detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCornerMode {
+    /// eFAST: a contiguous arc of the circle must be strictly newer than the rest of the circle
+    EFast,
+
+    /// Arc*: an arc grown greedily from the newest pixel on the circle must have a length in the
+    /// corner range, or its complement must
+    ArcStar,
+}
+
+/// An event-driven corner detector, which operates on the surface of active events (the latest
+/// timestamp of each pixel) rather than on intensities. A pixel is a corner if both its inner
+/// circle (radius 3, with an arc of 3 to 6 pixels) and its outer circle (radius 4, with an arc
+/// of 4 to 8 pixels) have a qualifying arc.
+#[derive(Debug, Clone)]
+pub struct EventCorner {
+    /// The algorithm to use
+    pub mode: EventCornerMode,
+
+    /// The latest timestamp of each pixel
+    sae: Array2<BigT>,
+}
+
+impl EventCorner {
+    /// Create an event-driven corner detector
+    #[must_use]
+    pub fn new(mode: EventCornerMode) -> Self {
+        EventCorner {
+            mode,
+            sae: Array2::zeros((0, 0)),
+        }
+    }
+}
+
+impl FeatureDetector for EventCorner {
+    fn name(&self) -> &'static str {
+        match self.mode {
+            EventCornerMode::EFast => "efast",
+            EventCornerMode::ArcStar => "arc_star",
+        }
+    }
+
+    fn detect(
+        &mut self,
+        coord: Coord,
+        t: BigT,
+        plane: PlaneSize,
+        _intensities: &Array3<u8>,
+    ) -> Option<f32> {
+        if coord.c_usize() != 0 {
+            return None;
+        }
+        if self.sae.dim() != (plane.h_usize(), plane.w_usize()) {
+            self.sae = Array2::zeros((plane.h_usize(), plane.w_usize()));
+        }
+        self.sae[[coord.y_usize(), coord.x_usize()]] = t;
+        if coord.is_border(plane.w_usize(), plane.h_usize(), 4) {
+            return None;
+        }
+
+        let circle_times = |circle: &[[isize; 2]]| -> Vec<BigT> {
+            circle
+                .iter()
+                .map(|[dx, dy]| {
+                    let y = (coord.y as isize + dy) as usize;
+                    let x = (coord.x as isize + dx) as usize;
+                    self.sae[[y, x]]
+                })
+                .collect()
+        };
+        let segment = match self.mode {
+            EventCornerMode::EFast => efast_segment,
+            EventCornerMode::ArcStar => arc_star_segment,
+        };
+
+        let inner = segment(&circle_times(&CIRCLE3), 3, 6)?;
+        let outer = segment(&circle_times(&CIRCLE4), 4, 8)?;
+        Some((inner + outer) as f32)
+    }
+}
+
+/// Find a contiguous arc with a length in `min_len..=max_len` whose timestamps are all newer
+/// than every other timestamp on the circle. Returns the arc length.
+fn efast_segment(times: &[BigT], min_len: usize, max_len: usize) -> Option<usize> {
+    let n = times.len();
+    (min_len..=max_len).find(|&len| {
+        (0..n).any(|start| {
+            let oldest_in_arc = (0..len).map(|i| times[(start + i) % n]).min();
+            let newest_outside = (len..n).map(|i| times[(start + i) % n]).max();
+            matches!((oldest_in_arc, newest_outside), (Some(a), Some(b)) if a > b)
+        })
+    })
+}
+
+/// Grow an arc from the newest timestamp on the circle, always extending towards the newer
+/// neighbor, for as long as that neighbor is at least as new as the oldest timestamp in the arc
+/// (and at least until the arc reaches `min_len`). Returns the arc length if either the arc or its
+/// complement has a length in `min_len..=max_len`.
+fn arc_star_segment(times: &[BigT], min_len: usize, max_len: usize) -> Option<usize> {
+    let n = times.len();
+    let newest = (0..n).max_by_key(|&i| times[i])?;
+    let mut oldest_in_arc = times[newest];
+    let mut cw = (newest + n - 1) % n;
+    let mut ccw = (newest + 1) % n;
+    let mut len = 1;
+
+    while len < n {
+        let (t_cw, t_ccw) = (times[cw], times[ccw]);
+        let next = t_cw.max(t_ccw);
+        if len >= min_len && next < oldest_in_arc {
+            break;
+        }
+        oldest_in_arc = oldest_in_arc.min(next);
+        if t_cw >= t_ccw {
+            cw = (cw + n - 1) % n;
+        } else {
+            ccw = (ccw + 1) % n;
+        }
+        len += 1;
+    }
+
+    let corner_range = min_len..=max_len;
+    (corner_range.contains(&len) || corner_range.contains(&(n - len))).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::cv::is_feature;
+
+    /// A 9x9 image with a bright quadrant whose corner is at the center
+    fn corner_image() -> Array3<u8> {
+        Array3::from_shape_fn(
+            (9, 9, 1),
+            |(y, x, _)| {
+                if y >= 4 && x >= 4 {
+                    200
+                } else {
+                    20
+                }
+            },
+        )
+    }
+
+    fn center() -> Coord {
+        Coord {
+            x: 4,
+            y: 4,
+            c: None,
+        }
+    }
+
+    #[test]
+    fn fast_matches_reference() {
+        let plane = PlaneSize::new(9, 9, 1).unwrap();
+        let image = corner_image();
+        let mut fast = Fast::default();
+        for y in 0..9 {
+            for x in 0..9 {
+                let coord = Coord { x, y, c: None };
+                assert_eq!(
+                    fast.detect(coord, 0, plane, &image).is_some(),
+                    is_feature(coord, plane, &image).unwrap()
+                );
+            }
+        }
+        assert!(fast.detect(center(), 0, plane, &image).is_some());
+
+        let mut strict = Fast {
+            threshold: 200,
+            streak_size: 9,
+        };
+        assert!(strict.detect(center(), 0, plane, &image).is_none());
+    }
+
+    #[test]
+    fn harris_and_shi_tomasi() {
+        let plane = PlaneSize::new(9, 9, 1).unwrap();
+        let image = corner_image();
+        let edge = Coord {
+            x: 4,
+            y: 6,
+            c: None,
+        };
+        for mut detector in [Harris::harris(), Harris::shi_tomasi()] {
+            assert!(detector.detect(center(), 0, plane, &image).is_some());
+            assert!(detector.detect(edge, 0, plane, &image).is_none());
+        }
+    }
+
+    #[test]
+    fn event_corner_segments() {
+        // The newest timestamps of the inner circle form a short arc
+        let mut times = [1; 16];
+        times[2..6].copy_from_slice(&[5, 6, 7, 8]);
+        assert_eq!(efast_segment(&times, 3, 6), Some(3));
+        assert_eq!(arc_star_segment(&times, 3, 6), Some(3));
+
+        // A moving edge makes half of the circle newer, which is not a corner
+        let mut edge = [1; 16];
+        edge[..8].copy_from_slice(&[9; 8]);
+        assert_eq!(efast_segment(&edge, 3, 6), None);
+        assert_eq!(arc_star_segment(&edge, 3, 6), None);
+    }
+}
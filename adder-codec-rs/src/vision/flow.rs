@@ -0,0 +1,271 @@
+use crate::framer::driver::PixelClock;
+use crate::framer::scale_intensity::FrameValue;
+use crate::transcoder::source::video::FramedViewMode;
+use adder_codec_core::codec::CodecMetadata;
+use adder_codec_core::{BigT, Coord, DeltaT, Event, PlaneSize, SourceType, D_EMPTY};
+use ndarray::{Array2, Array3};
+
+/// The velocity of the scene at a pixel, estimated when the pixel fired an event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowVector {
+    /// The pixel which fired the event
+    pub coord: Coord,
+
+    /// The time at which the event fired, in ticks
+    pub t: BigT,
+
+    /// The horizontal velocity, in pixels per second
+    pub vx: f32,
+
+    /// The vertical velocity, in pixels per second
+    pub vy: f32,
+}
+
+/// Estimates sparse optical flow by fitting a plane to the local surface of active events.
+///
+/// A pixel's entry in the surface is updated when one of its events changes its reconstructed
+/// intensity by at least the contrast threshold, so static pixels which fire only to maintain
+/// their intensity don't contribute. The gradient of a plane fit to the recent entries around
+/// the pixel gives the inverse of the velocity of a moving edge. Only the first channel is used.
+pub struct PlaneFitFlow {
+    plane: PlaneSize,
+    source_type: SourceType,
+    clock: PixelClock,
+    tps: DeltaT,
+    ref_interval: DeltaT,
+    delta_t_max: DeltaT,
+
+    /// The running timestamp of each pixel
+    pixel_ts: Array3<BigT>,
+
+    /// The instantaneous intensity of each pixel, as an 8-bit value
+    intensities: Array3<u8>,
+
+    /// The time of each pixel's last intensity change
+    surface: Array2<Option<BigT>>,
+
+    /// The last velocity estimated at each pixel
+    flow: Array2<Option<FlowVector>>,
+
+    radius: usize,
+    max_age: BigT,
+    min_points: usize,
+    contrast_threshold: u8,
+}
+
+impl PlaneFitFlow {
+    /// Create a flow estimator for a stream with the given metadata and source type. By default,
+    /// planes are fit to a 5x5 neighborhood of surface entries no older than `delta_t_max`.
+    #[must_use]
+    pub fn new(meta: &CodecMetadata, source_type: SourceType) -> Self {
+        let plane = meta.plane;
+        PlaneFitFlow {
+            plane,
+            source_type,
+            clock: PixelClock::from_meta(meta),
+            tps: meta.tps,
+            ref_interval: meta.ref_interval,
+            delta_t_max: meta.delta_t_max,
+            pixel_ts: Array3::zeros((plane.h_usize(), plane.w_usize(), plane.c_usize())),
+            intensities: Array3::zeros((plane.h_usize(), plane.w_usize(), plane.c_usize())),
+            surface: Array2::from_elem((plane.h_usize(), plane.w_usize()), None),
+            flow: Array2::from_elem((plane.h_usize(), plane.w_usize()), None),
+            radius: 2,
+            max_age: BigT::from(meta.delta_t_max),
+            min_points: 5,
+            contrast_threshold: 10,
+        }
+    }
+
+    /// Set the radius of the neighborhood to fit a plane to
+    #[must_use]
+    pub fn radius(mut self, radius: usize) -> Self {
+        self.radius = radius.max(1);
+        self
+    }
+
+    /// Set the maximum age, in ticks, of the surface entries to fit a plane to
+    #[must_use]
+    pub fn max_age(mut self, max_age: BigT) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Set the minimum number of surface entries needed to fit a plane
+    #[must_use]
+    pub fn min_points(mut self, min_points: usize) -> Self {
+        self.min_points = min_points.max(3);
+        self
+    }
+
+    /// Set the minimum change in 8-bit intensity for an event to update the surface
+    #[must_use]
+    pub fn contrast_threshold(mut self, contrast_threshold: u8) -> Self {
+        self.contrast_threshold = contrast_threshold;
+        self
+    }
+
+    /// Get the last velocity estimated at the given pixel, such as the location of a feature
+    #[must_use]
+    pub fn flow_at(&self, x: usize, y: usize) -> Option<FlowVector> {
+        self.flow.get((y, x)).copied().flatten()
+    }
+
+    /// Ingest an ADΔER event.
+    ///
+    /// returns: the velocity at the event's pixel, if the event updated the surface and a plane
+    /// could be fit around it
+    pub fn ingest_event(&mut self, event: &Event) -> Option<FlowVector> {
+        let px = [
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ];
+        if px[0] >= self.plane.h_usize()
+            || px[1] >= self.plane.w_usize()
+            || px[2] >= self.plane.c_usize()
+        {
+            return None;
+        }
+
+        let (delta_t, fired_t) = self.clock.advance(&mut self.pixel_ts[px], event)?;
+        if event.d == D_EMPTY || px[2] != 0 {
+            return None;
+        }
+
+        let practical_d_max =
+            fast_math::log2_raw(u8::max_f32() * (self.delta_t_max / self.ref_interval) as f32);
+        let intensity = u8::get_frame_value(
+            &Event {
+                coord: event.coord,
+                d: event.d,
+                t: delta_t as DeltaT,
+            },
+            self.source_type,
+            f64::from(self.ref_interval),
+            practical_d_max,
+            self.delta_t_max,
+            FramedViewMode::Intensity,
+            None,
+        );
+        let previous = std::mem::replace(&mut self.intensities[px], intensity);
+        if intensity.abs_diff(previous) < self.contrast_threshold {
+            return None;
+        }
+        self.surface[[px[0], px[1]]] = Some(fired_t);
+
+        let (vx, vy) = self.fit_plane(px[0], px[1], fired_t)?;
+        let vector = FlowVector {
+            coord: event.coord,
+            t: fired_t,
+            vx,
+            vy,
+        };
+        self.flow[[px[0], px[1]]] = Some(vector);
+        Some(vector)
+    }
+
+    /// Fit the plane `t = a * x + b * y + c` to the recent surface entries around a pixel, by
+    /// least squares.
+    ///
+    /// returns: the velocity, in pixels per second
+    fn fit_plane(&self, y: usize, x: usize, t: BigT) -> Option<(f32, f32)> {
+        let mut points = Vec::with_capacity((2 * self.radius + 1).pow(2));
+        for ny in y.saturating_sub(self.radius)..=(y + self.radius).min(self.plane.h_usize() - 1) {
+            for nx in
+                x.saturating_sub(self.radius)..=(x + self.radius).min(self.plane.w_usize() - 1)
+            {
+                if let Some(entry_t) = self.surface[[ny, nx]] {
+                    if entry_t <= t && t - entry_t <= self.max_age {
+                        points.push((
+                            nx as f64 - x as f64,
+                            ny as f64 - y as f64,
+                            entry_t as f64 - t as f64,
+                        ));
+                    }
+                }
+            }
+        }
+        if points.len() < self.min_points {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let (mean_x, mean_y, mean_t) = points.iter().fold((0.0, 0.0, 0.0), |sum, p| {
+            (sum.0 + p.0 / n, sum.1 + p.1 / n, sum.2 + p.2 / n)
+        });
+        let (mut sxx, mut syy, mut sxy, mut sxt, mut syt) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (px, py, pt) in &points {
+            let (dx, dy, dt) = (px - mean_x, py - mean_y, pt - mean_t);
+            sxx += dx * dx;
+            syy += dy * dy;
+            sxy += dx * dy;
+            sxt += dx * dt;
+            syt += dy * dt;
+        }
+
+        let det = sxx * syy - sxy * sxy;
+        if det.abs() < 1e-9 {
+            return None;
+        }
+
+        // The gradient of the surface, in ticks per pixel
+        let a = (sxt * syy - syt * sxy) / det;
+        let b = (syt * sxx - sxt * sxy) / det;
+        let magnitude_sq = a * a + b * b;
+        if magnitude_sq < 1e-9 {
+            return None;
+        }
+
+        let tps = f64::from(self.tps);
+        Some((
+            (a / magnitude_sq * tps) as f32,
+            (b / magnitude_sq * tps) as f32,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adder_codec_core::{SourceCamera, TimeMode};
+
+    #[test]
+    fn moving_edge() {
+        let meta = CodecMetadata {
+            codec_version: 2,
+            header_size: 0,
+            time_mode: TimeMode::AbsoluteT,
+            plane: PlaneSize::new(9, 9, 1).unwrap(),
+            tps: 1000,
+            ref_interval: 100,
+            delta_t_max: 1000,
+            event_size: 0,
+            source_camera: SourceCamera::Dvs,
+            adu_interval: 1,
+        };
+        let mut flow = PlaneFitFlow::new(&meta, SourceType::U8);
+
+        // A vertical edge sweeps right at one pixel per 10 ticks, or 100 pixels per second
+        let mut estimate = None;
+        for x in 0..9_u16 {
+            for y in 0..9_u16 {
+                let vector = flow.ingest_event(&Event {
+                    coord: Coord { x, y, c: None },
+                    d: 7,
+                    t: DeltaT::from(x + 1) * 10,
+                });
+                if (x, y) == (6, 4) {
+                    estimate = vector;
+                }
+            }
+        }
+
+        let estimate = estimate.unwrap();
+        assert_eq!(estimate.t, 70);
+        assert!((estimate.vx - 100.0).abs() < 1e-3);
+        assert!(estimate.vy.abs() < 1e-3);
+        assert_eq!(flow.flow_at(6, 4), Some(estimate));
+        assert!(flow.flow_at(0, 0).is_none());
+    }
+}
@@ -0,0 +1,11 @@
+/// Feature detectors which operate on a pixel's neighborhood of intensities
+pub mod detector;
+
+/// A processor which maintains intensity state from events and detects features
+pub mod processor;
+
+/// Sparse optical flow estimated from events
+pub mod flow;
+
+/// Tracking of features across time
+pub mod tracker;
@@ -0,0 +1,373 @@
+use crate::framer::driver::PixelClock;
+use crate::framer::scale_intensity::FrameValue;
+use crate::transcoder::source::video::FramedViewMode;
+use crate::vision::detector::{Fast, FeatureDetector};
+use adder_codec_core::codec::CodecMetadata;
+use adder_codec_core::{BigT, Coord, DeltaT, Event, PlaneSize, SourceType, D_EMPTY};
+use ndarray::Array3;
+use std::collections::{HashMap, VecDeque};
+
+/// Whether a feature appeared or disappeared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeatureChange {
+    /// The pixel became a feature
+    Detected,
+
+    /// The pixel is no longer a feature
+    Lost,
+}
+
+/// A change in whether a pixel is a feature, according to one detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureEvent {
+    /// The pixel whose feature status changed
+    pub coord: Coord,
+
+    /// The time of the event which caused the change, in ticks
+    pub t: BigT,
+
+    /// The index of the detector, in the order they were given to the [`VisionProcessor`]
+    pub detector: usize,
+
+    /// Whether the feature appeared or disappeared
+    pub change: FeatureChange,
+}
+
+/// Runs feature detectors on any ADΔER event stream, such as one read by a `Decoder` or produced
+/// live by a transcoder. Unlike detection during transcoding, it keeps its own instantaneous
+/// intensity for each pixel, reconstructed from the events themselves.
+///
+/// As in the transcoder, a pixel is only checked when it fires a non-empty event for its first
+/// channel. With non-maximum suppression enabled, a feature is only kept if no feature of the
+/// same detector within the suppression radius has a higher score.
+pub struct VisionProcessor {
+    plane: PlaneSize,
+    clock: PixelClock,
+    source_type: SourceType,
+    ref_interval: DeltaT,
+    delta_t_max: DeltaT,
+
+    /// The running timestamp of each pixel
+    pixel_ts: Array3<BigT>,
+
+    /// The instantaneous intensity of each pixel, as an 8-bit value
+    intensities: Array3<u8>,
+
+    detectors: Vec<Box<dyn FeatureDetector>>,
+
+    /// The current features and their scores for each detector
+    features: Vec<HashMap<Coord, f32>>,
+
+    /// The radius for non-maximum suppression, if enabled
+    nms_radius: Option<u16>,
+
+    feature_events: VecDeque<FeatureEvent>,
+}
+
+impl VisionProcessor {
+    /// Create a processor for a stream with the given metadata and source type (as given by its
+    /// decoder), running the [`Fast`] detector
+    #[must_use]
+    pub fn new(meta: &CodecMetadata, source_type: SourceType) -> Self {
+        Self::with_detectors(meta, source_type, vec![Box::new(Fast::default())])
+    }
+
+    /// Create a processor for a stream with the given metadata and source type (as given by its
+    /// decoder), running the given detectors
+    #[must_use]
+    pub fn with_detectors(
+        meta: &CodecMetadata,
+        source_type: SourceType,
+        detectors: Vec<Box<dyn FeatureDetector>>,
+    ) -> Self {
+        let plane = meta.plane;
+        let shape = (plane.h_usize(), plane.w_usize(), plane.c_usize());
+        VisionProcessor {
+            plane,
+            clock: PixelClock::from_meta(meta),
+            source_type,
+            ref_interval: meta.ref_interval,
+            delta_t_max: meta.delta_t_max,
+            pixel_ts: Array3::zeros(shape),
+            intensities: Array3::zeros(shape),
+            features: vec![HashMap::new(); detectors.len()],
+            detectors,
+            nms_radius: None,
+            feature_events: VecDeque::new(),
+        }
+    }
+
+    /// Enable non-maximum suppression of features within the given radius, in pixels
+    #[must_use]
+    pub fn non_max_suppression(mut self, radius: u16) -> Self {
+        self.nms_radius = Some(radius);
+        self
+    }
+
+    /// Get the names of the detectors, in order
+    #[must_use]
+    pub fn detector_names(&self) -> Vec<&'static str> {
+        self.detectors
+            .iter()
+            .map(|detector| detector.name())
+            .collect()
+    }
+
+    /// Get the current instantaneous intensities, with the shape `(height, width, channels)`
+    #[must_use]
+    pub fn intensities(&self) -> &Array3<u8> {
+        &self.intensities
+    }
+
+    /// Get the current features of the detector with the given index, and their scores
+    #[must_use]
+    pub fn features(&self, detector: usize) -> Option<&HashMap<Coord, f32>> {
+        self.features.get(detector)
+    }
+
+    /// Ingest an ADΔER event, updating the pixel's intensity and running the detectors on it.
+    ///
+    /// Returns `true` if there are feature events ready to pop
+    pub fn ingest_event(&mut self, event: &Event) -> bool {
+        let px = [
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ];
+        if px[0] >= self.plane.h_usize()
+            || px[1] >= self.plane.w_usize()
+            || px[2] >= self.plane.c_usize()
+        {
+            return !self.feature_events.is_empty();
+        }
+
+        let Some((delta_t, fired_t)) = self.clock.advance(&mut self.pixel_ts[px], event) else {
+            return !self.feature_events.is_empty();
+        };
+        if event.d == D_EMPTY {
+            return !self.feature_events.is_empty();
+        }
+
+        let practical_d_max =
+            fast_math::log2_raw(u8::max_f32() * (self.delta_t_max / self.ref_interval) as f32);
+        self.intensities[px] = u8::get_frame_value(
+            &Event {
+                coord: event.coord,
+                d: event.d,
+                t: delta_t as DeltaT,
+            },
+            self.source_type,
+            f64::from(self.ref_interval),
+            practical_d_max,
+            self.delta_t_max,
+            FramedViewMode::Intensity,
+            None,
+        );
+
+        if px[2] == 0 {
+            let scores: Vec<Option<f32>> = self
+                .detectors
+                .iter_mut()
+                .map(|detector| {
+                    detector.detect(event.coord, fired_t, self.plane, &self.intensities)
+                })
+                .collect();
+            for (idx, score) in scores.into_iter().enumerate() {
+                self.update_feature(idx, event.coord, fired_t, score);
+            }
+        }
+
+        !self.feature_events.is_empty()
+    }
+
+    /// Update whether `coord` is a feature for the detector with the given index, suppressing
+    /// non-maximal features if enabled
+    fn update_feature(&mut self, idx: usize, coord: Coord, t: BigT, score: Option<f32>) {
+        let mut lost = Vec::new();
+        let mut detected = false;
+
+        match (score, self.nms_radius) {
+            (Some(score), Some(radius)) => {
+                let neighbors = self.neighbors(coord, radius);
+                let features = &mut self.features[idx];
+                if neighbors
+                    .iter()
+                    .any(|neighbor| features.get(neighbor).map_or(false, |s| *s >= score))
+                {
+                    if features.remove(&coord).is_some() {
+                        lost.push(coord);
+                    }
+                } else {
+                    for neighbor in neighbors {
+                        if features.remove(&neighbor).is_some() {
+                            lost.push(neighbor);
+                        }
+                    }
+                    detected = features.insert(coord, score).is_none();
+                }
+            }
+            (Some(score), None) => {
+                detected = self.features[idx].insert(coord, score).is_none();
+            }
+            (None, _) => {
+                if self.features[idx].remove(&coord).is_some() {
+                    lost.push(coord);
+                }
+            }
+        }
+
+        for coord in lost {
+            self.feature_events.push_back(FeatureEvent {
+                coord,
+                t,
+                detector: idx,
+                change: FeatureChange::Lost,
+            });
+        }
+        if detected {
+            self.feature_events.push_back(FeatureEvent {
+                coord,
+                t,
+                detector: idx,
+                change: FeatureChange::Detected,
+            });
+        }
+    }
+
+    /// Get the coordinates within `radius` of `coord`, excluding `coord` itself
+    fn neighbors(&self, coord: Coord, radius: u16) -> Vec<Coord> {
+        let y_range =
+            coord.y.saturating_sub(radius)..=coord.y.saturating_add(radius).min(self.plane.h() - 1);
+        let mut neighbors = Vec::new();
+        for y in y_range {
+            let x_range = coord.x.saturating_sub(radius)
+                ..=coord.x.saturating_add(radius).min(self.plane.w() - 1);
+            for x in x_range {
+                if (x, y) != (coord.x, coord.y) {
+                    neighbors.push(Coord { x, y, c: coord.c });
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Pop the next feature event, in the order they occurred
+    pub fn pop_feature_event(&mut self) -> Option<FeatureEvent> {
+        self.feature_events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adder_codec_core::{SourceCamera, TimeMode};
+
+    /// Detects a feature wherever the pixel is brighter than all of its horizontal neighbors
+    struct Peak;
+
+    impl FeatureDetector for Peak {
+        fn name(&self) -> &'static str {
+            "peak"
+        }
+
+        fn detect(
+            &mut self,
+            coord: Coord,
+            _t: BigT,
+            plane: PlaneSize,
+            intensities: &Array3<u8>,
+        ) -> Option<f32> {
+            let (x, y) = (coord.x_usize(), coord.y_usize());
+            let value = intensities[[y, x, 0]];
+            (x > 0
+                && x + 1 < plane.w_usize()
+                && value > intensities[[y, x - 1, 0]]
+                && value > intensities[[y, x + 1, 0]])
+            .then_some(f32::from(value))
+        }
+    }
+
+    fn event(x: u16, d: u8, t: DeltaT) -> Event {
+        Event {
+            coord: Coord { x, y: 0, c: None },
+            d,
+            t,
+        }
+    }
+
+    fn meta(width: u16) -> CodecMetadata {
+        CodecMetadata {
+            codec_version: 2,
+            header_size: 0,
+            time_mode: TimeMode::AbsoluteT,
+            plane: PlaneSize::new(width, 1, 1).unwrap(),
+            tps: 1000,
+            ref_interval: 100,
+            delta_t_max: 100,
+            event_size: 0,
+            source_camera: SourceCamera::Dvs,
+            adu_interval: 1,
+        }
+    }
+
+    #[test]
+    fn features_from_events() {
+        let mut processor =
+            VisionProcessor::with_detectors(&meta(3), SourceType::U8, vec![Box::new(Peak)]);
+        assert_eq!(processor.detector_names(), vec!["peak"]);
+
+        assert!(!processor.ingest_event(&event(0, 5, 100)));
+        assert!(!processor.ingest_event(&event(2, 5, 100)));
+        assert!(processor.ingest_event(&event(1, 7, 100)));
+        assert_eq!(processor.intensities().as_slice().unwrap(), &[32, 128, 32]);
+        assert_eq!(
+            processor.pop_feature_event(),
+            Some(FeatureEvent {
+                coord: Coord {
+                    x: 1,
+                    y: 0,
+                    c: None
+                },
+                t: 100,
+                detector: 0,
+                change: FeatureChange::Detected,
+            })
+        );
+
+        // Empty events don't change the intensity
+        assert!(!processor.ingest_event(&event(1, D_EMPTY, 200)));
+        assert!(processor.ingest_event(&event(1, 4, 300)));
+        assert_eq!(
+            processor.pop_feature_event().unwrap().change,
+            FeatureChange::Lost
+        );
+        assert!(processor.features(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn suppresses_weaker_features() {
+        let mut processor =
+            VisionProcessor::with_detectors(&meta(5), SourceType::U8, vec![Box::new(Peak)])
+                .non_max_suppression(2);
+        let mut changes = Vec::new();
+        for (x, d) in [(0, 5), (2, 5), (4, 5), (1, 6), (3, 7)] {
+            processor.ingest_event(&event(x, d, 100));
+            while let Some(feature_event) = processor.pop_feature_event() {
+                changes.push((feature_event.coord.x, feature_event.change));
+            }
+        }
+
+        // Each new peak is stronger than the one before, within the suppression radius
+        assert_eq!(
+            changes,
+            vec![
+                (2, FeatureChange::Detected),
+                (2, FeatureChange::Lost),
+                (1, FeatureChange::Detected),
+                (1, FeatureChange::Lost),
+                (3, FeatureChange::Detected),
+            ]
+        );
+        assert_eq!(processor.features(0).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::framer::driver::FeatureInterval;
+use adder_codec_core::{BigT, Coord, DeltaT};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// The position of a tracked feature at one interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    /// The end of the interval, in ticks
+    pub t: BigT,
+
+    /// The horizontal position, in pixels
+    pub x: f32,
+
+    /// The vertical position, in pixels
+    pub y: f32,
+
+    /// The estimated horizontal velocity, in pixels per second
+    pub vx: f32,
+
+    /// The estimated vertical velocity, in pixels per second
+    pub vy: f32,
+}
+
+/// A feature followed across intervals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    /// The unique ID of the track
+    pub id: u64,
+
+    /// The positions of the feature, in order of time
+    pub points: Vec<TrackPoint>,
+
+    /// The number of consecutive intervals, up to the latest, in which the feature was not found
+    #[serde(skip)]
+    missed: u32,
+}
+
+impl Track {
+    /// Get the time of the first point, in ticks
+    #[must_use]
+    pub fn start_t(&self) -> BigT {
+        self.points.first().map_or(0, |point| point.t)
+    }
+
+    /// Get the time of the last point, in ticks
+    #[must_use]
+    pub fn end_t(&self) -> BigT {
+        self.points.last().map_or(0, |point| point.t)
+    }
+
+    /// Get the number of ticks between the first and last points
+    #[must_use]
+    pub fn age(&self) -> BigT {
+        self.end_t() - self.start_t()
+    }
+
+    fn last(&self) -> &TrackPoint {
+        self.points.last().expect("tracks always have a point")
+    }
+
+    /// Predict the position of the feature at time `t`, assuming constant velocity
+    fn predict(&self, t: BigT, tps: DeltaT) -> (f32, f32) {
+        let last = self.last();
+        let dt = (t.saturating_sub(last.t)) as f32 / tps as f32;
+        (last.x + last.vx * dt, last.y + last.vy * dt)
+    }
+}
+
+/// Associates the features detected in successive intervals into persistent tracks.
+///
+/// Each track's position is predicted with its velocity, and is matched to the nearest unmatched
+/// feature within the gating distance, closest pairs first. Features which are not matched start
+/// new tracks. A track ends once it has gone unmatched for more than the allowed number of
+/// intervals.
+pub struct FeatureTracker {
+    tps: DeltaT,
+    max_distance: f32,
+    max_missed: u32,
+
+    /// The weight of the newest velocity measurement, in `(0, 1]`
+    velocity_smoothing: f32,
+
+    next_id: u64,
+    active: Vec<Track>,
+    finished: Vec<Track>,
+}
+
+impl FeatureTracker {
+    /// Create a tracker for a stream with the given ticks per second. By default, a feature must
+    /// be within 5 pixels of a track's predicted position to continue it, and a track ends after
+    /// 2 intervals without a match.
+    #[must_use]
+    pub fn new(tps: DeltaT) -> Self {
+        FeatureTracker {
+            tps: tps.max(1),
+            max_distance: 5.0,
+            max_missed: 2,
+            velocity_smoothing: 0.5,
+            next_id: 0,
+            active: Vec::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    /// Set the maximum distance, in pixels, between a track's predicted position and a feature
+    /// which continues it
+    #[must_use]
+    pub fn max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Set the number of consecutive intervals a track may go unmatched before it ends
+    #[must_use]
+    pub fn max_missed(mut self, max_missed: u32) -> Self {
+        self.max_missed = max_missed;
+        self
+    }
+
+    /// Set the weight of the newest velocity measurement in each track's velocity, in `(0, 1]`
+    #[must_use]
+    pub fn velocity_smoothing(mut self, velocity_smoothing: f32) -> Self {
+        self.velocity_smoothing = velocity_smoothing.clamp(f32::EPSILON, 1.0);
+        self
+    }
+
+    /// Get the tracks which are still active
+    #[must_use]
+    pub fn active_tracks(&self) -> &[Track] {
+        &self.active
+    }
+
+    /// Update the tracks with the features of an interval popped from a
+    /// [`FrameSequence`](crate::framer::driver::FrameSequence)
+    pub fn ingest_interval(&mut self, interval: &FeatureInterval) {
+        self.update(interval.end_ts(), &interval.features);
+    }
+
+    /// Update the tracks with the features detected in the interval ending at time `t`, in ticks.
+    /// Repeated coordinates are ignored.
+    pub fn update(&mut self, t: BigT, features: &[Coord]) {
+        let mut seen = HashSet::new();
+        let features: Vec<(f32, f32)> = features
+            .iter()
+            .filter(|coord| seen.insert((coord.x, coord.y)))
+            .map(|coord| (f32::from(coord.x), f32::from(coord.y)))
+            .collect();
+
+        // Find all candidate pairs within the gating distance, closest first
+        let mut pairs = Vec::new();
+        for (track_idx, track) in self.active.iter().enumerate() {
+            let (px, py) = track.predict(t, self.tps);
+            for (feature_idx, (fx, fy)) in features.iter().enumerate() {
+                let distance = (fx - px).hypot(fy - py);
+                if distance <= self.max_distance {
+                    pairs.push((distance, track_idx, feature_idx));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut track_matched = vec![false; self.active.len()];
+        let mut feature_matched = vec![false; features.len()];
+        for (_, track_idx, feature_idx) in pairs {
+            if track_matched[track_idx] || feature_matched[feature_idx] {
+                continue;
+            }
+            track_matched[track_idx] = true;
+            feature_matched[feature_idx] = true;
+
+            let track = &mut self.active[track_idx];
+            let last = *track.last();
+            let (x, y) = features[feature_idx];
+            let dt = (t.saturating_sub(last.t)) as f32 / self.tps as f32;
+            let (vx, vy) = if dt > 0.0 {
+                let alpha = self.velocity_smoothing;
+                (
+                    alpha * (x - last.x) / dt + (1.0 - alpha) * last.vx,
+                    alpha * (y - last.y) / dt + (1.0 - alpha) * last.vy,
+                )
+            } else {
+                (last.vx, last.vy)
+            };
+            track.points.push(TrackPoint { t, x, y, vx, vy });
+            track.missed = 0;
+        }
+
+        // End the tracks which have gone unmatched for too long
+        for (track, matched) in self.active.iter_mut().zip(track_matched) {
+            if !matched {
+                track.missed += 1;
+            }
+        }
+        let max_missed = self.max_missed;
+        let (active, ended): (Vec<Track>, Vec<Track>) = std::mem::take(&mut self.active)
+            .into_iter()
+            .partition(|track| track.missed <= max_missed);
+        self.active = active;
+        self.finished.extend(ended);
+
+        for (feature_idx, (x, y)) in features.iter().enumerate() {
+            if !feature_matched[feature_idx] {
+                self.active.push(Track {
+                    id: self.next_id,
+                    points: vec![TrackPoint {
+                        t,
+                        x: *x,
+                        y: *y,
+                        vx: 0.0,
+                        vy: 0.0,
+                    }],
+                    missed: 0,
+                });
+                self.next_id += 1;
+            }
+        }
+    }
+
+    /// End all active tracks, and return every track in order of ID
+    #[must_use]
+    pub fn finish(mut self) -> Vec<Track> {
+        self.finished.append(&mut self.active);
+        self.finished.sort_by_key(|track| track.id);
+        self.finished
+    }
+}
+
+/// Write tracks as CSV, with one row per track point
+///
+/// # Errors
+/// Returns an error if the tracks cannot be written.
+pub fn write_tracks_csv<W: Write>(writer: &mut W, tracks: &[Track], tps: DeltaT) -> io::Result<()> {
+    writeln!(writer, "track_id,t,seconds,x,y,vx,vy")?;
+    for track in tracks {
+        for point in &track.points {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                track.id,
+                point.t,
+                point.t as f64 / f64::from(tps),
+                point.x,
+                point.y,
+                point.vx,
+                point.vy
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write tracks as a JSON array
+///
+/// # Errors
+/// Returns an error if the tracks cannot be written.
+pub fn write_tracks_json<W: Write>(writer: &mut W, tracks: &[Track]) -> io::Result<()> {
+    serde_json::to_writer_pretty(writer, tracks).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(x: u16, y: u16) -> Coord {
+        Coord { x, y, c: None }
+    }
+
+    #[test]
+    fn tracks_moving_features() {
+        let mut tracker = FeatureTracker::new(1000).max_missed(0);
+        for i in 0..3 {
+            let mut features = vec![coord(10 + 2 * i, 10), coord(30, 20 + 2 * i)];
+            if i == 0 {
+                features.push(coord(50, 50));
+                features.push(coord(10, 10));
+            }
+            tracker.update(BigT::from(i + 1) * 100, &features);
+        }
+        assert_eq!(tracker.active_tracks().len(), 2);
+
+        let tracks = tracker.finish();
+        assert_eq!(
+            tracks.iter().map(|track| track.id).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(tracks[0].points.len(), 3);
+        assert_eq!(tracks[0].age(), 200);
+        assert_eq!(tracks[0].points[1].vx, 10.0);
+        assert_eq!(tracks[0].points[2].vx, 15.0);
+        assert_eq!(tracks[1].points[2].vy, 15.0);
+        assert_eq!(tracks[2].points.len(), 1);
+
+        let mut csv = Vec::new();
+        write_tracks_csv(&mut csv, &tracks, 1000).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("track_id,t,seconds,x,y,vx,vy"));
+        assert_eq!(lines.next(), Some("0,100,0.1,10,10,0,0"));
+        assert_eq!(lines.count(), 6);
+
+        let mut json = Vec::new();
+        write_tracks_json(&mut json, &tracks).unwrap();
+        let parsed: Vec<Track> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].points, tracks[0].points);
+    }
+}
@@ -0,0 +1,71 @@
+use adder_codec_core::bitstream_io::{BigEndian, BitReader};
+use adder_codec_core::codec::compressed::stream::CompressedInput;
+use adder_codec_core::codec::decoder::Decoder;
+use adder_codec_core::codec::raw::stream::RawInput;
+use adder_codec_core::codec::CodecError;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+/// Wraps a reader to keep track of how many bytes into the stream it is, without the cost of
+/// seeking the underlying file
+pub struct CountingReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R> CountingReader<R> {
+    /// The position of the next byte to read
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+pub type FileDecoder = Decoder<CountingReader<BufReader<File>>>;
+pub type FileBitReader = BitReader<CountingReader<BufReader<File>>, BigEndian>;
+
+/// Open a raw or compressed ADΔER file, like [`adder_codec_core::open_file_decoder`], but with a
+/// reader that tracks its byte position
+pub fn open_counting_decoder(file_path: &str) -> Result<(FileDecoder, FileBitReader), CodecError> {
+    let open = || -> io::Result<FileBitReader> {
+        Ok(BitReader::endian(
+            CountingReader {
+                inner: BufReader::new(File::open(file_path)?),
+                position: 0,
+            },
+            BigEndian,
+        ))
+    };
+
+    // First try opening the file as a raw file, then try as a compressed file
+    let mut bitreader = open()?;
+    match Decoder::new_raw(RawInput::new(), &mut bitreader) {
+        Ok(decoder) => Ok((decoder, bitreader)),
+        Err(CodecError::WrongMagic) => {
+            let mut bitreader = open()?;
+            let decoder = Decoder::new_compressed(CompressedInput::new(0, 0, 0), &mut bitreader)?;
+            Ok((decoder, bitreader))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the byte position of a reader, if it's at a byte boundary
+pub fn byte_position(bitreader: &mut FileBitReader) -> Option<u64> {
+    bitreader.reader().map(|reader| reader.position())
+}
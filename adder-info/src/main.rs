@@ -1,9 +1,14 @@
-use crate::TimeMode::AbsoluteT;
+mod input;
+mod statistics;
+mod verify;
+
+use crate::input::open_counting_decoder;
+use crate::statistics::{gather_statistics, DynamicRange, StreamStatistics};
+use crate::verify::verify_file;
+use adder_codec_core::codec::EncoderType;
 use adder_codec_core::*;
-use adder_codec_rs::framer::scale_intensity::event_to_intensity;
-use adder_codec_rs::utils::stream_migration::absolute_event_to_dt_event;
 use clap::Parser;
-use ndarray::Array3;
+use serde::Serialize;
 use std::io::Write;
 use std::path::Path;
 use std::{error, io};
@@ -19,142 +24,256 @@ pub struct MyArgs {
     /// Calculate dynamic range of the event stream? (Takes more time)
     #[clap(short, long, action)]
     pub(crate) dynamic_range: bool,
+
+    /// Decode every event to gather statistics of the stream? (Takes more time, but is always
+    /// done for compressed files)
+    #[clap(short, long, action)]
+    pub(crate) statistics: bool,
+
+    /// Print the information as JSON
+    #[clap(short, long, action)]
+    pub(crate) json: bool,
+
+    /// Decode the entire stream and check that it's valid, instead of printing its information.
+    /// Exits with an error code if it isn't. A compressed stream cut off exactly at an ADU
+    /// boundary can't be detected, so the end time of its last ADU is printed to compare against
+    /// the expected duration.
+    #[clap(short, long, action)]
+    pub(crate) verify: bool,
+}
+
+/// Everything we know about an ADΔER file
+#[derive(Serialize)]
+struct InfoReport {
+    width: u16,
+    height: u16,
+    channels: u8,
+    source_camera: SourceCamera,
+    codec_version: u8,
+    time_mode: TimeMode,
+    tps: DeltaT,
+    ref_interval: DeltaT,
+    delta_t_max: DeltaT,
+    compression: EncoderType,
+    file_size: u64,
+    header_size: usize,
+    event_count: u64,
+    events_per_pixel_channel: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    statistics: Option<StreamStatistics>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dynamic_range: Option<DynamicRange>,
 }
 
 fn main() -> Result<(), Box<dyn error::Error>> {
     let args: MyArgs = MyArgs::parse();
-    adder_info(args, io::stdout())?;
+    if args.verify {
+        if !adder_verify(args, io::stdout())? {
+            std::process::exit(1);
+        }
+    } else {
+        adder_info(args, io::stdout())?;
+    }
     Ok(())
 }
 
-fn adder_info(args: MyArgs, out: impl Write) -> Result<(), Box<dyn error::Error>> {
-    let file_path = args.input.as_str();
-    let (mut stream, mut bitreader) = open_file_decoder(file_path)?;
+/// Check the stream against the invariants of the format. Returns whether it's valid.
+fn adder_verify(args: MyArgs, out: impl Write) -> Result<bool, Box<dyn error::Error>> {
+    let file_size = Path::new(&args.input).metadata()?.len();
+    let report = verify_file(&args.input, file_size);
 
-    let first_event_position = stream.get_input_stream_position(&mut bitreader)?;
+    let mut handle = io::BufWriter::new(out);
+    if args.json {
+        serde_json::to_writer_pretty(&mut handle, &report)?;
+        writeln!(handle)?;
+    } else {
+        match &report.violation {
+            None => {
+                writeln!(handle, "Valid: {} events checked", report.events_checked)?;
+                if let Some(last_adu_end) = report.last_adu_end {
+                    writeln!(handle, "\tLast ADU ends at: {last_adu_end:.3} s")?;
+                }
+            }
+            Some(violation) => {
+                writeln!(handle, "Invalid: {}", violation.message)?;
+                writeln!(handle, "\tByte offset: {}", violation.offset)?;
+                if let Some(event_index) = violation.event_index {
+                    writeln!(handle, "\tEvent index: {event_index}")?;
+                }
+                if let Some(event) = violation.event {
+                    writeln!(handle, "\tEvent: {event:?}")?;
+                }
+            }
+        }
+    }
+    handle.flush()?;
 
-    let eof_position_bytes = stream.get_eof_position(&mut bitreader)?;
+    Ok(report.valid)
+}
+
+fn adder_info(args: MyArgs, out: impl Write) -> Result<(), Box<dyn error::Error>> {
+    let file_path = args.input.as_str();
+    let (mut stream, mut bitreader) = open_counting_decoder(file_path)?;
     let file_size = Path::new(file_path).metadata()?.len();
 
     let meta = *stream.meta();
-
-    // TODO: Need a different mechanism for compressed files
-    let num_events = (eof_position_bytes - 1 - meta.header_size as u64) / meta.event_size as u64;
-    let events_per_px = num_events / meta.plane.volume() as u64;
+    let compression = stream.get_compression_type();
 
     let mut handle = io::BufWriter::new(out);
 
+    // Raw events have a fixed size, so we can count them without decoding them (not counting
+    // the EOF event). Compressed events must be decoded.
+    let num_events = match compression {
+        EncoderType::Raw => Some(
+            (file_size.saturating_sub(meta.header_size as u64) / meta.event_size as u64)
+                .saturating_sub(1),
+        ),
+        _ => None,
+    };
+
+    let statistics = if args.statistics || args.dynamic_range || num_events.is_none() {
+        let progress: Option<(u64, &mut dyn Write)> = match num_events {
+            Some(num_events) if !args.json => Some((num_events, &mut handle)),
+            _ => None,
+        };
+        Some(gather_statistics(&mut stream, &mut bitreader, progress)?)
+    } else {
+        None
+    };
+    let event_count = match &statistics {
+        Some(statistics) => statistics.event_count,
+        None => num_events.unwrap_or(0),
+    };
+
+    let report = InfoReport {
+        width: meta.plane.w(),
+        height: meta.plane.h(),
+        channels: meta.plane.c(),
+        source_camera: meta.source_camera,
+        codec_version: meta.codec_version,
+        time_mode: meta.time_mode,
+        tps: meta.tps,
+        ref_interval: meta.ref_interval,
+        delta_t_max: meta.delta_t_max,
+        compression,
+        file_size,
+        header_size: meta.header_size,
+        event_count,
+        events_per_pixel_channel: event_count / meta.plane.volume() as u64,
+        dynamic_range: match &statistics {
+            Some(statistics) if args.dynamic_range => Some(statistics.dynamic_range(&meta)),
+            _ => None,
+        },
+        statistics: statistics
+            .filter(|_| args.statistics || compression == EncoderType::Compressed),
+    };
+
+    if args.json {
+        serde_json::to_writer_pretty(&mut handle, &report)?;
+        writeln!(handle)?;
+    } else {
+        write_report(&report, &mut handle)?;
+    }
+    handle.flush()?;
+
+    Ok(())
+}
+
+fn write_report(report: &InfoReport, handle: &mut impl Write) -> io::Result<()> {
     writeln!(handle, "Dimensions")?;
-    writeln!(handle, "\tWidth: {}", meta.plane.w())?;
-    writeln!(handle, "\tHeight: {}", meta.plane.h())?;
-    writeln!(handle, "\tColor channels: {}", meta.plane.c())?;
-    writeln!(handle, "Source camera: {:?}", meta.source_camera)?;
+    writeln!(handle, "\tWidth: {}", report.width)?;
+    writeln!(handle, "\tHeight: {}", report.height)?;
+    writeln!(handle, "\tColor channels: {}", report.channels)?;
+    writeln!(handle, "Source camera: {:?}", report.source_camera)?;
     writeln!(handle, "ADΔER transcoder parameters")?;
-    writeln!(handle, "\tCodec version: {}", meta.codec_version)?;
-    writeln!(handle, "\tTime mode: {:?}", meta.time_mode)?;
-    writeln!(handle, "\tTicks per second: {}", meta.tps)?;
+    writeln!(handle, "\tCodec version: {}", report.codec_version)?;
+    writeln!(handle, "\tTime mode: {:?}", report.time_mode)?;
+    writeln!(handle, "\tTicks per second: {}", report.tps)?;
     writeln!(
         handle,
         "\tReference ticks per source interval: {}",
-        meta.ref_interval
+        report.ref_interval
     )?;
-    writeln!(handle, "\tΔt_max: {}", meta.delta_t_max)?;
+    writeln!(handle, "\tΔt_max: {}", report.delta_t_max)?;
     writeln!(handle, "File metadata")?;
-    writeln!(handle, "\tFile size: {file_size}")?;
-    writeln!(handle, "\tHeader size: {0}", meta.header_size)?;
-    writeln!(handle, "\tADΔER event count: {num_events}")?;
-    writeln!(handle, "\tEvents per pixel channel: {events_per_px}")?;
-    handle.flush()?;
+    writeln!(handle, "\tFile size: {}", report.file_size)?;
+    writeln!(handle, "\tHeader size: {0}", report.header_size)?;
+    writeln!(handle, "\tCompression: {:?}", report.compression)?;
+    writeln!(handle, "\tADΔER event count: {}", report.event_count)?;
+    writeln!(
+        handle,
+        "\tEvents per pixel channel: {}",
+        report.events_per_pixel_channel
+    )?;
 
-    // Calculate the dynamic range of the events. That is, what is the highest intensity
-    // event, and what is the lowest intensity event?
-    if args.dynamic_range {
-        let divisor = num_events / 100;
-        stream.set_input_stream_position(&mut bitreader, first_event_position)?;
-        let mut max_intensity: Intensity = 0.0;
-        let mut min_intensity: Intensity = f64::MAX;
-        let mut event_count: u64 = 0;
-
-        // Setup time tracker for AbsoluteT mode
-        let data = vec![0_u32; meta.plane.volume()];
-
-        let mut t_tree: Array3<DeltaT> = Array3::from_shape_vec(
-            (
-                meta.plane.h_usize(),
-                meta.plane.w_usize(),
-                meta.plane.c_usize(),
-            ),
-            data,
+    if let Some(statistics) = &report.statistics {
+        writeln!(handle, "Statistics")?;
+        if let (Some(min_t), Some(max_t)) = (statistics.min_t, statistics.max_t) {
+            writeln!(handle, "\tTimestamps: {min_t} to {max_t} ticks")?;
+        }
+        writeln!(handle, "\tDuration: {:.3} s", statistics.duration)?;
+        let per_second = &statistics.events_per_second;
+        if !per_second.is_empty() {
+            writeln!(
+                handle,
+                "\tEvents per second: min {}, mean {:.1}, max {}",
+                per_second.iter().min().unwrap_or(&0),
+                per_second.iter().sum::<u64>() as f64 / per_second.len() as f64,
+                per_second.iter().max().unwrap_or(&0)
+            )?;
+        }
+        writeln!(
+            handle,
+            "\tEvents per channel: {:?}",
+            statistics.channel_counts
         )?;
-
-        while let Ok(mut event) = stream.digest_event(&mut bitreader) {
-            if meta.codec_version >= 2 && meta.time_mode == AbsoluteT {
-                let last_t = &mut t_tree[[
-                    event.coord.y_usize(),
-                    event.coord.x_usize(),
-                    event.coord.c_usize(),
-                ]];
-                let new_t = event.t;
-                event = absolute_event_to_dt_event(event, *last_t);
-                *last_t = new_t;
+        writeln!(handle, "\tD histogram:")?;
+        for (d, count) in &statistics.d_histogram {
+            match *d {
+                D_ZERO_INTEGRATION => writeln!(handle, "\t\tD_ZERO_INTEGRATION: {count}")?,
+                D_EMPTY => writeln!(handle, "\t\tD_EMPTY: {count}")?,
+                d => writeln!(handle, "\t\t{d}: {count}")?,
             }
-
-            match event_to_intensity(&event) {
-                _ if event.d == D_EMPTY => {
-                    // ignore empty events
-                }
-                a if a.is_infinite() => {
-                    println!("INFINITE");
-                    dbg!(event);
-                }
-                a if a < min_intensity => {
-                    if event.d == D_ZERO_INTEGRATION {
-                        min_intensity = 1.0 / event.t as f64;
-                    } else {
-                        min_intensity = a;
-                    }
-                }
-                a if a > max_intensity => {
-                    max_intensity = a;
-                }
-                _ => {}
-            }
-
-            event_count += 1;
-            if event_count % divisor == 0 {
-                write!(
-                    handle,
-                    "\rCalculating dynamic range...{}%",
-                    (event_count * 100) / num_events
-                )?;
-                handle.flush()?;
+        }
+        if let Some(adus) = &statistics.adus {
+            writeln!(handle, "\tADU count: {}", adus.count)?;
+            writeln!(
+                handle,
+                "\tADU size: min {} B, mean {:.1} B, max {} B",
+                adus.min_bytes, adus.mean_bytes, adus.max_bytes
+            )?;
+            writeln!(handle, "\tADU size histogram:")?;
+            for (bin, count) in &adus.size_histogram {
+                writeln!(handle, "\t\t{bin}-{} B: {count}", bin * 2 - 1)?;
             }
         }
+    }
 
-        let theory_dr_ratio = D_SHIFT[D_SHIFT.len() - 1] as f64 / (1.0 / meta.delta_t_max as f64);
-        let theory_dr_db = 10.0 * theory_dr_ratio.log10();
-        let theory_dr_bits = theory_dr_ratio.log2();
-        writeln!(handle, "\rDynamic range                       ")?;
+    // The dynamic range of the events. That is, what is the highest intensity event, and what
+    // is the lowest intensity event?
+    if let Some(dynamic_range) = &report.dynamic_range {
+        writeln!(handle, "Dynamic range")?;
         writeln!(handle, "\tTheoretical range:")?;
-        writeln!(handle, "\t\t{theory_dr_db:.4} dB (power)")?;
-        writeln!(handle, "\t\t{theory_dr_bits:.4} bits")?;
-
-        let real_dr_ratio = max_intensity / min_intensity;
-        let real_dr_db = 10.0 * real_dr_ratio.log10();
-        let real_dr_bits = real_dr_ratio.log2();
+        writeln!(handle, "\t\t{:.4} dB (power)", dynamic_range.theoretical_db)?;
+        writeln!(handle, "\t\t{:.4} bits", dynamic_range.theoretical_bits)?;
         writeln!(handle, "\tRealized range:")?;
-        writeln!(handle, "\t\t{real_dr_db:.4} dB (power)")?;
-        writeln!(handle, "\t\t{real_dr_bits:.4} bits")?;
+        writeln!(handle, "\t\t{:.4} dB (power)", dynamic_range.realized_db)?;
+        writeln!(handle, "\t\t{:.4} bits", dynamic_range.realized_bits)?;
     }
 
-    handle.flush()?;
-
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{adder_info, MyArgs};
+    use crate::{adder_info, adder_verify, MyArgs};
+    use adder_codec_core::codec::compressed::stream::CompressedOutput;
+    use adder_codec_core::codec::encoder::Encoder;
+    use adder_codec_core::codec::raw::stream::RawOutput;
+    use adder_codec_core::codec::{CodecMetadata, EncoderOptions, LATEST_CODEC_VERSION};
+    use adder_codec_core::{Coord, Event, PlaneSize, SourceCamera, TimeMode};
     use std::io::Cursor;
 
     #[test]
@@ -162,6 +281,7 @@ mod tests {
         let args = MyArgs {
             input: "./tests/test_sample.adder".to_string(),
             dynamic_range: true,
+            ..Default::default()
         };
 
         let mut data = Vec::new();
@@ -183,9 +303,182 @@ mod tests {
         assert!(string.contains("t_max: 240000"));
         assert!(string.contains("File size: 1307"));
         assert!(string.contains("Header size: 29"));
-        assert!(string.contains("event count: 137"));
-        assert!(string.contains("Events per pixel channel: 34"));
+        assert!(string.contains("event count: 141"));
+        assert!(string.contains("Events per pixel channel: 35"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adder_info_json() -> Result<(), Box<dyn std::error::Error>> {
+        let args = MyArgs {
+            input: "./tests/test_sample.adder".to_string(),
+            statistics: true,
+            json: true,
+            ..Default::default()
+        };
+
+        let mut data = Vec::new();
+        adder_info(args, Cursor::new(&mut data))?;
+        let info: serde_json::Value = serde_json::from_slice(&data)?;
+
+        assert_eq!(info["width"], 2);
+        assert_eq!(info["source_camera"], "FramedU8");
+        assert_eq!(info["compression"], "Raw");
+        assert_eq!(info["event_count"], 141);
+
+        let statistics = &info["statistics"];
+        assert_eq!(statistics["event_count"], 141);
+        assert_eq!(statistics["min_t"], 107225);
+        assert_eq!(statistics["max_t"], 1192248);
+        assert_eq!(
+            statistics["events_per_second"].as_array().unwrap().len(),
+            10
+        );
+        assert_eq!(statistics["channel_counts"], serde_json::json!([141]));
+        assert_eq!(statistics["d_histogram"]["7"], 39);
+        assert!(statistics.get("adus").is_none());
+        assert!(info.get("dynamic_range").is_none());
+
+        Ok(())
+    }
+
+    /// Verify a copy of the sample file, with `modify` applied to its bytes
+    fn verify_modified_sample(
+        name: &str,
+        modify: impl FnOnce(&mut Vec<u8>),
+    ) -> Result<(bool, String), Box<dyn std::error::Error>> {
+        let mut bytes = std::fs::read("./tests/test_sample.adder")?;
+        modify(&mut bytes);
+        verify_bytes(name, bytes)
+    }
+
+    /// Encode the given events as a 2x2 AbsoluteT stream, and verify it
+    fn verify_encoded(
+        name: &str,
+        compressed: bool,
+        events: &[Event],
+    ) -> Result<(bool, String), Box<dyn std::error::Error>> {
+        let plane = PlaneSize::new(2, 2, 1)?;
+        let meta = CodecMetadata {
+            codec_version: LATEST_CODEC_VERSION,
+            header_size: 0,
+            time_mode: TimeMode::AbsoluteT,
+            plane,
+            tps: 1000,
+            ref_interval: 100,
+            delta_t_max: 200,
+            event_size: 0,
+            source_camera: SourceCamera::FramedU8,
+            adu_interval: 1,
+        };
+        let options = EncoderOptions::default(plane);
+        let bytes = if compressed {
+            let mut encoder =
+                Encoder::new_compressed(CompressedOutput::new(meta, Vec::new()), options);
+            encoder.ingest_events(events)?;
+            encoder.close_writer()?
+        } else {
+            let mut encoder = Encoder::new_raw(RawOutput::new(meta, Vec::new()), options);
+            encoder.ingest_events(events)?;
+            encoder.close_writer()?
+        };
+        verify_bytes(name, bytes.ok_or("The encoder has no output")?)
+    }
+
+    fn verify_bytes(
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(bool, String), Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, bytes)?;
+
+        let args = MyArgs {
+            input: path.to_str().unwrap().to_string(),
+            verify: true,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        let valid = adder_verify(args, Cursor::new(&mut data))?;
+        std::fs::remove_file(&path)?;
+        Ok((valid, String::from_utf8(data)?))
+    }
+
+    #[test]
+    fn test_verify_valid() -> Result<(), Box<dyn std::error::Error>> {
+        let (valid, string) = verify_modified_sample("adder_info_valid.adder", |_| {})?;
+        assert!(valid);
+        assert!(string.contains("Valid: 141 events checked"));
+        Ok(())
+    }
 
+    #[test]
+    fn test_verify_truncated() -> Result<(), Box<dyn std::error::Error>> {
+        // Cut the file off in the middle of the EOF event
+        let (valid, string) = verify_modified_sample("adder_info_truncated.adder", |bytes| {
+            bytes.truncate(bytes.len() - 5)
+        })?;
+        assert!(!valid);
+        assert!(string.contains("truncated or malformed"));
+        assert!(string.contains("Byte offset: 1298"));
+        assert!(string.contains("Event index: 141"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_out_of_plane() -> Result<(), Box<dyn std::error::Error>> {
+        // Move the fourth event's x coordinate outside the 2x2 plane
+        let (valid, string) = verify_modified_sample("adder_info_out_of_plane.adder", |bytes| {
+            bytes[29 + 3 * 9 + 1] = 5
+        })?;
+        assert!(!valid);
+        assert!(string.contains("Coordinate (5, "));
+        assert!(string.contains("Byte offset: 56"));
+        assert!(string.contains("Event index: 3"));
+        Ok(())
+    }
+
+    fn event(x: u16, y: u16, t: u32) -> Event {
+        Event {
+            coord: Coord { x, y, c: None },
+            d: 7,
+            t,
+        }
+    }
+
+    #[test]
+    fn test_verify_absolute_t() -> Result<(), Box<dyn std::error::Error>> {
+        // Pixel (1, 1) first fires more than delta_t_max after the start of the stream
+        let events = [
+            event(0, 0, 100),
+            event(0, 0, 200),
+            event(1, 1, 250),
+            event(1, 1, 300),
+        ];
+        let (valid, string) = verify_encoded("adder_info_absolute_t.adder", false, &events)?;
+        assert!(valid);
+        assert!(string.contains("Valid: 4 events checked"));
+
+        let events = [event(0, 0, 100), event(0, 0, 200), event(0, 0, 150)];
+        let (valid, string) = verify_encoded("adder_info_decreasing_t.adder", false, &events)?;
+        assert!(!valid);
+        assert!(string.contains("Timestamp 150 is earlier than the pixel's previous timestamp 200"));
+        assert!(string.contains("Event index: 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_compressed() -> Result<(), Box<dyn std::error::Error>> {
+        let mut events = Vec::new();
+        for t in (100..=1000).step_by(100) {
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                events.push(event(x, y, t));
+            }
+        }
+        let (valid, string) = verify_encoded("adder_info_compressed.adder", true, &events)?;
+        assert!(valid, "{string}");
+        assert!(string.contains("Valid: "));
+        assert!(string.contains("Last ADU ends at: "));
         Ok(())
     }
 }
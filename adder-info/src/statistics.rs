@@ -0,0 +1,216 @@
+use crate::input::{byte_position, FileBitReader, FileDecoder};
+use adder_codec_core::codec::{CodecMetadata, EncoderType};
+use adder_codec_core::{AbsoluteT, Intensity, TimeMode, D, D_EMPTY, D_SHIFT, D_ZERO_INTEGRATION};
+use adder_codec_rs::framer::scale_intensity::event_to_intensity;
+use adder_codec_rs::utils::stream_migration::absolute_event_to_dt_event;
+use ndarray::Array3;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+
+/// Statistics of a stream, gathered by decoding every event
+#[derive(Serialize)]
+pub struct StreamStatistics {
+    /// The number of events, not counting the EOF event
+    pub event_count: u64,
+
+    /// The earliest absolute timestamp of an event, in ticks
+    pub min_t: Option<AbsoluteT>,
+
+    /// The latest absolute timestamp of an event, in ticks
+    pub max_t: Option<AbsoluteT>,
+
+    /// The duration of the stream, in seconds
+    pub duration: f64,
+
+    /// The number of events with timestamps in each second of the stream
+    pub events_per_second: Vec<u64>,
+
+    /// The number of events with each `D` value, including `D_ZERO_INTEGRATION` and `D_EMPTY`
+    pub d_histogram: BTreeMap<D, u64>,
+
+    /// The number of events in each color channel
+    pub channel_counts: Vec<u64>,
+
+    /// The sizes of the ADUs of a compressed stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adus: Option<AduStatistics>,
+
+    #[serde(skip)]
+    min_intensity: Intensity,
+
+    #[serde(skip)]
+    max_intensity: Intensity,
+}
+
+/// The size distribution of the ADUs (arithmetic-coded data units) of a compressed stream. An ADU
+/// with no events is counted as part of the next one.
+#[derive(Serialize)]
+pub struct AduStatistics {
+    pub count: u64,
+    pub min_bytes: u64,
+    pub max_bytes: u64,
+    pub mean_bytes: f64,
+
+    /// The number of ADUs in each power-of-two size range, keyed by its lower bound in bytes
+    pub size_histogram: BTreeMap<u64, u64>,
+}
+
+/// The range of intensities that a stream can represent, and the range that it does represent
+#[derive(Serialize)]
+pub struct DynamicRange {
+    pub theoretical_db: f64,
+    pub theoretical_bits: f64,
+    pub realized_db: f64,
+    pub realized_bits: f64,
+}
+
+/// Decode the rest of a stream to gather its statistics. If `progress` is given, with the expected
+/// number of events, the percentage decoded is written to it as we go.
+pub fn gather_statistics(
+    stream: &mut FileDecoder,
+    bitreader: &mut FileBitReader,
+    mut progress: Option<(u64, &mut dyn Write)>,
+) -> io::Result<StreamStatistics> {
+    let meta = *stream.meta();
+    let absolute_t = meta.codec_version >= 2 && meta.time_mode == TimeMode::AbsoluteT;
+    let compressed = stream.get_compression_type() == EncoderType::Compressed;
+
+    let mut stats = StreamStatistics {
+        event_count: 0,
+        min_t: None,
+        max_t: None,
+        duration: 0.0,
+        events_per_second: Vec::new(),
+        d_histogram: BTreeMap::new(),
+        channel_counts: vec![0; meta.plane.c_usize()],
+        adus: None,
+        min_intensity: f64::MAX,
+        max_intensity: 0.0,
+    };
+    let mut adu_sizes = Vec::new();
+    let mut last_position = byte_position(bitreader);
+    let divisor = progress
+        .as_ref()
+        .map_or(1, |(expected, _)| (expected / 100).max(1));
+
+    // The absolute timestamp of the last event of each pixel
+    let mut t_tree: Array3<AbsoluteT> = Array3::zeros((
+        meta.plane.h_usize(),
+        meta.plane.w_usize(),
+        meta.plane.c_usize(),
+    ));
+
+    while let Ok(mut event) = stream.digest_event(bitreader) {
+        if compressed {
+            // The stream only moves forward when the decoder reads a new ADU
+            let position = byte_position(bitreader);
+            if let (Some(last), Some(position)) = (last_position, position) {
+                if position > last {
+                    adu_sizes.push(position - last);
+                }
+            }
+            last_position = position;
+        }
+
+        let last_t = &mut t_tree[[
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ]];
+        let t = if absolute_t {
+            let t = event.t;
+            event = absolute_event_to_dt_event(event, *last_t);
+            t
+        } else {
+            last_t.saturating_add(event.t)
+        };
+        *last_t = t;
+
+        stats.event_count += 1;
+        stats.min_t = Some(stats.min_t.map_or(t, |min_t| min_t.min(t)));
+        stats.max_t = Some(stats.max_t.map_or(t, |max_t| max_t.max(t)));
+        let second = (t / meta.tps.max(1)) as usize;
+        if second >= stats.events_per_second.len() {
+            stats.events_per_second.resize(second + 1, 0);
+        }
+        stats.events_per_second[second] += 1;
+        *stats.d_histogram.entry(event.d).or_insert(0) += 1;
+        if let Some(count) = stats.channel_counts.get_mut(event.coord.c_usize()) {
+            *count += 1;
+        }
+
+        match event_to_intensity(&event) {
+            _ if event.d == D_EMPTY => {
+                // ignore empty events
+            }
+            a if a.is_infinite() => {}
+            a if a < stats.min_intensity => {
+                if event.d == D_ZERO_INTEGRATION {
+                    stats.min_intensity = 1.0 / event.t as f64;
+                } else {
+                    stats.min_intensity = a;
+                }
+            }
+            a if a > stats.max_intensity => {
+                stats.max_intensity = a;
+            }
+            _ => {}
+        }
+
+        if let Some((expected, handle)) = &mut progress {
+            if stats.event_count % divisor == 0 {
+                write!(
+                    handle,
+                    "\rDecoding events...{}%",
+                    (stats.event_count * 100) / (*expected).max(1)
+                )?;
+                handle.flush()?;
+            }
+        }
+    }
+    if let Some((_, handle)) = &mut progress {
+        write!(handle, "\r                             \r")?;
+    }
+
+    stats.duration = stats.max_t.unwrap_or(0) as f64 / meta.tps.max(1) as f64;
+    if compressed {
+        stats.adus = Some(AduStatistics::new(&adu_sizes));
+    }
+    Ok(stats)
+}
+
+impl StreamStatistics {
+    pub fn dynamic_range(&self, meta: &CodecMetadata) -> DynamicRange {
+        let theory_dr_ratio = D_SHIFT[D_SHIFT.len() - 1] as f64 / (1.0 / meta.delta_t_max as f64);
+        let real_dr_ratio = self.max_intensity / self.min_intensity;
+        DynamicRange {
+            theoretical_db: 10.0 * theory_dr_ratio.log10(),
+            theoretical_bits: theory_dr_ratio.log2(),
+            realized_db: 10.0 * real_dr_ratio.log10(),
+            realized_bits: real_dr_ratio.log2(),
+        }
+    }
+}
+
+impl AduStatistics {
+    fn new(sizes: &[u64]) -> Self {
+        let mut size_histogram = BTreeMap::new();
+        for size in sizes {
+            let bin = 1 << (u64::BITS - 1 - size.leading_zeros());
+            *size_histogram.entry(bin).or_insert(0) += 1;
+        }
+        AduStatistics {
+            count: sizes.len() as u64,
+            min_bytes: sizes.iter().copied().min().unwrap_or(0),
+            max_bytes: sizes.iter().copied().max().unwrap_or(0),
+            mean_bytes: if sizes.is_empty() {
+                0.0
+            } else {
+                sizes.iter().sum::<u64>() as f64 / sizes.len() as f64
+            },
+            size_histogram,
+        }
+    }
+}
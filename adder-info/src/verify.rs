@@ -0,0 +1,274 @@
+use crate::input::{byte_position, open_counting_decoder, FileBitReader, FileDecoder};
+use adder_codec_core::codec::{CodecError, CodecMetadata, EncoderType};
+use adder_codec_core::{AbsoluteT, Event, TimeMode, D_EMPTY, D_MAX, D_ZERO_INTEGRATION};
+use ndarray::Array3;
+use serde::Serialize;
+
+/// The result of checking a stream against the invariants of the format
+#[derive(Serialize)]
+pub struct VerifyReport {
+    pub valid: bool,
+
+    /// The number of events which passed the checks
+    pub events_checked: u64,
+
+    /// For a valid compressed stream, the time at which its last ADU ends, in seconds. A
+    /// compressed stream has no EOF event, so one cut off at an ADU boundary still looks valid.
+    /// Compare this to the expected duration to catch that.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_adu_end: Option<f64>,
+
+    /// The first invariant which the stream breaks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub violation: Option<Violation>,
+}
+
+/// Where and how a stream breaks the invariants of the format
+#[derive(Serialize)]
+pub struct Violation {
+    /// The byte offset of the problem. For an event of a compressed stream, this is the offset of
+    /// the ADU containing it.
+    pub offset: u64,
+
+    /// The index of the offending event, counting from zero
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_index: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<Event>,
+
+    pub message: String,
+}
+
+impl VerifyReport {
+    fn fail(events_checked: u64, violation: Violation) -> Self {
+        VerifyReport {
+            valid: false,
+            events_checked,
+            last_adu_end: None,
+            violation: Some(violation),
+        }
+    }
+}
+
+/// Decode an entire raw or compressed file, and check its header and every event. Stops at the
+/// first violation.
+pub fn verify_file(file_path: &str, file_size: u64) -> VerifyReport {
+    let (mut stream, mut bitreader) = match open_counting_decoder(file_path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            return VerifyReport::fail(
+                0,
+                Violation {
+                    offset: 0,
+                    event_index: None,
+                    event: None,
+                    message: format!("Couldn't decode the header: {e}"),
+                },
+            )
+        }
+    };
+    verify_stream(&mut stream, &mut bitreader, file_size)
+}
+
+fn verify_stream(
+    stream: &mut FileDecoder,
+    bitreader: &mut FileBitReader,
+    file_size: u64,
+) -> VerifyReport {
+    let meta = *stream.meta();
+    let compressed = stream.get_compression_type() == EncoderType::Compressed;
+    if let Err(message) = check_header(&meta, compressed) {
+        return VerifyReport::fail(
+            0,
+            Violation {
+                offset: 0,
+                event_index: None,
+                event: None,
+                message,
+            },
+        );
+    }
+
+    let absolute_t = meta.codec_version >= 2 && meta.time_mode == TimeMode::AbsoluteT;
+    // The timestamp of each pixel's last event, once it has fired
+    let mut last_t: Array3<Option<AbsoluteT>> = Array3::from_elem(
+        (
+            meta.plane.h_usize(),
+            meta.plane.w_usize(),
+            meta.plane.c_usize(),
+        ),
+        None,
+    );
+    let mut events_checked = 0;
+    let mut adu_offset = 0;
+    let mut max_t: Option<AbsoluteT> = None;
+
+    loop {
+        let before = byte_position(bitreader).unwrap_or(0);
+        let result = stream.digest_event(bitreader);
+        let after = byte_position(bitreader).unwrap_or(before);
+        if compressed && after > before {
+            adu_offset = before;
+        }
+        let offset = if compressed { adu_offset } else { before };
+        let violation = |message: String, event: Option<Event>| Violation {
+            offset,
+            event_index: Some(events_checked),
+            event,
+            message,
+        };
+
+        let event = match result {
+            Ok(event) => event,
+            Err(CodecError::Eof) if !compressed => {
+                if after != file_size {
+                    return VerifyReport::fail(
+                        events_checked,
+                        Violation {
+                            offset: after,
+                            event_index: None,
+                            event: None,
+                            message: format!(
+                                "{} bytes of data after the EOF event",
+                                file_size.saturating_sub(after)
+                            ),
+                        },
+                    );
+                }
+                break;
+            }
+            // A compressed stream ends after its last ADU, with no EOF event
+            Err(_) if compressed && before == file_size => break,
+            Err(e) => {
+                let message = if before == file_size {
+                    "The file ends without an EOF event".to_string()
+                } else if compressed {
+                    format!("The ADU at this offset is truncated or malformed ({e})")
+                } else {
+                    format!("The event at this offset is truncated or malformed ({e})")
+                };
+                return VerifyReport::fail(
+                    events_checked,
+                    Violation {
+                        offset: before,
+                        ..violation(message, None)
+                    },
+                );
+            }
+        };
+
+        if let Err(message) = check_event(&meta, absolute_t, &mut last_t, event) {
+            return VerifyReport::fail(events_checked, violation(message, Some(event)));
+        }
+        events_checked += 1;
+        max_t = max_t.max(Some(event.t));
+    }
+
+    // Each ADU spans a fixed number of ticks, so the last event falls in the last ADU
+    let adu_span = u64::from(meta.ref_interval) * meta.adu_interval as u64;
+    let last_adu_end = match max_t {
+        Some(max_t) if compressed && adu_span > 0 => {
+            let end_t = (u64::from(max_t) / adu_span + 1) * adu_span;
+            Some(end_t as f64 / f64::from(meta.tps))
+        }
+        _ => None,
+    };
+
+    VerifyReport {
+        valid: true,
+        events_checked,
+        last_adu_end,
+        violation: None,
+    }
+}
+
+fn check_header(meta: &CodecMetadata, compressed: bool) -> Result<(), String> {
+    let channels = meta.plane.c();
+    if channels != 1 && channels != 3 {
+        return Err(format!("The header has {channels} color channels"));
+    }
+    if meta.tps == 0 || meta.ref_interval == 0 || meta.delta_t_max == 0 {
+        return Err(format!(
+            "The header has a zero time parameter (tps {}, ref_interval {}, delta_t_max {})",
+            meta.tps, meta.ref_interval, meta.delta_t_max
+        ));
+    }
+    if meta.delta_t_max < meta.ref_interval {
+        return Err(format!(
+            "The header's delta_t_max ({}) is less than its ref_interval ({})",
+            meta.delta_t_max, meta.ref_interval
+        ));
+    }
+    if compressed {
+        if meta.adu_interval == 0 {
+            return Err("The header has an ADU interval of zero".to_string());
+        }
+    } else {
+        let expected_size = if channels == 1 { 9 } else { 11 };
+        if meta.event_size != expected_size {
+            return Err(format!(
+                "The header has an event size of {} bytes, but events with {channels} channel(s) \
+                take {expected_size}",
+                meta.event_size
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_event(
+    meta: &CodecMetadata,
+    absolute_t: bool,
+    last_t: &mut Array3<Option<AbsoluteT>>,
+    event: Event,
+) -> Result<(), String> {
+    let (x, y, c) = (event.coord.x, event.coord.y, event.coord.c);
+    let (d, t) = (event.d, event.t);
+    if x >= meta.plane.w() || y >= meta.plane.h() {
+        return Err(format!(
+            "Coordinate ({x}, {y}) is outside the {}x{} plane",
+            meta.plane.w(),
+            meta.plane.h()
+        ));
+    }
+    let c = match (c, meta.plane.c()) {
+        (None, 1) => 0,
+        (Some(c), channels) if c < channels => c,
+        (c, channels) => {
+            return Err(format!(
+                "Channel {c:?} is invalid for a plane with {channels} channel(s)"
+            ))
+        }
+    };
+    if d > D_MAX && d != D_ZERO_INTEGRATION && d != D_EMPTY {
+        return Err(format!("D of {d} is greater than D_MAX ({D_MAX})"));
+    }
+
+    // A pixel's first event may come any length of time after the start of the stream
+    let last_t = &mut last_t[[y as usize, x as usize, c as usize]];
+    let dt = if absolute_t {
+        let dt = match *last_t {
+            Some(last) if t < last => {
+                return Err(format!(
+                    "Timestamp {t} is earlier than the pixel's previous timestamp {last}"
+                ));
+            }
+            Some(last) => Some(t - last),
+            None => None,
+        };
+        *last_t = Some(t);
+        dt
+    } else {
+        let dt = last_t.map(|_| t);
+        *last_t = Some(last_t.unwrap_or(0).saturating_add(t));
+        dt
+    };
+    if let Some(dt) = dt.filter(|dt| *dt > meta.delta_t_max) {
+        return Err(format!(
+            "Δt of {dt} is greater than delta_t_max ({})",
+            meta.delta_t_max
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,500 @@
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// How many events go in each AEDAT4 event packet
+const AEDAT4_PACKET_EVENTS: usize = 4096;
+
+/// The size of a `.npy` header. It's fixed, so that it can be rewritten with the final event
+/// count without moving the data.
+const NPY_HEADER_SIZE: usize = 192;
+
+/// The file format of the output DVS events
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DvsFormat {
+    /// Human-readable `t x y p` lines, after a `width height` line
+    Text,
+
+    /// AEDAT4 event packets, as read by DV and dv-processing. Timestamps are in microseconds.
+    Aedat4,
+
+    /// Prophesee EVT 2.0 (`.raw`). Timestamps are in microseconds.
+    Evt2,
+
+    /// Prophesee CD event `.dat`. Timestamps are in microseconds, and wrap after about 71
+    /// minutes.
+    Dat,
+
+    /// A NumPy structured array of (t, x, y, p) records
+    Npy,
+
+    /// Packed little-endian (t: u64, x: u16, y: u16, p: u8) records
+    Bin,
+}
+
+impl DvsFormat {
+    /// Guess the format from a file's extension
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("aedat4") => DvsFormat::Aedat4,
+            Some("raw") | Some("evt2") => DvsFormat::Evt2,
+            Some("dat") => DvsFormat::Dat,
+            Some("npy") => DvsFormat::Npy,
+            Some("bin") => DvsFormat::Bin,
+            _ => DvsFormat::Text,
+        }
+    }
+
+    /// Whether the format's timestamps must be in microseconds
+    fn needs_microseconds(&self) -> bool {
+        matches!(self, DvsFormat::Aedat4 | DvsFormat::Evt2 | DvsFormat::Dat)
+    }
+}
+
+/// A DVS event, with its timestamp in ADΔER ticks
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct DvsEvent {
+    t: u64,
+    x: u16,
+    y: u16,
+    polarity: bool,
+}
+
+/// Writes DVS events in any [`DvsFormat`]
+pub struct DvsWriter {
+    format: DvsFormat,
+    writer: BufWriter<File>,
+    tps: u64,
+    microseconds: bool,
+
+    /// Events held back until no earlier event can arrive, so that every format is written in
+    /// time order
+    pending: BinaryHeap<Reverse<DvsEvent>>,
+
+    /// How far behind the latest event an event may arrive, in ticks
+    reorder_window: u64,
+    latest_t: u64,
+    event_count: u64,
+    aedat4_packet: Vec<DvsEvent>,
+
+    /// The upper timestamp bits of the last EVT 2.0 `EVT_TIME_HIGH` word
+    evt2_time_high: Option<u64>,
+}
+
+impl DvsWriter {
+    /// Create the output file and write its header. Timestamps are written in ticks, unless
+    /// `microseconds` is set or the format requires microseconds.
+    pub fn new(
+        path: &Path,
+        format: DvsFormat,
+        width: u16,
+        height: u16,
+        tps: u32,
+        microseconds: bool,
+        reorder_window: u64,
+    ) -> io::Result<Self> {
+        let max_dimension = match format {
+            DvsFormat::Evt2 => 1 << 11,
+            DvsFormat::Dat => 1 << 14,
+            _ => u32::MAX,
+        };
+        if u32::from(width) > max_dimension || u32::from(height) > max_dimension {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{format:?} can't represent a {width}x{height} sensor"),
+            ));
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format {
+            DvsFormat::Text => {
+                // Write the width and height as first line header
+                writeln!(writer, "{width} {height}")?;
+            }
+            DvsFormat::Aedat4 => {
+                writer.write_all(b"#!AER-DAT4.0\r\n")?;
+                let header = aedat4_io_header(width, height);
+                writer.write_all(&(header.len() as i32).to_le_bytes())?;
+                writer.write_all(&header)?;
+            }
+            DvsFormat::Evt2 => {
+                write!(
+                    writer,
+                    "% evt 2.0\n\
+                    % format EVT2;height={height};width={width}\n\
+                    % geometry {width}x{height}\n\
+                    % end\n"
+                )?;
+            }
+            DvsFormat::Dat => {
+                write!(
+                    writer,
+                    "% Data file containing CD events.\n\
+                    % Version 2\n\
+                    % Height {height}\n\
+                    % Width {width}\n"
+                )?;
+                // The event type (CD) and event size in bytes
+                writer.write_all(&[0x0C, 8])?;
+            }
+            DvsFormat::Npy => writer.write_all(&npy_header(0))?,
+            DvsFormat::Bin => {}
+        }
+
+        Ok(DvsWriter {
+            format,
+            writer,
+            tps: u64::from(tps.max(1)),
+            microseconds: microseconds || format.needs_microseconds(),
+            pending: BinaryHeap::new(),
+            reorder_window,
+            latest_t: 0,
+            event_count: 0,
+            aedat4_packet: Vec::with_capacity(AEDAT4_PACKET_EVENTS),
+            evt2_time_high: None,
+        })
+    }
+
+    /// Write an event with a timestamp `t` in ticks, and a positive or negative polarity
+    pub fn write_event(&mut self, t: u128, x: u16, y: u16, polarity: bool) -> io::Result<()> {
+        let event = DvsEvent {
+            t: t as u64,
+            x,
+            y,
+            polarity,
+        };
+        self.latest_t = self.latest_t.max(event.t);
+        self.pending.push(Reverse(event));
+        while let Some(Reverse(oldest)) = self.pending.peek() {
+            if oldest.t + self.reorder_window >= self.latest_t {
+                break;
+            }
+            let oldest = *oldest;
+            self.pending.pop();
+            self.emit(oldest)?;
+        }
+        Ok(())
+    }
+
+    /// Write any remaining events and finish the file. Returns the number of events written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        while let Some(Reverse(event)) = self.pending.pop() {
+            self.emit(event)?;
+        }
+        self.write_aedat4_packet()?;
+        if self.format == DvsFormat::Npy {
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.writer.write_all(&npy_header(self.event_count))?;
+        }
+        self.writer.flush()?;
+        Ok(self.event_count)
+    }
+
+    fn timestamp(&self, t: u64) -> u64 {
+        if self.microseconds {
+            (u128::from(t) * 1_000_000 / u128::from(self.tps)) as u64
+        } else {
+            t
+        }
+    }
+
+    fn emit(&mut self, event: DvsEvent) -> io::Result<()> {
+        self.event_count += 1;
+        let t = self.timestamp(event.t);
+        let (x, y) = (event.x, event.y);
+        match self.format {
+            DvsFormat::Text => {
+                let polarity = if event.polarity { "1" } else { "-1" };
+                writeln!(self.writer, "{t} {x} {y} {polarity}")?;
+            }
+            DvsFormat::Aedat4 => {
+                self.aedat4_packet.push(DvsEvent { t, ..event });
+                if self.aedat4_packet.len() == AEDAT4_PACKET_EVENTS {
+                    self.write_aedat4_packet()?;
+                }
+            }
+            DvsFormat::Evt2 => {
+                let time_high = t >> 6;
+                if self.evt2_time_high != Some(time_high) {
+                    let word = (0x8_u32 << 28) | (time_high & 0x0FFF_FFFF) as u32;
+                    self.writer.write_all(&word.to_le_bytes())?;
+                    self.evt2_time_high = Some(time_high);
+                }
+                let word = (u32::from(event.polarity) << 28)
+                    | (((t & 0x3F) as u32) << 22)
+                    | (u32::from(x) << 11)
+                    | u32::from(y);
+                self.writer.write_all(&word.to_le_bytes())?;
+            }
+            DvsFormat::Dat => {
+                let data = (u32::from(event.polarity) << 28) | (u32::from(y) << 14) | u32::from(x);
+                self.writer.write_all(&(t as u32).to_le_bytes())?;
+                self.writer.write_all(&data.to_le_bytes())?;
+            }
+            DvsFormat::Npy | DvsFormat::Bin => {
+                self.writer.write_all(&t.to_le_bytes())?;
+                self.writer.write_all(&x.to_le_bytes())?;
+                self.writer.write_all(&y.to_le_bytes())?;
+                self.writer.write_all(&[u8::from(event.polarity)])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the buffered AEDAT4 events as an `EventPacket` flatbuffer, laid out by hand:
+    /// the root table offset and file identifier, the vtable, the table, then the vector of
+    /// 16-byte `Event` structs.
+    fn write_aedat4_packet(&mut self) -> io::Result<()> {
+        if self.aedat4_packet.is_empty() {
+            return Ok(());
+        }
+        let events = std::mem::take(&mut self.aedat4_packet);
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(32 + events.len() * 16);
+        buffer.extend(16_u32.to_le_bytes());
+        buffer.extend(b"EVTS");
+        // The vtable's size, the table's size, and the offset of the `elements` field
+        for entry in [6_u16, 8, 4] {
+            buffer.extend(entry.to_le_bytes());
+        }
+        buffer.extend([0; 2]);
+        // The table's offset back to its vtable, and forward to the vector
+        buffer.extend(8_i32.to_le_bytes());
+        buffer.extend(8_u32.to_le_bytes());
+        // Align the vector's elements to 8 bytes
+        buffer.extend([0; 4]);
+        buffer.extend((events.len() as u32).to_le_bytes());
+        for event in &events {
+            buffer.extend((event.t as i64).to_le_bytes());
+            buffer.extend((event.x as i16).to_le_bytes());
+            buffer.extend((event.y as i16).to_le_bytes());
+            buffer.push(u8::from(event.polarity));
+            buffer.extend([0; 3]);
+        }
+
+        // The packet header: the stream ID, and the size of the packet
+        self.writer.write_all(&0_i32.to_le_bytes())?;
+        self.writer
+            .write_all(&(buffer.len() as i32).to_le_bytes())?;
+        self.writer.write_all(&buffer)?;
+        self.aedat4_packet = events;
+        self.aedat4_packet.clear();
+        Ok(())
+    }
+}
+
+/// The AEDAT4 `IOHeader` flatbuffer, describing a single uncompressed stream of polarity events.
+/// The compression and data table position fields are left at their defaults (none).
+fn aedat4_io_header(width: u16, height: u16) -> Vec<u8> {
+    let info = format!(
+        "<dv version=\"2.0\">\
+        <node name=\"outInfo\" path=\"/mainloop/Recorder/outInfo/\">\
+        <node name=\"0\" path=\"/mainloop/Recorder/outInfo/0/\">\
+        <attr key=\"compression\" type=\"string\">NONE</attr>\
+        <attr key=\"originalModuleName\" type=\"string\">adder-to-dvs</attr>\
+        <attr key=\"originalOutputName\" type=\"string\">events</attr>\
+        <attr key=\"typeDescription\" type=\"string\">Array of events (polarity ON/OFF).</attr>\
+        <attr key=\"typeIdentifier\" type=\"string\">EVTS</attr>\
+        <node name=\"info\" path=\"/mainloop/Recorder/outInfo/0/info/\">\
+        <attr key=\"sizeX\" type=\"int\">{width}</attr>\
+        <attr key=\"sizeY\" type=\"int\">{height}</attr>\
+        <attr key=\"source\" type=\"string\">adder-to-dvs</attr>\
+        </node></node></node></dv>"
+    );
+
+    let mut buffer: Vec<u8> = Vec::new();
+    buffer.extend(20_u32.to_le_bytes());
+    buffer.extend(b"IOHE");
+    // The vtable's size, the table's size, and the offsets of the three fields
+    for entry in [10_u16, 8, 0, 0, 4] {
+        buffer.extend(entry.to_le_bytes());
+    }
+    buffer.extend([0; 2]);
+    // The table's offset back to its vtable, and forward to the `infoNode` string
+    buffer.extend(12_i32.to_le_bytes());
+    buffer.extend(4_u32.to_le_bytes());
+    buffer.extend((info.len() as u32).to_le_bytes());
+    buffer.extend(info.as_bytes());
+    buffer.push(0);
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    buffer
+}
+
+/// A `.npy` version 1.0 header for a structured array of `count` events
+fn npy_header(count: u64) -> Vec<u8> {
+    let dict = format!(
+        "{{'descr': [('t', '<u8'), ('x', '<u2'), ('y', '<u2'), ('p', '|u1')], \
+        'fortran_order': False, 'shape': ({count},), }}"
+    );
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend(((NPY_HEADER_SIZE - 10) as u16).to_le_bytes());
+    header.extend(dict.as_bytes());
+    header.resize(NPY_HEADER_SIZE - 1, b' ');
+    header.push(b'\n');
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the events (t, x, y, polarity) to a file in the given format, with timestamps in
+    /// microseconds, and read the file back
+    fn write_events(name: &str, format: DvsFormat, events: &[(u128, u16, u16, bool)]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(name);
+        let mut writer = DvsWriter::new(&path, format, 640, 480, 1_000_000, true, 0).unwrap();
+        for &(t, x, y, polarity) in events {
+            writer.write_event(t, x, y, polarity).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), events.len() as u64);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    /// Skip a Prophesee header of `%` lines
+    fn skip_prophesee_header(bytes: &[u8]) -> &[u8] {
+        let mut rest = bytes;
+        while rest.first() == Some(&b'%') {
+            let line_end = rest.iter().position(|&byte| byte == b'\n').unwrap();
+            rest = &rest[line_end + 1..];
+        }
+        rest
+    }
+
+    fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+        u16::from_le_bytes(bytes[pos..pos + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+    }
+
+    /// Follow a flatbuffer's root table to the data referenced by the field with the given index
+    fn flatbuffer_field_data(buffer: &[u8], index: usize) -> usize {
+        let table = u32_at(buffer, 0) as usize;
+        let vtable = table - u32_at(buffer, table) as usize;
+        let field = table + u16_at(buffer, vtable + 4 + 2 * index) as usize;
+        field + u32_at(buffer, field) as usize
+    }
+
+    const EVENTS: [(u128, u16, u16, bool); 3] =
+        [(100, 5, 7, true), (130, 639, 479, false), (200, 0, 1, true)];
+
+    #[test]
+    fn evt2_words() {
+        let bytes = write_events("adder_to_dvs_words.raw", DvsFormat::Evt2, &EVENTS);
+        let body = skip_prophesee_header(&bytes);
+        assert_eq!(body.len() % 4, 0);
+
+        let mut time_high = None;
+        let mut decoded = Vec::new();
+        for word in body
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        {
+            match word >> 28 {
+                0x8 => time_high = Some(u128::from(word & 0x0FFF_FFFF)),
+                p @ (0x0 | 0x1) => decoded.push((
+                    (time_high.unwrap() << 6) | u128::from((word >> 22) & 0x3F),
+                    ((word >> 11) & 0x7FF) as u16,
+                    (word & 0x7FF) as u16,
+                    p == 1,
+                )),
+                kind => panic!("Unexpected word type {kind:#x}"),
+            }
+        }
+        assert_eq!(decoded, EVENTS);
+    }
+
+    #[test]
+    fn dat_events() {
+        let bytes = write_events("adder_to_dvs_events.dat", DvsFormat::Dat, &EVENTS);
+        let body = skip_prophesee_header(&bytes);
+        // The CD event type, and the event size
+        assert_eq!(body[..2], [0x0C_u8, 8]);
+
+        let decoded: Vec<_> = body[2..]
+            .chunks(8)
+            .map(|event| {
+                let data = u32_at(event, 4);
+                (
+                    u128::from(u32_at(event, 0)),
+                    (data & 0x3FFF) as u16,
+                    ((data >> 14) & 0x3FFF) as u16,
+                    data >> 28 == 1,
+                )
+            })
+            .collect();
+        assert_eq!(decoded, EVENTS);
+    }
+
+    #[test]
+    fn npy_records() {
+        let bytes = write_events("adder_to_dvs_records.npy", DvsFormat::Npy, &EVENTS);
+        assert_eq!(bytes[..8], *b"\x93NUMPY\x01\x00");
+        let header_len = usize::from(u16_at(&bytes, 8));
+        // The data must start on a 64-byte boundary
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (3,)"));
+        assert!(header.ends_with('\n'));
+
+        let records = &bytes[10 + header_len..];
+        assert_eq!(records.len(), EVENTS.len() * 13);
+        assert_eq!(u64::from_le_bytes(records[13..21].try_into().unwrap()), 130);
+        assert_eq!(u16_at(records, 21), 639);
+        assert_eq!(u16_at(records, 23), 479);
+        assert_eq!(records[25], 0);
+    }
+
+    #[test]
+    fn aedat4_flatbuffers() {
+        let bytes = write_events("adder_to_dvs_packets.aedat4", DvsFormat::Aedat4, &EVENTS);
+        let magic = b"#!AER-DAT4.0\r\n";
+        assert_eq!(bytes[..magic.len()], *magic);
+
+        let header_len = u32_at(&bytes, magic.len()) as usize;
+        let header = &bytes[magic.len() + 4..magic.len() + 4 + header_len];
+        assert_eq!(header[4..8], *b"IOHE");
+        // The infoNode string is the third field
+        let info = flatbuffer_field_data(header, 2);
+        let info_len = u32_at(header, info) as usize;
+        let info = std::str::from_utf8(&header[info + 4..info + 4 + info_len]).unwrap();
+        assert!(info.contains("<attr key=\"sizeX\" type=\"int\">640</attr>"));
+        assert!(info.contains("<attr key=\"typeIdentifier\" type=\"string\">EVTS</attr>"));
+
+        let packet = &bytes[magic.len() + 4 + header_len..];
+        // The stream ID, and the size of the packet
+        assert_eq!(u32_at(packet, 0), 0);
+        let packet_len = u32_at(packet, 4) as usize;
+        assert_eq!(packet.len(), 8 + packet_len);
+        let packet = &packet[8..];
+        assert_eq!(packet[4..8], *b"EVTS");
+        let elements = flatbuffer_field_data(packet, 0);
+        assert_eq!(u32_at(packet, elements), EVENTS.len() as u32);
+        let decoded: Vec<_> = packet[elements + 4..]
+            .chunks(16)
+            .map(|event| {
+                (
+                    u64::from_le_bytes(event[..8].try_into().unwrap()) as u128,
+                    u16_at(event, 8),
+                    u16_at(event, 10),
+                    event[12] == 1,
+                )
+            })
+            .collect();
+        assert_eq!(decoded, EVENTS);
+    }
+}
@@ -1,3 +1,8 @@
+mod formats;
+mod sensor;
+
+use crate::formats::{DvsFormat, DvsWriter};
+use crate::sensor::{SensorModel, SensorSettings};
 use adder_codec_core::codec::CodecMetadata;
 use adder_codec_core::*;
 use clap::Parser;
@@ -6,9 +11,9 @@ use std::cmp::max;
 use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::Write;
 use std::option::Option;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{error, io};
 use video_rs::{Encoder, EncoderSettings, Options, PixelFormat};
 
@@ -20,9 +25,19 @@ pub struct MyArgs {
     #[clap(short, long)]
     pub(crate) input: String,
 
-    /// Output DVS event text file path
-    #[clap(long)]
-    pub(crate) output_text: String,
+    /// Output DVS event file path. Its format is chosen by its extension (`.aedat4`, `.raw` or
+    /// `.evt2`, `.dat`, `.npy`, or `.bin`, and text otherwise), unless `--format` is given.
+    #[clap(long, alias = "output-text")]
+    pub(crate) output_events: String,
+
+    /// Output DVS event file format
+    #[clap(long, value_enum)]
+    pub(crate) format: Option<DvsFormat>,
+
+    /// Write timestamps in microseconds, rather than ADΔER ticks? AEDAT4 and Prophesee formats
+    /// always use microseconds.
+    #[clap(long, action)]
+    pub(crate) microseconds: bool,
 
     /// Output DVS event video file path
     #[clap(long)]
@@ -33,6 +48,32 @@ pub struct MyArgs {
 
     #[clap(short, long, action)]
     pub show_display: bool,
+
+    /// The standard deviation of each pixel's ON and OFF contrast thresholds, which are nominally
+    /// 0.15 (in log intensity)
+    #[clap(long, default_value_t = 0.0)]
+    pub threshold_sigma: f64,
+
+    /// How long a pixel can't fire after firing, in seconds
+    #[clap(long, default_value_t = 0.0)]
+    pub refractory_period: f64,
+
+    /// The rate of shot noise events of random polarity, in Hz per pixel
+    #[clap(long, default_value_t = 0.0)]
+    pub shot_noise_rate: f64,
+
+    /// The rate of leak events (ON polarity), in Hz per pixel
+    #[clap(long, default_value_t = 0.0)]
+    pub leak_rate: f64,
+
+    /// The standard deviation of the random delay added to each event's timestamp, in seconds.
+    /// The delay is at most four standard deviations.
+    #[clap(long, default_value_t = 0.0)]
+    pub timestamp_jitter: f64,
+
+    /// The seed for the random sensor effects above
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
 }
 
 struct DvsPixel {
@@ -52,7 +93,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
     dbg!(args.clone());
     let file_path = args.input.as_str();
 
-    let output_text_path = args.output_text.as_str();
+    let output_events_path = Path::new(&args.output_events);
     let output_video_path = args.output_video.as_str();
 
     let (mut stream, mut bitreader) = open_file_decoder(file_path)?;
@@ -91,15 +132,30 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         }
         Err(_) => None,
     };
-    let mut text_writer: BufWriter<File> = BufWriter::new(File::create(output_text_path)?);
-    {
-        // Write the width and height as first line header
-        let dims_str = meta.plane.w().to_string() + " " + &*meta.plane.h().to_string() + "\n";
-        let amt = text_writer
-            .write(dims_str.as_ref())
-            .expect("Could not write");
-        debug_assert_eq!(amt, dims_str.len());
-    }
+    let mut sensor = SensorModel::new(
+        meta.plane,
+        meta.tps,
+        SensorSettings {
+            threshold_sigma: args.threshold_sigma,
+            refractory_period: args.refractory_period,
+            shot_noise_rate: args.shot_noise_rate,
+            leak_rate: args.leak_rate,
+            timestamp_jitter: args.timestamp_jitter,
+            seed: args.seed,
+        },
+    );
+
+    let mut dvs_writer = DvsWriter::new(
+        output_events_path,
+        args.format
+            .unwrap_or_else(|| DvsFormat::from_path(output_events_path)),
+        meta.plane.w(),
+        meta.plane.h(),
+        meta.tps,
+        args.microseconds,
+        // Jitter can delay an event past later ones, by up to its maximum
+        meta.delta_t_max as u64 * 4 + sensor.max_jitter(),
+    )?;
 
     let mut event_count: u64 = 0;
 
@@ -172,6 +228,24 @@ fn main() -> Result<(), Box<dyn error::Error>> {
             frame_count += 1;
         }
 
+        for noise in sensor.noise_events(current_t) {
+            if sensor.fire(noise.y as usize, noise.x as usize, 0, noise.t) {
+                set_instant_dvs_pixel(
+                    Event {
+                        coord: Coord::new(noise.x, noise.y, None),
+                        d: 0,
+                        t: 0,
+                    },
+                    &meta,
+                    &mut instantaneous_frame_deque,
+                    (noise.t / frame_length) as usize,
+                    frame_count,
+                    if noise.polarity { 255 } else { 0 },
+                )?;
+                dvs_writer.write_event(noise.t, noise.x, noise.y, noise.polarity)?;
+            }
+        }
+
         match stream.digest_event(&mut bitreader) {
             Ok(mut event) => {
                 event_count += 1;
@@ -226,9 +300,13 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                                 let y = event.coord.y;
                                 let new_intensity_ln =
                                     event_to_frame_intensity(&event, frame_length);
-                                let c = 0.15;
+                                let (on_threshold, off_threshold) =
+                                    sensor.thresholds(y as usize, x as usize, c);
                                 match (new_intensity_ln, px.frame_intensity_ln) {
-                                    (a, b) if a >= b + c => {
+                                    (a, b)
+                                        if a >= b + on_threshold
+                                            && sensor.fire(y as usize, x as usize, c, px.t) =>
+                                    {
                                         // Fire a positive polarity event
                                         set_instant_dvs_pixel(
                                             event,
@@ -238,20 +316,13 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                                             frame_count,
                                             255,
                                         )?;
-                                        let dvs_string = px.t.to_string()
-                                            + " "
-                                            + x.to_string().as_str()
-                                            + " "
-                                            + y.to_string().as_str()
-                                            + " "
-                                            + "1\n";
-                                        let amt = text_writer
-                                            .write(dvs_string.as_ref())
-                                            .expect("Could not write");
-                                        debug_assert_eq!(amt, dvs_string.len());
+                                        dvs_writer.write_event(sensor.jitter(px.t), x, y, true)?;
                                         px.frame_intensity_ln = new_intensity_ln;
                                     }
-                                    (a, b) if a <= b - c => {
+                                    (a, b)
+                                        if a <= b - off_threshold
+                                            && sensor.fire(y as usize, x as usize, c, px.t) =>
+                                    {
                                         // Fire a negative polarity event
                                         set_instant_dvs_pixel(
                                             event,
@@ -261,17 +332,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                                             frame_count,
                                             0,
                                         )?;
-                                        let dvs_string = px.t.to_string()
-                                            + " "
-                                            + x.to_string().as_str()
-                                            + " "
-                                            + y.to_string().as_str()
-                                            + " "
-                                            + "-1\n";
-                                        let amt = text_writer
-                                            .write(dvs_string.as_ref())
-                                            .expect("Could not write");
-                                        debug_assert_eq!(amt, dvs_string.len());
+                                        dvs_writer.write_event(sensor.jitter(px.t), x, y, false)?;
                                         px.frame_intensity_ln = new_intensity_ln;
                                     }
                                     (_, _) => {}
@@ -288,8 +349,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         }
     }
 
-    text_writer.flush().expect("Could not flush");
-    drop(text_writer);
+    let dvs_event_count = dvs_writer.finish()?;
 
     let mut event_count_mat = instantaneous_frame_deque[0].clone();
     unsafe {
@@ -321,6 +381,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         }
     }
     println!("\n");
+    println!("Wrote {dvs_event_count} DVS events");
 
     // TODO: restore this functionality
     // if args.show_display {
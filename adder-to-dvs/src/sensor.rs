@@ -0,0 +1,260 @@
+use adder_codec_core::PlaneSize;
+use ndarray::Array3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The nominal log-intensity change which makes a pixel fire
+pub const CONTRAST_THRESHOLD: f64 = 0.15;
+
+/// The lowest threshold a pixel can have after mismatch is applied, so that no pixel fires on
+/// every change
+const MIN_THRESHOLD: f64 = 0.01;
+
+/// The largest timestamp jitter, in standard deviations. Bounding it bounds how far out of order
+/// the jittered events can be.
+const MAX_JITTER_SIGMAS: f64 = 4.0;
+
+/// The non-ideal effects of a real DVS sensor. All are off by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SensorSettings {
+    /// The standard deviation of each pixel's ON and OFF thresholds around
+    /// [`CONTRAST_THRESHOLD`]
+    pub threshold_sigma: f64,
+
+    /// How long a pixel can't fire after firing, in seconds
+    pub refractory_period: f64,
+
+    /// The rate of shot noise events of random polarity, in Hz per pixel
+    pub shot_noise_rate: f64,
+
+    /// The rate of leak events (ON polarity), in Hz per pixel
+    pub leak_rate: f64,
+
+    /// The standard deviation of the delay added to each event's timestamp by the pixel's
+    /// limited bandwidth, in seconds. The delay is at most four standard deviations.
+    pub timestamp_jitter: f64,
+
+    /// The seed of the random number generator, so that runs can be reproduced
+    pub seed: u64,
+}
+
+/// An event which a sensor fires with no change in intensity
+pub struct NoiseEvent {
+    pub t: u128,
+    pub x: u16,
+    pub y: u16,
+    pub polarity: bool,
+}
+
+/// Applies the effects of [`SensorSettings`] to the idealised DVS events
+pub struct SensorModel {
+    settings: SensorSettings,
+    rng: StdRng,
+    plane: PlaneSize,
+    on_thresholds: Array3<f64>,
+    off_thresholds: Array3<f64>,
+
+    /// When each pixel last fired, in ticks
+    last_fire_t: Array3<Option<u128>>,
+    refractory_ticks: u128,
+    jitter_ticks: f64,
+
+    /// The combined rate of noise events across the sensor, per tick
+    noise_rate: f64,
+    next_noise_t: f64,
+}
+
+impl SensorModel {
+    pub fn new(plane: PlaneSize, tps: u32, settings: SensorSettings) -> Self {
+        let mut rng = StdRng::seed_from_u64(settings.seed);
+        let shape = (plane.h_usize(), plane.w_usize(), plane.c_usize());
+        let mut thresholds = || {
+            Array3::from_shape_simple_fn(shape, || {
+                if settings.threshold_sigma > 0.0 {
+                    (CONTRAST_THRESHOLD + settings.threshold_sigma * gaussian(&mut rng))
+                        .max(MIN_THRESHOLD)
+                } else {
+                    CONTRAST_THRESHOLD
+                }
+            })
+        };
+        let on_thresholds = thresholds();
+        let off_thresholds = thresholds();
+
+        let tps = f64::from(tps);
+        let noise_rate = (settings.shot_noise_rate + settings.leak_rate).max(0.0)
+            * (plane.w_usize() * plane.h_usize()) as f64
+            / tps;
+        let mut model = SensorModel {
+            settings,
+            rng,
+            plane,
+            on_thresholds,
+            off_thresholds,
+            last_fire_t: Array3::from_elem(shape, None),
+            refractory_ticks: (settings.refractory_period.max(0.0) * tps) as u128,
+            jitter_ticks: settings.timestamp_jitter.max(0.0) * tps,
+            noise_rate,
+            next_noise_t: 0.0,
+        };
+        model.next_noise_t = model.noise_interval();
+        model
+    }
+
+    /// The ON and OFF thresholds of a pixel
+    pub fn thresholds(&self, y: usize, x: usize, c: usize) -> (f64, f64) {
+        (
+            self.on_thresholds[[y, x, c]],
+            self.off_thresholds[[y, x, c]],
+        )
+    }
+
+    /// Try to fire a pixel at time `t`. Returns false if it's still in its refractory period,
+    /// in which case the intensity change isn't consumed, and may fire the pixel later.
+    pub fn fire(&mut self, y: usize, x: usize, c: usize, t: u128) -> bool {
+        if self.refractory_ticks == 0 {
+            return true;
+        }
+        let last_fire_t = &mut self.last_fire_t[[y, x, c]];
+        if let Some(last) = *last_fire_t {
+            if t < last + self.refractory_ticks {
+                return false;
+            }
+        }
+        *last_fire_t = Some(t);
+        true
+    }
+
+    /// Delay a timestamp, in ticks, by the pixel's response time
+    pub fn jitter(&mut self, t: u128) -> u128 {
+        if self.jitter_ticks <= 0.0 {
+            return t;
+        }
+        let sigmas = gaussian(&mut self.rng).abs().min(MAX_JITTER_SIGMAS);
+        t + (sigmas * self.jitter_ticks) as u128
+    }
+
+    /// The largest delay that [`jitter`](Self::jitter) can add, in ticks
+    pub fn max_jitter(&self) -> u64 {
+        (MAX_JITTER_SIGMAS * self.jitter_ticks) as u64
+    }
+
+    /// Generate the noise events up to time `t`, in ticks, in time order
+    pub fn noise_events(&mut self, t: u128) -> Vec<NoiseEvent> {
+        let mut events = Vec::new();
+        if self.noise_rate <= 0.0 {
+            return events;
+        }
+        let leak_fraction = self.settings.leak_rate.max(0.0)
+            / (self.settings.shot_noise_rate.max(0.0) + self.settings.leak_rate.max(0.0));
+        while self.next_noise_t <= t as f64 {
+            let polarity = if self.rng.gen::<f64>() < leak_fraction {
+                true
+            } else {
+                self.rng.gen()
+            };
+            events.push(NoiseEvent {
+                t: self.next_noise_t as u128,
+                x: self.rng.gen_range(0..self.plane.w()),
+                y: self.rng.gen_range(0..self.plane.h()),
+                polarity,
+            });
+            self.next_noise_t += self.noise_interval();
+        }
+        events
+    }
+
+    /// The time until the next noise event, in ticks, for a Poisson process
+    fn noise_interval(&mut self) -> f64 {
+        if self.noise_rate <= 0.0 {
+            return f64::INFINITY;
+        }
+        -(1.0 - self.rng.gen::<f64>()).ln() / self.noise_rate
+    }
+}
+
+/// Sample the standard normal distribution, with the Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SensorSettings {
+        SensorSettings {
+            threshold_sigma: 0.03,
+            refractory_period: 0.001,
+            shot_noise_rate: 5.0,
+            leak_rate: 1.0,
+            timestamp_jitter: 0.001,
+            seed: 7,
+        }
+    }
+
+    fn noise(sensor: &mut SensorModel, t: u128) -> Vec<(u128, u16, u16, bool)> {
+        sensor
+            .noise_events(t)
+            .into_iter()
+            .map(|event| (event.t, event.x, event.y, event.polarity))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_sensor() {
+        let plane = PlaneSize::new(4, 3, 1).unwrap();
+        let mut a = SensorModel::new(plane, 10_000, settings());
+        let mut b = SensorModel::new(plane, 10_000, settings());
+        assert_eq!(a.on_thresholds, b.on_thresholds);
+        assert_eq!(a.off_thresholds, b.off_thresholds);
+
+        let noise_a = noise(&mut a, 10_000);
+        assert!(!noise_a.is_empty());
+        assert_eq!(noise_a, noise(&mut b, 10_000));
+        for t in 0..100 {
+            assert_eq!(a.jitter(t), b.jitter(t));
+        }
+
+        let c = SensorModel::new(
+            plane,
+            10_000,
+            SensorSettings {
+                seed: 8,
+                ..settings()
+            },
+        );
+        assert_ne!(a.on_thresholds, c.on_thresholds);
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        let plane = PlaneSize::new(1, 1, 1).unwrap();
+        let mut sensor = SensorModel::new(plane, 10_000, settings());
+        assert_eq!(sensor.max_jitter(), 40);
+        for _ in 0..10_000 {
+            assert!(sensor.jitter(1000) - 1000 <= u128::from(sensor.max_jitter()));
+        }
+    }
+
+    #[test]
+    fn refractory_period() {
+        let plane = PlaneSize::new(2, 1, 1).unwrap();
+        // 10 ticks long
+        let mut sensor = SensorModel::new(
+            plane,
+            10_000,
+            SensorSettings {
+                refractory_period: 0.001,
+                ..Default::default()
+            },
+        );
+        assert!(sensor.fire(0, 0, 0, 100));
+        assert!(!sensor.fire(0, 0, 0, 105));
+        // Other pixels aren't affected
+        assert!(sensor.fire(0, 1, 0, 105));
+        assert!(sensor.fire(0, 0, 0, 110));
+    }
+}
@@ -21,6 +21,8 @@ use bevy_egui::egui::{
 };
 
 use crate::transcoder::adder::replace_adder_transcoder;
+use crate::transcoder::profile::{run_headless, HeadlessArgs};
+use clap::{Parser, Subcommand};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -49,11 +51,33 @@ pub struct MainUiState {
 
 use crate::utils::slider::NotchedSlider;
 
+/// Command line argument parser. Without a subcommand, the GUI is launched.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct VizArgs {
+    #[clap(subcommand)]
+    command: Option<VizCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum VizCommand {
+    /// Transcode a file without the GUI, using a transcode profile saved from the GUI
+    Transcode(HeadlessArgs),
+}
+
 /// This example demonstrates the following functionality and use-cases of bevy_egui:
 /// - rendering loaded assets;
 /// - toggling hidpi scaling (by pressing '/' button);
 /// - configuring egui contexts during the startup.
 fn main() {
+    if let Some(VizCommand::Transcode(args)) = VizArgs::parse().command {
+        if let Err(e) = run_headless(&args) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
         .insert_resource(Msaa::default())
@@ -241,9 +265,30 @@ fn draw_ui(
 
                     // Right-align the text so it's easier to compare to the ADDER version
                     ui.with_layout(egui::Layout::top_down(Align::Max), |ui| {
+                        let (title, str) = match main_ui_state.view {
+                            Tabs::Transcoder => {
+                                let last = transcoder_state
+                                    .ui_info_state
+                                    .plot_points_raw_source_bitrate_y
+                                    .points
+                                    .iter()
+                                    .last();
+                                let str_num = match last {
+                                    None => -999.0,
+                                    Some(item) => item.unwrap_or(-999.0),
+                                };
+
+                                (
+                                    "Input".to_string(),
+                                    format!("{number:.prec$} MB/s", prec = 2, number = str_num),
+                                )
+                            }
+                            Tabs::Player => player_state.comparison_label(),
+                        };
+
                         let mut job = LayoutJob::default();
                         job.append(
-                            "Input\n",
+                            &(title + "\n"),
                             0.0,
                             TextFormat {
                                 font_id: FontId::new(24.0, FontFamily::Proportional),
@@ -251,19 +296,6 @@ fn draw_ui(
                                 ..Default::default()
                             },
                         );
-
-                        let last = transcoder_state
-                            .ui_info_state
-                            .plot_points_raw_source_bitrate_y
-                            .points
-                            .iter()
-                            .last();
-                        let str_num = match last {
-                            None => -999.0,
-                            Some(item) => item.unwrap_or(-999.0),
-                        };
-
-                        let str = format!("{number:.prec$} MB/s", prec = 2, number = str_num);
                         job.append(
                             &str,
                             0.0,
@@ -1,31 +1,42 @@
 use crate::player::adder::codec::CodecError;
+use crate::player::compare::{Comparison, ComparisonArtifact};
+use crate::player::input::{open_decoder, StreamReader};
 use crate::player::ui::ReconstructionMethod;
-use adder_codec_rs::adder_codec_core::bitstream_io::{BigEndian, BitReader};
+use adder_codec_rs::adder_codec_core::bitstream_io::{BigEndian, BitRead, BitReader};
 use adder_codec_rs::adder_codec_core::codec::decoder::Decoder;
 use adder_codec_rs::adder_codec_core::*;
 use adder_codec_rs::framer::driver::FramerMode::INSTANTANEOUS;
 use adder_codec_rs::framer::driver::{FrameSequence, Framer, FramerBuilder};
 use adder_codec_rs::framer::scale_intensity::event_to_intensity;
+use adder_codec_rs::framer::timestamped::random_access_position;
 
+use crate::utils::analytics::{EventAnalyzer, EventTally};
 use crate::utils::prep_bevy_image;
 
 use adder_codec_rs::transcoder::source::video::FramedViewMode;
-use adder_codec_rs::utils::cv::is_feature;
-use adder_codec_rs::utils::viz::draw_feature_coord;
+use adder_codec_rs::utils::cv::{is_feature, FlowVector, PlaneFitFlow};
+use adder_codec_rs::utils::viz::{draw_feature_coord, draw_flow_vector};
 use bevy::prelude::Image;
 use ndarray::Array;
 use ndarray::Array3;
 
-use adder_codec_rs::adder_codec_core::codec::EncoderType;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
-use std::io::BufReader;
+use std::io::SeekFrom;
 use std::path::Path;
 use video_rs_adder_dep::Frame;
 
+/// The length of drawn flow vectors, in seconds of motion
+const FLOW_DRAW_SECONDS: f32 = 1.0 / 30.0;
+
 pub type PlayerArtifact = (u64, Option<Image>);
-pub type PlayerStreamArtifact = (u64, StreamState, Option<Image>);
+pub type PlayerStreamArtifact = (
+    u64,
+    StreamState,
+    Option<Image>,
+    Option<ComparisonArtifact>,
+    Option<EventTally>,
+);
 
 #[derive(Default, Clone, Debug)]
 pub struct StreamState {
@@ -38,10 +49,20 @@ pub struct StreamState {
     pub running_intensities: Array3<i32>,
 }
 
-// TODO: allow flexibility with decoding non-file inputs
+/// The settings of a live stream's player which can be changed without reconnecting to it
+pub struct LiveSettings {
+    pub(crate) view_mode: FramedViewMode,
+    pub(crate) detect_features: bool,
+    pub(crate) buffer_limit: Option<u32>,
+    pub(crate) reconstruction_method: ReconstructionMethod,
+    pub(crate) optical_flow: bool,
+    pub(crate) analytics: bool,
+    pub(crate) comparison: Option<Comparison>,
+}
+
 pub struct InputStream {
-    pub(crate) decoder: Decoder<BufReader<File>>,
-    pub(crate) bitreader: BitReader<BufReader<File>, BigEndian>,
+    pub(crate) decoder: Decoder<StreamReader>,
+    pub(crate) bitreader: BitReader<StreamReader, BigEndian>,
 }
 unsafe impl Send for InputStream {}
 
@@ -52,6 +73,16 @@ pub struct AdderPlayer {
     pub(crate) input_stream: Option<InputStream>,
     pub(crate) display_frame: Frame,
     pub(crate) running_intensities: Array3<u8>,
+    flow: Option<PlaneFitFlow>,
+    comparison: Option<Comparison>,
+    analyzer: Option<EventAnalyzer>,
+    seek_target_ticks: Option<DeltaT>,
+    /// Whether frames are being reconstructed only to reach a seek target, and needn't be shown
+    seeking: bool,
+    /// After seeking with the fast reconstruction, marks the pixels whose running times are not
+    /// yet known
+    unstarted: Option<Array3<bool>>,
+    live: bool,
     playback_speed: f32,
     reconstruction_method: ReconstructionMethod,
     current_frame: u32,
@@ -84,82 +115,198 @@ impl AdderPlayer {
             Some(ext) => match ext.to_ascii_lowercase().to_str() {
                 None => Err(Box::new(AdderPlayerError("Invalid file type".into()))),
                 Some("adder") => {
-                    let input_path = path_buf.to_str().expect("Invalid string").to_string();
-                    let (stream, bitreader) = open_file_decoder(&input_path)?;
-
-                    let meta = *stream.meta();
-
-                    let mut reconstructed_frame_rate = meta.tps as f32 / meta.ref_interval as f32;
-                    if !is_framed(meta.source_camera) {
-                        reconstructed_frame_rate = 60.0;
-                    }
-
-                    reconstructed_frame_rate /= playback_speed;
-
-                    let framer_builder: FramerBuilder = FramerBuilder::new(meta.plane, 1)
-                        .codec_version(meta.codec_version, meta.time_mode)
-                        .time_parameters(
-                            meta.tps,
-                            meta.ref_interval,
-                            meta.delta_t_max,
-                            Some(reconstructed_frame_rate),
-                        )
-                        .mode(INSTANTANEOUS)
-                        .buffer_limit(buffer_limit)
-                        .view_mode(view_mode)
-                        .detect_features(detect_features)
-                        .source(stream.get_source_type(), meta.source_camera);
-
-                    let frame_sequence: FrameSequence<u8> = framer_builder.clone().finish();
-
-                    Ok(AdderPlayer {
-                        stream_state: StreamState {
-                            current_t_ticks: 0,
-                            tps: meta.tps,
-                            file_pos: 0,
-                            volume: meta.plane.volume(),
-                            running_intensities: Array::zeros((
-                                meta.plane.h_usize(),
-                                meta.plane.w_usize(),
-                                meta.plane.c_usize(),
-                            )),
-                            last_timestamps: Array::zeros((
-                                meta.plane.h_usize(),
-                                meta.plane.w_usize(),
-                                meta.plane.c_usize(),
-                            )),
-                        },
-                        framer_builder: Some(framer_builder),
-                        frame_sequence: Some(frame_sequence),
-                        input_stream: Some(InputStream {
-                            decoder: stream,
-                            bitreader,
-                        }),
-                        display_frame: Array3::zeros((
-                            meta.plane.h_usize(),
-                            meta.plane.w_usize(),
-                            meta.plane.c_usize(),
-                        )),
-                        running_intensities: Array3::zeros((
-                            meta.plane.h_usize(),
-                            meta.plane.w_usize(),
-                            1,
-                        )),
+                    let (stream, bitreader) = open_decoder(StreamReader::open_file(path_buf)?)?;
+                    Self::from_decoder(
+                        stream,
+                        bitreader,
                         playback_speed,
-                        reconstruction_method: ReconstructionMethod::Accurate,
-                        current_frame: 0,
-                    })
+                        view_mode,
+                        detect_features,
+                        buffer_limit,
+                    )
                 }
                 Some(_) => Err(Box::new(AdderPlayerError("Invalid file type".into()))),
             },
         }
     }
 
+    /// Play a live stream as it arrives over a TCP or Unix socket. See [`StreamReader::connect`]
+    /// for the address format.
+    pub(crate) fn new_live(
+        address: &str,
+        view_mode: FramedViewMode,
+        detect_features: bool,
+        buffer_limit: Option<u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (stream, bitreader) = open_decoder(StreamReader::connect(address)?)?;
+        let mut player = Self::from_decoder(
+            stream,
+            bitreader,
+            1.0,
+            view_mode,
+            detect_features,
+            buffer_limit,
+        )?;
+        player.live = true;
+        Ok(player)
+    }
+
+    fn from_decoder(
+        stream: Decoder<StreamReader>,
+        bitreader: BitReader<StreamReader, BigEndian>,
+        playback_speed: f32,
+        view_mode: FramedViewMode,
+        detect_features: bool,
+        buffer_limit: Option<u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let meta = *stream.meta();
+
+        let mut reconstructed_frame_rate = meta.tps as f32 / meta.ref_interval as f32;
+        if !is_framed(meta.source_camera) {
+            reconstructed_frame_rate = 60.0;
+        }
+
+        reconstructed_frame_rate /= playback_speed;
+
+        let framer_builder: FramerBuilder = FramerBuilder::new(meta.plane, 1)
+            .codec_version(meta.codec_version, meta.time_mode)
+            .time_parameters(
+                meta.tps,
+                meta.ref_interval,
+                meta.delta_t_max,
+                Some(reconstructed_frame_rate),
+            )
+            .mode(INSTANTANEOUS)
+            .buffer_limit(buffer_limit)
+            .view_mode(view_mode)
+            .detect_features(detect_features)
+            .source(stream.get_source_type(), meta.source_camera);
+
+        let frame_sequence: FrameSequence<u8> = framer_builder.clone().finish();
+
+        Ok(AdderPlayer {
+            stream_state: StreamState {
+                current_t_ticks: 0,
+                tps: meta.tps,
+                file_pos: 0,
+                volume: meta.plane.volume(),
+                running_intensities: Array::zeros((
+                    meta.plane.h_usize(),
+                    meta.plane.w_usize(),
+                    meta.plane.c_usize(),
+                )),
+                last_timestamps: Array::zeros((
+                    meta.plane.h_usize(),
+                    meta.plane.w_usize(),
+                    meta.plane.c_usize(),
+                )),
+            },
+            framer_builder: Some(framer_builder),
+            frame_sequence: Some(frame_sequence),
+            input_stream: Some(InputStream {
+                decoder: stream,
+                bitreader,
+            }),
+            display_frame: Array3::zeros((
+                meta.plane.h_usize(),
+                meta.plane.w_usize(),
+                meta.plane.c_usize(),
+            )),
+            running_intensities: Array3::zeros((
+                meta.plane.h_usize(),
+                meta.plane.w_usize(),
+                1,
+            )),
+            flow: None,
+            comparison: None,
+            analyzer: None,
+            seek_target_ticks: None,
+            seeking: false,
+            unstarted: None,
+            live: false,
+            playback_speed,
+            reconstruction_method: ReconstructionMethod::Accurate,
+            current_frame: 0,
+        })
+    }
+
     pub fn reconstruction_method(mut self, method: ReconstructionMethod) -> Self {
         self.reconstruction_method = method;
         self
     }
 
+    pub fn optical_flow(mut self, enabled: bool) -> Self {
+        self.flow = match (&self.input_stream, enabled) {
+            (Some(stream), true) => Some(PlaneFitFlow::new(
+                stream.decoder.meta(),
+                stream.decoder.get_source_type(),
+            )),
+            _ => None,
+        };
+        self
+    }
+
+    pub fn comparison(mut self, comparison: Option<Comparison>) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    pub fn analytics(mut self, enabled: bool) -> Self {
+        self.analyzer = match (&self.input_stream, enabled) {
+            (Some(stream), true) => {
+                let meta = stream.decoder.meta();
+                Some(EventAnalyzer::new(meta.plane, meta.time_mode))
+            }
+            _ => None,
+        };
+        self
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// Whether the stream has ended for good, as a live stream does when its connection closes
+    pub fn is_finished(&self) -> bool {
+        self.input_stream.is_none()
+    }
+
+    /// Get the time of the latest reconstructed frame, in seconds
+    pub fn current_time(&self) -> f64 {
+        self.stream_state.current_t_ticks as f64 / self.stream_state.tps.max(1) as f64
+    }
+
+    /// Get the length of time covered by each reconstructed frame, in seconds
+    pub fn frame_length(&self) -> f64 {
+        match &self.input_stream {
+            None => 0.0,
+            Some(stream) => {
+                let meta = stream.decoder.meta();
+                let frame_length = if is_framed(meta.source_camera) {
+                    meta.ref_interval as f64 / meta.tps as f64
+                } else {
+                    1.0 / 60.0
+                };
+                frame_length * self.playback_speed as f64
+            }
+        }
+    }
+
+    /// Start playback at the given time, in seconds. On the first call to
+    /// [`consume_source`](Self::consume_source), the stream is seeked to about
+    /// `2 * delta_t_max` ticks before the target, where possible, and decoded from there. Streams
+    /// which can't be seeked, such as raw streams in [`TimeMode::DeltaT`], are decoded from their
+    /// start instead. The frames before the target are reconstructed without preparing their
+    /// images, or estimating their flow and features.
+    pub fn seek_time(mut self, seconds: f64) -> Self {
+        self.seek_target_ticks = if seconds > 0.0 {
+            Some((seconds * self.stream_state.tps as f64) as DeltaT)
+        } else {
+            None
+        };
+        self
+    }
+
     pub fn stream_pos(mut self, pos: u64) -> Self {
         if let Some(ref mut stream) = self.input_stream {
             if pos > stream.decoder.meta().header_size as u64 {
@@ -180,7 +327,7 @@ impl AdderPlayer {
     pub fn consume_source(&mut self, detect_features: bool) -> PlayerStreamArtifact {
         let stream = match &mut self.input_stream {
             None => {
-                return (0, self.stream_state.clone(), None);
+                return (0, self.stream_state.clone(), None, None, None);
             }
             Some(s) => s,
         };
@@ -203,10 +350,40 @@ impl AdderPlayer {
             }
         }
 
-        let res = match self.reconstruction_method {
-            ReconstructionMethod::Fast => self.consume_source_fast(detect_features),
-            ReconstructionMethod::Accurate => self.consume_source_accurate(),
-        };
+        let mut seek_event_count = 0;
+        if let Some(target) = self.seek_target_ticks.take() {
+            // Stop a frame before the target, so that the next frame reconstructed is the one
+            // nearest to it
+            if let Err(e) = self.seek_stream(target) {
+                eprintln!("Couldn't seek the stream: {}", e);
+            }
+            let frame_ticks = (self.frame_length() * self.stream_state.tps as f64) as DeltaT;
+            self.seeking = true;
+            while self.stream_state.current_t_ticks + frame_ticks * 3 / 2 <= target {
+                let before = self.stream_state.current_t_ticks;
+                match self.reconstruct(false) {
+                    Ok((event_count, _)) => seek_event_count += event_count,
+                    Err(_) => break,
+                }
+                if self.stream_state.current_t_ticks < before {
+                    // The target is past the end of the stream, and we looped
+                    break;
+                }
+            }
+            self.seeking = false;
+            // Only analyze the events from where playback starts
+            if let Some(analyzer) = &mut self.analyzer {
+                analyzer.take_tally();
+            }
+        }
+
+        let res = self
+            .reconstruct(detect_features)
+            .map(|(event_count, image)| (event_count + seek_event_count, image));
+        if res.is_err() && self.is_live() {
+            // The connection closed, and a live stream can't loop
+            self.input_stream = None;
+        }
 
         self.stream_state.file_pos = match &mut self.input_stream {
             None => 0,
@@ -216,8 +393,116 @@ impl AdderPlayer {
                 .unwrap_or(0),
         };
         match res {
-            Ok(a) => (a.0, self.stream_state.clone(), a.1),
-            Err(_b) => (0, self.stream_state.clone(), None),
+            Ok(a) => {
+                let time = self.current_time();
+                let comparison = match (&mut self.comparison, &a.1) {
+                    (Some(comparison), Some(_)) => {
+                        match comparison.compare(&self.display_frame, time) {
+                            Ok(artifact) => Some(artifact),
+                            Err(e) => {
+                                eprintln!("Comparison error: {}", e);
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                let tally = self.analyzer.as_mut().map(EventAnalyzer::take_tally);
+                (a.0, self.stream_state.clone(), a.1, comparison, tally)
+            }
+            Err(_b) => (0, self.stream_state.clone(), None, None, None),
+        }
+    }
+
+    /// Position the stream shortly before `target`, so that reaching it needn't reconstruct every
+    /// frame from the start of the stream. Since every pixel fires at least once per
+    /// `delta_t_max`, playback resumes `delta_t_max` ticks after the seeked position, and each
+    /// pixel's first event before then only establishes its running time. Live streams, and
+    /// streams which can't be seeked, are left where they are.
+    fn seek_stream(&mut self, target: DeltaT) -> Result<(), Box<dyn Error>> {
+        let stream = match (&mut self.input_stream, self.live) {
+            (Some(stream), false) => stream,
+            _ => return Ok(()),
+        };
+        let meta = *stream.decoder.meta();
+        let margin = 2 * BigT::from(meta.delta_t_max);
+        if BigT::from(target) <= margin {
+            return Ok(());
+        }
+
+        let position = match random_access_position(
+            &mut stream.decoder,
+            &mut stream.bitreader,
+            BigT::from(target) - margin,
+        ) {
+            Ok(Some(position)) => position,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                // Searching a raw stream moves its position, so restart it from the header
+                stream
+                    .decoder
+                    .set_input_stream_position(&mut stream.bitreader, meta.header_size as u64)?;
+                return Err(e.into());
+            }
+        };
+        stream
+            .decoder
+            .set_input_stream_position(&mut stream.bitreader, position)?;
+
+        self.restart_at(target - meta.delta_t_max);
+        Ok(())
+    }
+
+    /// Start reconstructing frames at `start_t`, after events have been skipped or consumed
+    /// without the current reconstruction. Each pixel's next event only establishes its running
+    /// time.
+    fn restart_at(&mut self, start_t: DeltaT) {
+        let plane = self.stream_state.last_timestamps.raw_dim();
+        match self.reconstruction_method {
+            ReconstructionMethod::Fast => {
+                let frame_ticks = (self.frame_length() * self.stream_state.tps as f64) as DeltaT;
+                self.stream_state.last_timestamps = Array3::zeros(plane);
+                self.unstarted = Some(Array3::from_elem(plane, true));
+                self.stream_state.current_t_ticks = start_t;
+                self.current_frame = start_t / frame_ticks.max(1) + 1;
+            }
+            ReconstructionMethod::Accurate => {
+                if let Some(builder) = &self.framer_builder {
+                    let mut frame_sequence: FrameSequence<u8> = builder.clone().finish();
+                    frame_sequence.start_at(BigT::from(start_t));
+                    let tpf = frame_sequence.state.tpf;
+                    self.stream_state.current_t_ticks = start_t.div_ceil(tpf) * tpf;
+                    self.frame_sequence = Some(frame_sequence);
+                }
+            }
+        }
+    }
+
+    /// Change how a live stream is reconstructed, without reconnecting to it. Only the framer
+    /// and the optional analyses are rebuilt, so playback continues from the latest events.
+    pub(crate) fn reconfigure(&mut self, settings: LiveSettings) {
+        self.framer_builder = self.framer_builder.take().map(|builder| {
+            builder
+                .view_mode(settings.view_mode)
+                .detect_features(settings.detect_features)
+                .buffer_limit(settings.buffer_limit)
+        });
+        self.frame_sequence = self.framer_builder.clone().map(|builder| builder.finish());
+        self.reconstruction_method = settings.reconstruction_method;
+        if self.stream_state.current_t_ticks > 0 {
+            self.restart_at(self.stream_state.current_t_ticks);
+        }
+
+        *self = std::mem::take(self)
+            .optical_flow(settings.optical_flow)
+            .analytics(settings.analytics)
+            .comparison(settings.comparison);
+    }
+
+    fn reconstruct(&mut self, detect_features: bool) -> Result<PlayerArtifact, Box<dyn Error>> {
+        match self.reconstruction_method {
+            ReconstructionMethod::Fast => self.consume_source_fast(detect_features),
+            ReconstructionMethod::Accurate => self.consume_source_accurate(),
         }
     }
 
@@ -263,6 +548,9 @@ impl AdderPlayer {
         //     )?;
         // }
 
+        // The flow vectors estimated during this frame, drawn once the frame is complete
+        let mut flow_vectors: Vec<(Coord, FlowVector)> = Vec::new();
+
         let image_bevy = loop {
             let display_mat = &mut self.display_frame;
             let color = display_mat.shape()[2] == 3;
@@ -271,18 +559,49 @@ impl AdderPlayer {
                 > (self.current_frame as u128 * frame_length as u128)
             {
                 self.current_frame += 1;
+                if self.seeking {
+                    break None;
+                }
 
-                let image_bevy =
-                    prep_bevy_image(display_mat.clone(), color, meta.plane.w(), meta.plane.h())?;
+                let mut image_mat = display_mat.clone();
+                for (coord, vector) in &flow_vectors {
+                    draw_flow_vector(
+                        coord.x,
+                        coord.y,
+                        vector.vx,
+                        vector.vy,
+                        FLOW_DRAW_SECONDS,
+                        &mut image_mat,
+                    );
+                }
+                let image_bevy = prep_bevy_image(image_mat, color, meta.plane.w(), meta.plane.h())?;
                 break Some(image_bevy);
             }
 
             match stream.decoder.digest_event(&mut stream.bitreader) {
                 Ok(mut event) if event.d <= D_ZERO_INTEGRATION => {
                     event_count += 1;
+                    if let Some(analyzer) = &mut self.analyzer {
+                        analyzer.ingest(&event);
+                    }
+
+                    // Estimate the flow before the event's timestamp is made relative
+                    if let (Some(flow), false) = (&mut self.flow, self.seeking) {
+                        if let Some(vector) = flow.ingest_event(&event) {
+                            flow_vectors.push((event.coord, vector));
+                        }
+                    }
                     let y = event.coord.y as i32;
                     let x = event.coord.x as i32;
                     let c = event.coord.c.unwrap_or(0) as i32;
+
+                    // After a seek, a pixel's first event only establishes its running time
+                    let first_after_seek = self.unstarted.as_mut().is_some_and(|unstarted| {
+                        std::mem::replace(
+                            &mut unstarted[[y as usize, x as usize, c as usize]],
+                            false,
+                        )
+                    });
                     // if (y | x | c) == 0x0 {
                     //     self.stream_state.current_t_ticks += event.delta_t;
                     // }
@@ -313,6 +632,9 @@ impl AdderPlayer {
                                 + 1)
                                 * meta.ref_interval;
                         }
+                        if first_after_seek {
+                            continue;
+                        }
                         event.t = dt;
                     } else {
                         panic!("Relative time mode is deprecated.");
@@ -443,16 +765,11 @@ impl AdderPlayer {
                     }
                 }
                 Err(_e) => {
-                    if stream.decoder.get_compression_type() == EncoderType::Raw {
-                        stream.decoder.set_input_stream_position(
-                            &mut stream.bitreader,
-                            meta.header_size as u64,
-                        )?;
-                    } else {
-                        stream
-                            .decoder
-                            .set_input_stream_position(&mut stream.bitreader, 1)?;
-                    }
+                    // Both raw and compressed streams restart right after the header
+                    stream.decoder.set_input_stream_position(
+                        &mut stream.bitreader,
+                        meta.header_size as u64,
+                    )?;
                     self.frame_sequence =
                         self.framer_builder.clone().map(|builder| builder.finish());
                     self.stream_state.last_timestamps = Array::zeros((
@@ -462,12 +779,18 @@ impl AdderPlayer {
                     ));
                     self.stream_state.current_t_ticks = 0;
                     self.current_frame = 0;
+                    self.unstarted = None;
+                    if let Some(flow) = &mut self.flow {
+                        *flow = PlaneFitFlow::new(&meta, stream.decoder.get_source_type());
+                    }
 
                     break None;
                 }
-                _ => {
-                    // Got an event with 0 integration, so don't need to update a pixel value
-                    // eprintln!("???");
+                Ok(event) => {
+                    // Got an empty event, so don't need to update a pixel value
+                    if let Some(analyzer) = &mut self.analyzer {
+                        analyzer.ingest(&event);
+                    }
                 }
             }
         };
@@ -525,7 +848,8 @@ impl AdderPlayer {
             //     idx += 1;
             // }
 
-            if let Some(feature_interval) = frame_sequence.pop_features() {
+            let features = frame_sequence.pop_features();
+            if let Some(feature_interval) = features.filter(|_| !self.seeking) {
                 for feature in feature_interval.features {
                     let db = display_mat.as_slice_mut().unwrap();
 
@@ -589,6 +913,9 @@ impl AdderPlayer {
             // show_display_force("keypoints", &keypoint_mat, 1)?;
 
             self.stream_state.current_t_ticks += frame_sequence.state.tpf;
+            if self.seeking {
+                return Ok((0, None));
+            }
 
             let image_mat = self.display_frame.clone();
             let color = image_mat.shape()[2] == 3;
@@ -609,6 +936,9 @@ impl AdderPlayer {
             match stream.decoder.digest_event(&mut stream.bitreader) {
                 Ok(mut event) => {
                     event_count += 1;
+                    if let Some(analyzer) = &mut self.analyzer {
+                        analyzer.ingest(&event);
+                    }
                     let filled = frame_sequence.ingest_event(&mut event, last_event);
 
                     last_event = Some(event);
@@ -623,16 +953,11 @@ impl AdderPlayer {
                         eprintln!("Completely done");
                         // TODO: Need to reset the UI event count events_ppc count when looping back here
                         // Loop/restart back to the beginning
-                        if stream.decoder.get_compression_type() == EncoderType::Raw {
-                            stream.decoder.set_input_stream_position(
-                                &mut stream.bitreader,
-                                meta.header_size as u64,
-                            )?;
-                        } else {
-                            stream
-                                .decoder
-                                .set_input_stream_position(&mut stream.bitreader, 1)?;
-                        }
+                        // Both raw and compressed streams restart right after the header
+                        stream.decoder.set_input_stream_position(
+                            &mut stream.bitreader,
+                            meta.header_size as u64,
+                        )?;
 
                         self.frame_sequence =
                             self.framer_builder.clone().map(|builder| builder.finish());
@@ -653,3 +978,69 @@ impl AdderPlayer {
         }
     }
 }
+
+/// Find the duration of a stream, in seconds. Compressed streams are measured by their number of
+/// ADUs, and raw [`TimeMode::AbsoluteT`] streams by the latest of their last events. Other raw
+/// streams are decoded in full, so they can be slow to measure.
+pub(crate) fn stream_duration(path: &Path) -> Result<f64, Box<dyn Error>> {
+    let (mut stream, mut bitreader) =
+        open_file_decoder(path.to_str().ok_or("Invalid path string")?)?;
+    let meta = *stream.meta();
+    let header_size = meta.header_size as u64;
+
+    if stream.get_compression_type() == codec::EncoderType::Compressed {
+        // Every ADU spans the same number of ticks, and each is prefixed by its size, so they're
+        // counted without decoding them
+        let mut adu_count: BigT = 0;
+        let mut position = header_size;
+        loop {
+            bitreader.seek_bits(SeekFrom::Start(position * 8))?;
+            let mut size = [0u8; 4];
+            match bitreader.read_bytes(&mut size) {
+                Ok(()) => position += 4 + u64::from(u32::from_be_bytes(size)),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            adu_count += 1;
+        }
+        let adu_span = meta.adu_interval as BigT * BigT::from(meta.ref_interval);
+        return Ok((adu_count * adu_span) as f64 / meta.tps as f64);
+    }
+
+    if meta.codec_version >= 2 && meta.time_mode == TimeMode::AbsoluteT {
+        // Events are approximately in timestamp order, and every pixel fires at least once per
+        // delta_t_max, so the latest event is among the last few
+        let event_size = u64::from(meta.event_size);
+        let stream_size = bitreader.seek_bits(SeekFrom::End(0))? / 8;
+        let event_count = stream_size.saturating_sub(header_size) / event_size;
+        let tail_count = event_count.min(meta.plane.volume() as u64 + 1);
+        stream.set_input_stream_position(
+            &mut bitreader,
+            header_size + (event_count - tail_count) * event_size,
+        )?;
+
+        let mut t_max: BigT = 0;
+        while let Ok(event) = stream.digest_event(&mut bitreader) {
+            t_max = t_max.max(BigT::from(event.t));
+        }
+        return Ok(t_max as f64 / meta.tps as f64);
+    }
+
+    // The absolute timestamp of the last event of each pixel
+    let mut t_tree: Array3<BigT> = Array3::zeros((
+        meta.plane.h_usize(),
+        meta.plane.w_usize(),
+        meta.plane.c_usize(),
+    ));
+    let mut t_max: BigT = 0;
+    while let Ok(event) = stream.digest_event(&mut bitreader) {
+        let last_t = &mut t_tree[[
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ]];
+        *last_t += BigT::from(event.t);
+        t_max = t_max.max(*last_t);
+    }
+    Ok(t_max as f64 / meta.tps as f64)
+}
@@ -0,0 +1,227 @@
+use crate::player::adder::AdderPlayer;
+use crate::player::ui::ReconstructionMethod;
+use crate::utils::{difference_heatmap, prep_bevy_image};
+use adder_codec_rs::adder_codec_core::PlaneSize;
+use adder_codec_rs::transcoder::source::video::FramedViewMode;
+use adder_codec_rs::utils::cv::{calculate_quality_metrics, handle_color, QualityMetrics};
+use bevy::prelude::Image;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use video_rs_adder_dep::{Decoder, Frame, Locator, Options, Resize};
+
+/// The most times the reference is advanced for a single frame of the main stream, so that a
+/// reference which falls far behind can't stall playback
+const MAX_REFERENCE_STEPS: usize = 1000;
+
+/// The result of comparing a frame of the main stream to the reference
+pub struct ComparisonArtifact {
+    /// The latest frame of the reference
+    pub(crate) reference: Option<Image>,
+
+    /// The heat map of the differences between the frames, if they have the same shape
+    pub(crate) difference: Option<Image>,
+
+    /// The quality of the main stream's frame, if the frames have the same shape
+    pub(crate) metrics: Option<QualityMetrics>,
+}
+
+/// The settings with which the main stream is played, so the reference can be played the same way
+#[derive(Clone)]
+pub struct PlaybackSettings {
+    pub(crate) playback_speed: f32,
+    pub(crate) view_mode: FramedViewMode,
+    pub(crate) buffer_limit: Option<u32>,
+    pub(crate) reconstruction_method: ReconstructionMethod,
+}
+
+enum ReferenceSource {
+    /// Another ADΔER stream
+    Adder(Box<AdderPlayer>),
+
+    /// A framed video, such as the source of the main stream
+    Video {
+        decoder: Decoder,
+        frame_rate: f64,
+        frames_decoded: u64,
+    },
+}
+
+/// Plays a reference stream in sync with the player's main stream, and compares their frames
+pub struct Comparison {
+    path: PathBuf,
+    plane: PlaneSize,
+    settings: PlaybackSettings,
+    metric_ssim: bool,
+    source: ReferenceSource,
+
+    /// The latest frame of the reference
+    reference_frame: Option<Frame>,
+
+    /// The time of the main stream's last frame, in seconds
+    last_time: f64,
+}
+
+// The video decoder is only ever used by the player thread
+unsafe impl Send for Comparison {}
+
+impl Comparison {
+    /// Open a reference stream (`.adder` or `.mp4`) to compare against a main stream with the
+    /// given plane. Videos are resized to the plane.
+    pub fn new(
+        path: &Path,
+        plane: PlaneSize,
+        settings: PlaybackSettings,
+        metric_ssim: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Comparison {
+            path: path.to_path_buf(),
+            plane,
+            source: open_reference(path, plane, &settings)?,
+            settings,
+            metric_ssim,
+            reference_frame: None,
+            last_time: 0.0,
+        })
+    }
+
+    /// Advance the reference to `time`, in seconds, and compare its latest frame to `frame`
+    pub fn compare(
+        &mut self,
+        frame: &Frame,
+        time: f64,
+    ) -> Result<ComparisonArtifact, Box<dyn Error>> {
+        // The main stream looped, so start the reference over
+        if time < self.last_time {
+            self.source = open_reference(&self.path, self.plane, &self.settings)?;
+            self.reference_frame = None;
+        }
+        self.last_time = time;
+        self.advance(time)?;
+
+        let reference = match &self.reference_frame {
+            None => {
+                return Ok(ComparisonArtifact {
+                    reference: None,
+                    difference: None,
+                    metrics: None,
+                })
+            }
+            Some(reference) => reference,
+        };
+        let (height, width, channels) = reference.dim();
+        let reference_image = prep_bevy_image(
+            reference.clone(),
+            channels == 3,
+            width as u16,
+            height as u16,
+        )?;
+        if reference.shape() != frame.shape() {
+            return Ok(ComparisonArtifact {
+                reference: Some(reference_image),
+                difference: None,
+                metrics: None,
+            });
+        }
+
+        let metrics = calculate_quality_metrics(
+            reference,
+            frame,
+            QualityMetrics {
+                psnr: Some(0.0),
+                mse: Some(0.0),
+                ssim: if self.metric_ssim { Some(0.0) } else { None },
+                ms_ssim: None,
+            },
+        )?;
+        let difference = prep_bevy_image(
+            difference_heatmap(reference, frame),
+            true,
+            width as u16,
+            height as u16,
+        )?;
+        Ok(ComparisonArtifact {
+            reference: Some(reference_image),
+            difference: Some(difference),
+            metrics: Some(metrics),
+        })
+    }
+
+    /// Play the reference until its latest frame ends at or after `time`, in seconds
+    fn advance(&mut self, time: f64) -> Result<(), Box<dyn Error>> {
+        match &mut self.source {
+            ReferenceSource::Adder(player) => {
+                for _ in 0..MAX_REFERENCE_STEPS {
+                    let before = player.current_time();
+                    if before >= time && self.reference_frame.is_some() {
+                        break;
+                    }
+                    let (_, _, image, _, _) = player.consume_source(false);
+                    if image.is_some() {
+                        self.reference_frame = Some(player.display_frame.clone());
+                    }
+                    if player.current_time() < before {
+                        // The reference ended before the main stream, and looped
+                        break;
+                    }
+                }
+            }
+            ReferenceSource::Video {
+                decoder,
+                frame_rate,
+                frames_decoded,
+            } => {
+                for _ in 0..MAX_REFERENCE_STEPS {
+                    if *frames_decoded as f64 / *frame_rate >= time
+                        && self.reference_frame.is_some()
+                    {
+                        break;
+                    }
+                    let (_, frame) = match decoder.decode() {
+                        Ok(decoded) => decoded,
+                        Err(_) => break,
+                    };
+                    *frames_decoded += 1;
+                    self.reference_frame = Some(handle_color(frame, self.plane.c() == 3)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_reference(
+    path: &Path,
+    plane: PlaneSize,
+    settings: &PlaybackSettings,
+) -> Result<ReferenceSource, Box<dyn Error>> {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("adder") => Ok(ReferenceSource::Adder(Box::new(
+            AdderPlayer::new(
+                path,
+                settings.playback_speed,
+                settings.view_mode,
+                false,
+                settings.buffer_limit,
+            )?
+            .reconstruction_method(settings.reconstruction_method.clone()),
+        ))),
+        Some("mp4") => {
+            let decoder = Decoder::new_with_options_and_resize(
+                &Locator::Path(path.to_path_buf()),
+                &Options::default(),
+                Resize::Exact(plane.w().into(), plane.h().into()),
+            )?;
+            Ok(ReferenceSource::Video {
+                frame_rate: f64::from(decoder.frame_rate()),
+                decoder,
+                frames_decoded: 0,
+            })
+        }
+        _ => Err("Invalid comparison file type".into()),
+    }
+}
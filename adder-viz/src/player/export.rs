@@ -0,0 +1,337 @@
+#[cfg(feature = "compression")]
+use adder_codec_rs::adder_codec_core::codec::compressed::stream::CompressedOutput;
+use adder_codec_rs::adder_codec_core::codec::encoder::Encoder;
+use adder_codec_rs::adder_codec_core::codec::raw::stream::RawOutput;
+use adder_codec_rs::adder_codec_core::codec::{CodecMetadata, EncoderOptions, EncoderType};
+use adder_codec_rs::adder_codec_core::{
+    is_framed, open_file_decoder, AbsoluteT, BigT, Event, PlaneSize, SourceType, TimeMode, D_MAX,
+};
+use adder_codec_rs::framer::driver::FramerMode::INSTANTANEOUS;
+use adder_codec_rs::framer::driver::{FrameSequence, Framer, FramerBuilder, PixelClock};
+use adder_codec_rs::framer::writers::{FrameWriter, ImageFormat, ImageSequenceWriter, Y4mWriter};
+use adder_codec_rs::transcoder::source::video::FramedViewMode;
+use ndarray::Array3;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The output format of an exported clip
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum ClipFormat {
+    /// A new ADΔER file, with the given representation
+    #[default]
+    Adder,
+
+    /// A Y4M video of the frames reconstructed from the clip
+    Y4m,
+
+    /// A directory of PNG images of the frames reconstructed from the clip
+    PngSequence,
+}
+
+/// A rectangular region of the image plane, in pixels
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct ClipRegion {
+    pub(crate) x: u16,
+    pub(crate) y: u16,
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+}
+
+impl ClipRegion {
+    /// The region covering the whole of a plane
+    pub fn full(plane: PlaneSize) -> Self {
+        ClipRegion {
+            x: 0,
+            y: 0,
+            width: plane.w(),
+            height: plane.h(),
+        }
+    }
+
+    fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && x - self.x < self.width && y >= self.y && y - self.y < self.height
+    }
+}
+
+/// What to export from a stream
+#[derive(Clone)]
+pub struct ClipSettings {
+    /// The start of the clip, in seconds
+    pub(crate) start: f64,
+
+    /// The end of the clip, in seconds
+    pub(crate) end: f64,
+
+    pub(crate) region: ClipRegion,
+    pub(crate) format: ClipFormat,
+
+    /// The representation of an ADΔER clip
+    pub(crate) encoder_type: EncoderType,
+
+    /// The view mode of reconstructed frames
+    pub(crate) view_mode: FramedViewMode,
+}
+
+/// Write the events of a time range and region of an ADΔER stream to a new file. Returns the
+/// number of events in the clip.
+///
+/// The clip's timestamps start at zero, and its coordinates are relative to the region. The clip
+/// is always in absolute time mode, so the events of a [`TimeMode::DeltaT`] stream are moved into
+/// absolute time. The first event of each pixel in the clip may have integrated intensity from
+/// before the clip's start, so its `D` is scaled down to the part of the integration within the
+/// clip. This is only exact to the nearest power of two.
+pub fn export_clip(
+    input: &Path,
+    output: &Path,
+    settings: &ClipSettings,
+) -> Result<u64, Box<dyn Error>> {
+    let (mut stream, mut bitreader) =
+        open_file_decoder(input.to_str().ok_or("Invalid path string")?)?;
+    let meta = *stream.meta();
+    let region = settings.region;
+    if region.width == 0
+        || region.height == 0
+        || u32::from(region.x) + u32::from(region.width) > u32::from(meta.plane.w())
+        || u32::from(region.y) + u32::from(region.height) > u32::from(meta.plane.h())
+    {
+        return Err(format!("Region {region:?} is outside the image plane").into());
+    }
+    if settings.end <= settings.start {
+        return Err("The clip must end after it starts".into());
+    }
+
+    let plane = PlaneSize::new(region.width, region.height, meta.plane.c())?;
+    let clip_meta = CodecMetadata {
+        codec_version: meta.codec_version.max(2),
+        header_size: 0,
+        time_mode: TimeMode::AbsoluteT,
+        plane,
+        event_size: 0,
+        ..meta
+    };
+    let mut clipper = EventClipper::new(
+        &meta,
+        region,
+        (settings.start * meta.tps as f64) as BigT,
+        (settings.end * meta.tps as f64) as BigT,
+    );
+    let mut sink = ClipSink::new(
+        output,
+        settings,
+        clip_meta,
+        stream.get_source_type(),
+        reconstructed_frame_rate(&meta),
+    )?;
+
+    let mut event_count = 0;
+    loop {
+        match stream.digest_event(&mut bitreader) {
+            Ok(event) => {
+                if let Some(event) = clipper.clip(event) {
+                    sink.ingest(event)?;
+                    event_count += 1;
+                }
+
+                // Events are only roughly in time order, so read a little past the end
+                if clipper.latest_t > clipper.end + BigT::from(meta.delta_t_max) {
+                    break;
+                }
+            }
+            Err(e) if stream.is_end_of_stream(&e) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    sink.finish()?;
+    Ok(event_count)
+}
+
+/// The frame rate at which the player reconstructs a stream at normal speed
+fn reconstructed_frame_rate(meta: &CodecMetadata) -> f32 {
+    if is_framed(meta.source_camera) {
+        meta.tps as f32 / meta.ref_interval as f32
+    } else {
+        60.0
+    }
+}
+
+/// Selects the events of a clip and moves them into its time and space
+struct EventClipper {
+    region: ClipRegion,
+    start: BigT,
+    end: BigT,
+    clock: PixelClock,
+
+    /// The running time of each pixel, clipped or not
+    running_t: Array3<BigT>,
+
+    /// The latest time at which any event fired
+    latest_t: BigT,
+}
+
+impl EventClipper {
+    fn new(meta: &CodecMetadata, region: ClipRegion, start: BigT, end: BigT) -> Self {
+        EventClipper {
+            region,
+            start,
+            end,
+            clock: PixelClock::from_meta(meta),
+            running_t: Array3::zeros((
+                meta.plane.h_usize(),
+                meta.plane.w_usize(),
+                meta.plane.c_usize(),
+            )),
+            latest_t: 0,
+        }
+    }
+
+    fn clip(&mut self, mut event: Event) -> Option<Event> {
+        let running_t = self.running_t.get_mut([
+            event.coord.y_usize(),
+            event.coord.x_usize(),
+            event.coord.c_usize(),
+        ])?;
+        let last_t = *running_t;
+        let (_, fired_t) = self.clock.advance(running_t, &event)?;
+        self.latest_t = self.latest_t.max(fired_t);
+
+        let (x, y) = (event.coord.x, event.coord.y);
+        if !self.region.contains(x, y) {
+            return None;
+        }
+
+        // An event at the start would have no time to integrate over
+        if fired_t <= self.start || fired_t > self.end {
+            return None;
+        }
+
+        if last_t < self.start && event.d <= D_MAX {
+            let integrated = (fired_t - last_t) as f64;
+            let clipped = (fired_t - self.start) as f64;
+            let d = (f64::from(event.d) + (clipped / integrated).log2()).round();
+            event.d = d.clamp(0.0, f64::from(D_MAX)) as u8;
+        }
+        event.t = (fired_t - self.start) as AbsoluteT;
+        event.coord.x = x - self.region.x;
+        event.coord.y = y - self.region.y;
+        Some(event)
+    }
+}
+
+/// The reconstructed frame outputs of a clip
+enum FrameOutput {
+    Y4m(Y4mWriter<BufWriter<File>>),
+    Png(ImageSequenceWriter),
+}
+
+impl FrameWriter<u8> for FrameOutput {
+    fn write_frame(&mut self, frame: &Array3<u8>) -> io::Result<()> {
+        match self {
+            FrameOutput::Y4m(writer) => writer.write_frame(frame),
+            FrameOutput::Png(writer) => writer.write_frame(frame),
+        }
+    }
+}
+
+enum ClipSink {
+    Events(Encoder<BufWriter<File>>),
+    Frames {
+        frame_sequence: FrameSequence<u8>,
+        writer: FrameOutput,
+    },
+}
+
+impl ClipSink {
+    fn new(
+        output: &Path,
+        settings: &ClipSettings,
+        meta: CodecMetadata,
+        source_type: SourceType,
+        frame_rate: f32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let writer = match settings.format {
+            ClipFormat::Adder => {
+                let write = BufWriter::new(File::create(output)?);
+                let options = EncoderOptions::default(meta.plane);
+                let encoder = match settings.encoder_type {
+                    EncoderType::Raw => Encoder::new_raw(RawOutput::new(meta, write), options),
+                    #[cfg(feature = "compression")]
+                    EncoderType::Compressed => {
+                        Encoder::new_compressed(CompressedOutput::new(meta, write), options)
+                    }
+                    #[cfg(not(feature = "compression"))]
+                    EncoderType::Compressed => {
+                        return Err("Compressed clips need the `compression` feature".into())
+                    }
+                    EncoderType::Empty => return Err("Clips can't use the empty encoder".into()),
+                };
+                return Ok(ClipSink::Events(encoder));
+            }
+            ClipFormat::Y4m => FrameOutput::Y4m(Y4mWriter::new(
+                BufWriter::new(File::create(output)?),
+                frame_rate,
+            )),
+            ClipFormat::PngSequence => FrameOutput::Png(ImageSequenceWriter::new(
+                output,
+                "frame_",
+                ImageFormat::Png,
+            )?),
+        };
+
+        let frame_sequence = FramerBuilder::new(meta.plane, meta.plane.h_usize())
+            .codec_version(meta.codec_version, meta.time_mode)
+            .time_parameters(
+                meta.tps,
+                meta.ref_interval,
+                meta.delta_t_max,
+                Some(frame_rate),
+            )
+            .mode(INSTANTANEOUS)
+            .view_mode(settings.view_mode)
+            .source(source_type, meta.source_camera)
+            .finish();
+        Ok(ClipSink::Frames {
+            frame_sequence,
+            writer,
+        })
+    }
+
+    fn ingest(&mut self, mut event: Event) -> Result<(), Box<dyn Error>> {
+        match self {
+            ClipSink::Events(encoder) => encoder.ingest_event(event)?,
+            ClipSink::Frames {
+                frame_sequence,
+                writer,
+            } => {
+                if frame_sequence.ingest_event(&mut event, None) {
+                    frame_sequence.write_filled_frames(writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            ClipSink::Events(encoder) => {
+                if let Some(mut writer) = encoder.close_writer()? {
+                    writer.flush()?;
+                }
+            }
+            ClipSink::Frames {
+                mut frame_sequence,
+                mut writer,
+            } => {
+                while frame_sequence.flush_frame_buffer() {
+                    frame_sequence.write_filled_frames(&mut writer)?;
+                }
+                if let FrameOutput::Y4m(writer) = writer {
+                    writer.into_inner()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
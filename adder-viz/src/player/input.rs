@@ -0,0 +1,147 @@
+use adder_codec_rs::adder_codec_core::bitstream_io::{BigEndian, BitReader};
+#[cfg(feature = "compression")]
+use adder_codec_rs::adder_codec_core::codec::compressed::stream::CompressedInput;
+use adder_codec_rs::adder_codec_core::codec::decoder::Decoder;
+use adder_codec_rs::adder_codec_core::codec::raw::stream::RawInput;
+use adder_codec_rs::adder_codec_core::codec::CodecError;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+/// How many bytes at the start of a live stream are kept, so that its header can be read again
+const LIVE_HEAD_SIZE: usize = 1024;
+
+/// The bytes an ADΔER stream is read from: a file, or a live network stream
+pub enum StreamReader {
+    File(BufReader<File>),
+    Live(LiveReader),
+}
+
+impl StreamReader {
+    pub fn open_file(path: &Path) -> io::Result<Self> {
+        Ok(StreamReader::File(BufReader::new(File::open(path)?)))
+    }
+
+    /// Connect to a live stream. `address` is either a TCP address (`host:port`, optionally
+    /// prefixed with `tcp://`), or a Unix socket (prefixed with `unix:`, or ending in `.sock`).
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let address = address.trim();
+        let unix_path = address
+            .strip_prefix("unix:")
+            .or_else(|| address.ends_with(".sock").then_some(address));
+        let inner: Box<dyn Read + Send> = match unix_path {
+            #[cfg(unix)]
+            Some(path) => Box::new(UnixStream::connect(path)?),
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Unix sockets aren't supported on this platform",
+                ))
+            }
+            None => Box::new(TcpStream::connect(
+                address.strip_prefix("tcp://").unwrap_or(address),
+            )?),
+        };
+        Ok(StreamReader::Live(LiveReader {
+            inner: BufReader::new(inner),
+            head: Vec::with_capacity(LIVE_HEAD_SIZE),
+            position: 0,
+        }))
+    }
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            StreamReader::File(reader) => reader.read(buf),
+            StreamReader::Live(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for StreamReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            StreamReader::File(reader) => reader.seek(pos),
+            StreamReader::Live(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Reads a stream which can't be rewound, such as a socket. The decoder only needs to find its
+/// position, except when it reads the header again, so the first bytes are kept for that.
+pub struct LiveReader {
+    inner: BufReader<Box<dyn Read + Send>>,
+
+    /// The first bytes read from the stream
+    head: Vec<u8>,
+
+    /// The position of the next byte to read
+    position: u64,
+}
+
+impl Read for LiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position as usize;
+        if position < self.head.len() {
+            // Read the head again after rewinding
+            let len = buf.len().min(self.head.len() - position);
+            buf[..len].copy_from_slice(&self.head[position..position + len]);
+            self.position += len as u64;
+            return Ok(len);
+        }
+
+        let len = self.inner.read(buf)?;
+        if position == self.head.len() && position < LIVE_HEAD_SIZE {
+            let kept = len.min(LIVE_HEAD_SIZE - position);
+            self.head.extend_from_slice(&buf[..kept]);
+        }
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl Seek for LiveReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            // Everything read so far was kept, so we can rewind within it
+            SeekFrom::Start(target)
+                if self.position <= self.head.len() as u64 && target <= self.position =>
+            {
+                self.position = target;
+                Ok(target)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Can't seek in a live stream",
+            )),
+        }
+    }
+}
+
+/// Open a decoder for a raw or compressed stream
+pub fn open_decoder(
+    reader: StreamReader,
+) -> Result<(Decoder<StreamReader>, BitReader<StreamReader, BigEndian>), CodecError> {
+    let mut bitreader = BitReader::endian(reader, BigEndian);
+
+    // First try reading the stream as raw, then as compressed
+    match Decoder::new_raw(RawInput::new(), &mut bitreader) {
+        Ok(decoder) => Ok((decoder, bitreader)),
+        #[cfg(feature = "compression")]
+        Err(CodecError::WrongMagic) => {
+            let mut reader = bitreader.into_reader();
+            reader.seek(SeekFrom::Start(0))?;
+            let mut bitreader = BitReader::endian(reader, BigEndian);
+            let decoder = Decoder::new_compressed(CompressedInput::new(0, 0, 0), &mut bitreader)?;
+            Ok((decoder, bitreader))
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -1,2 +1,5 @@
 mod adder;
+mod compare;
+mod export;
+mod input;
 pub mod ui;
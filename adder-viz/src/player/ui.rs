@@ -1,8 +1,8 @@
-use crossbeam_channel::{bounded, Receiver};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender, TryRecvError};
 use std::collections::VecDeque;
 use std::error::Error;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use adder_codec_rs::transcoder::source::video::FramedViewMode;
 use bevy::asset::Assets;
@@ -12,12 +12,20 @@ use bevy::prelude::{Commands, Image, Res, ResMut};
 use bevy::time::Time;
 use bevy_egui::egui::{Color32, RichText, Ui};
 
-use crate::player::adder::{AdderPlayer, PlayerStreamArtifact, StreamState};
+use crate::player::adder::{
+    stream_duration, AdderPlayer, LiveSettings, PlayerStreamArtifact, StreamState,
+};
+use crate::player::compare::{Comparison, PlaybackSettings};
+use crate::player::export::{export_clip, ClipFormat, ClipRegion, ClipSettings};
 use crate::{add_checkbox_row, add_radio_row, add_slider_row, Images};
 use bevy_egui::egui;
 
+use crate::utils::analytics::AnalyticsUiState;
 use crate::utils::PlotY;
+use adder_codec_rs::adder_codec_core::codec::EncoderType;
 use adder_codec_rs::adder_codec_core::PlaneSize;
+use bevy_egui::egui::plot::Corner::LeftTop;
+use bevy_egui::egui::plot::{Legend, Plot};
 use rayon::current_num_threads;
 
 #[derive(PartialEq)]
@@ -47,6 +55,17 @@ impl Default for ReconstructionMethod {
     }
 }
 
+/// What to show next to the main stream when comparing it to a reference
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum ComparisonView {
+    /// The reference stream itself
+    #[default]
+    Reference,
+
+    /// A heat map of the differences between the streams
+    Difference,
+}
+
 pub struct PlayerUiState {
     playing: bool,
     looping: bool,
@@ -56,10 +75,23 @@ pub struct PlayerUiState {
     total_frames: u32,
     current_time: f32,
     total_time: f32,
+    timeline_time: f32,
+    seek_time: Option<f32>,
+    frames_to_step: u32,
     ui_sliders: PlayerUiSliders,
     ui_sliders_drag: PlayerUiSliders,
     pub(crate) detect_features: bool,
+    pub(crate) show_flow: bool,
     pub(crate) buffer_limit: Option<u32>,
+    comparison_path: Option<PathBuf>,
+    comparison_view: ComparisonView,
+    metric_ssim: bool,
+    clip_start: f64,
+    clip_end: f64,
+    clip_region: ClipRegion,
+    clip_format: ClipFormat,
+    clip_encoder_type: EncoderType,
+    live_address_input: String,
 }
 
 impl Default for PlayerUiState {
@@ -73,10 +105,23 @@ impl Default for PlayerUiState {
             total_frames: 0,
             current_time: 0.0,
             total_time: 0.0,
+            timeline_time: 0.0,
+            seek_time: None,
+            frames_to_step: 0,
             ui_sliders: Default::default(),
             ui_sliders_drag: Default::default(),
             detect_features: false,
+            show_flow: false,
             buffer_limit: Some(60),
+            comparison_path: None,
+            comparison_view: ComparisonView::Reference,
+            metric_ssim: false,
+            clip_start: 0.0,
+            clip_end: 0.0,
+            clip_region: Default::default(),
+            clip_format: ClipFormat::Adder,
+            clip_encoder_type: EncoderType::Raw,
+            live_address_input: String::new(),
         }
     }
 }
@@ -89,8 +134,20 @@ pub struct InfoUiState {
     events_total: u64,
     event_size: u8,
     plane: PlaneSize,
+    frame_length: f64,
     source_name: RichText,
+    comparison_name: RichText,
+    export_status: RichText,
     pub(crate) plot_points_raw_adder_bitrate_y: PlotY,
+    plot_points_psnr_y: PlotY,
+    plot_points_ssim_y: PlotY,
+    analytics: AnalyticsUiState,
+
+    /// The wall-clock and stream times of the first frame of a live stream
+    live_start: Option<(Instant, f64)>,
+
+    /// How far a live stream's playback lags behind real time, in seconds
+    live_latency: Option<f64>,
 }
 
 impl Default for InfoUiState {
@@ -105,10 +162,22 @@ impl Default for InfoUiState {
             events_total: 0,
             event_size: 0,
             plane: Default::default(),
+            frame_length: 0.0,
             source_name: RichText::new("No file selected yet"),
+            comparison_name: RichText::new("No comparison selected"),
+            export_status: RichText::new(""),
             plot_points_raw_adder_bitrate_y: PlotY {
                 points: plot_points.clone(),
             },
+            plot_points_psnr_y: PlotY {
+                points: plot_points.clone(),
+            },
+            plot_points_ssim_y: PlotY {
+                points: plot_points,
+            },
+            analytics: Default::default(),
+            live_start: None,
+            live_latency: None,
         }
     }
 }
@@ -125,7 +194,14 @@ impl InfoUiState {
 #[derive(Resource, Default)]
 pub struct PlayerState {
     player_rx: Option<Receiver<PlayerStreamArtifact>>,
+    duration_rx: Option<Receiver<f64>>,
+    /// Receives the player for a live stream, once it's connected and its header is read
+    connect_rx: Option<Receiver<Result<AdderPlayer, String>>>,
+    /// Sends changed settings to a live stream's player, which can't be restarted
+    live_settings_tx: Option<Sender<LiveSettings>>,
+    export_rx: Option<Receiver<Result<u64, String>>>,
     player_path_buf: Option<PathBuf>,
+    live_address: Option<String>,
     ui_state: PlayerUiState,
     pub(crate) ui_info_state: InfoUiState,
 }
@@ -138,18 +214,98 @@ impl PlayerState {
         mut images: ResMut<Assets<Image>>,
         mut handles: ResMut<Images>,
     ) -> Result<(), Box<dyn Error>> {
-        if !self.ui_state.playing {
+        if let Some(duration) = self.duration_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.ui_state.total_time = duration as f32;
+            self.duration_rx = None;
+        }
+        if let Some(player) = self.connect_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.connect_rx = None;
+            let address = self.live_address.clone().unwrap_or_default();
+            let player = player.map_err(Into::into).map(|mut player| {
+                // The settings may have changed while connecting
+                player.reconfigure(self.live_settings(None));
+                player
+            });
+            self.start_player(player, format!("Live: {address}"));
+        }
+        if let Some(result) = self.export_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.ui_info_state.export_status = match result {
+                Ok(event_count) => RichText::new(format!("Exported {event_count} events"))
+                    .color(Color32::DARK_GREEN),
+                Err(e) => RichText::new(e).color(Color32::RED),
+            };
+            self.export_rx = None;
+        }
+
+        if !self.ui_state.playing && self.ui_state.frames_to_step == 0 {
             return Ok(());
         }
         if let Some(rx) = &self.player_rx {
-            let (event_count, stream_state, image_opt) = rx.try_recv()?;
+            let (event_count, stream_state, image_opt, comparison_opt, tally_opt) =
+                match rx.try_recv() {
+                    Ok(artifact) => artifact,
+                    Err(TryRecvError::Disconnected) if self.live_address.is_some() => {
+                        self.ui_info_state.source_name =
+                            RichText::new("Live stream ended").color(Color32::RED);
+                        self.ui_info_state.live_latency = None;
+                        self.player_rx = None;
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
             self.ui_info_state.events_total += event_count;
             self.ui_info_state.stream_state = stream_state;
+            self.ui_state.current_time = (self.ui_info_state.stream_state.current_t_ticks as f64
+                / self.ui_info_state.stream_state.tps.max(1) as f64)
+                as f32;
+            // The duration of a stream is unknown until it's been scanned
+            self.ui_state.total_time = self.ui_state.total_time.max(self.ui_state.current_time);
+
+            if let Some(comparison) = comparison_opt {
+                let metrics = comparison.metrics;
+                self.ui_info_state
+                    .plot_points_psnr_y
+                    .update(metrics.and_then(|m| m.psnr));
+                self.ui_info_state
+                    .plot_points_ssim_y
+                    .update(metrics.and_then(|m| m.ssim));
+
+                let side_image = match self.ui_state.comparison_view {
+                    ComparisonView::Reference => comparison.reference,
+                    ComparisonView::Difference => comparison.difference,
+                };
+                images.remove(&handles.input_view);
+                handles.input_view = match side_image {
+                    Some(image) => images.add(image),
+                    None => Default::default(),
+                };
+            } else if self.ui_state.comparison_path.is_none() {
+                handles.input_view = Default::default();
+            }
+
+            if let Some(tally) = tally_opt {
+                self.ui_info_state.analytics.add_tally(tally);
+            }
 
-            if let Some(image) = image_opt {
+            if let Some(mut image) = image_opt {
+                if self.live_address.is_some() {
+                    let time = self.ui_state.current_time as f64;
+                    let (wall_start, stream_start) = *self
+                        .ui_info_state
+                        .live_start
+                        .get_or_insert((Instant::now(), time));
+                    self.ui_info_state.live_latency =
+                        Some(wall_start.elapsed().as_secs_f64() - (time - stream_start));
+                }
+                let analytics = &mut self.ui_info_state.analytics;
+                if analytics.enabled {
+                    analytics.end_frame(self.ui_info_state.frame_length);
+                    analytics.draw_density_overlay(&mut image);
+                }
                 images.remove(&handles.image_view);
                 let handle = images.add(image);
                 handles.image_view = handle;
+                self.ui_state.frames_to_step = self.ui_state.frames_to_step.saturating_sub(1);
             } else if self.ui_info_state.stream_state.file_pos == 1 {
                 dbg!("Looping...");
                 self.reset_update_adder_params(true);
@@ -227,6 +383,9 @@ impl PlayerState {
         //     }
         // }
 
+        // Live streams can't be seeked
+        let seekable = self.live_address.is_none();
+
         ui.add_enabled(true, egui::Label::new("Playback controls:"));
         ui.horizontal(|ui| {
             if self.ui_state.playing {
@@ -247,6 +406,50 @@ impl PlayerState {
                 self.ui_info_state.stream_state.file_pos = 0; // To force the player to restart
                 need_to_update = true;
             }
+
+            if ui
+                .add_enabled(seekable, egui::Button::new("⏪"))
+                .on_hover_text("Step back one frame")
+                .clicked()
+            {
+                self.ui_state.playing = false;
+                self.ui_state.seek_time = Some(
+                    (self.ui_state.current_time - self.ui_info_state.frame_length as f32).max(0.0),
+                );
+                // Show the frame that the player stops at
+                self.ui_state.frames_to_step = 1;
+                need_to_update = true;
+            }
+            if ui
+                .button("⏩")
+                .on_hover_text("Step forward one frame")
+                .clicked()
+            {
+                self.ui_state.playing = false;
+                self.ui_state.frames_to_step += 1;
+            }
+        });
+        ui.end_row();
+
+        ui.label("Timeline:");
+        ui.horizontal(|ui| {
+            let response = ui.add_enabled(
+                seekable,
+                egui::Slider::new(
+                    &mut self.ui_state.timeline_time,
+                    0.0..=self.ui_state.total_time,
+                )
+                .suffix(" s")
+                .max_decimals(2),
+            );
+            if response.drag_released() || (response.changed() && !response.dragged()) {
+                self.ui_state.seek_time = Some(self.ui_state.timeline_time);
+                self.ui_state.frames_to_step = u32::from(!self.ui_state.playing);
+                need_to_update = true;
+            } else if !response.dragged() {
+                self.ui_state.timeline_time = self.ui_state.current_time;
+            }
+            ui.label(format!("/ {:.2} s", self.ui_state.total_time));
         });
         ui.end_row();
 
@@ -339,11 +542,200 @@ impl PlayerState {
             .changed();
         ui.end_row();
 
+        ui.label("Motion:");
+        need_to_update |= ui
+            .add_enabled(
+                self.ui_state.reconstruction_method == ReconstructionMethod::Fast,
+                egui::Checkbox::new(&mut self.ui_state.show_flow, "Show optical flow"),
+            )
+            .changed();
+        ui.end_row();
+
+        ui.label("Compare with:");
+        ui.horizontal(|ui| {
+            if ui.button("Open reference").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("adder video", &["adder"])
+                    .add_filter("framed video", &["mp4"])
+                    .pick_file()
+                {
+                    self.ui_state.comparison_path = Some(path);
+                    need_to_update = true;
+                }
+            }
+            if ui
+                .add_enabled(
+                    self.ui_state.comparison_path.is_some(),
+                    egui::Button::new("Clear"),
+                )
+                .clicked()
+            {
+                self.ui_state.comparison_path = None;
+                need_to_update = true;
+            }
+        });
+        ui.end_row();
+
+        ui.label("");
+        ui.label(self.ui_info_state.comparison_name.clone());
+        ui.end_row();
+
+        let comparing = self.ui_state.comparison_path.is_some();
+        add_radio_row(
+            comparing,
+            "Comparison view:",
+            vec![
+                ("Reference", ComparisonView::Reference),
+                ("Difference", ComparisonView::Difference),
+            ],
+            ui,
+            &mut self.ui_state.comparison_view,
+        );
+        need_to_update |= add_checkbox_row(
+            comparing,
+            "Metrics:",
+            "SSIM (Warning: slow!)",
+            ui,
+            &mut self.ui_state.metric_ssim,
+        );
+
+        self.clip_export_grid_contents(ui);
+
         if need_to_update {
             self.reset_update_adder_params(true)
         }
     }
 
+    fn clip_export_grid_contents(&mut self, ui: &mut Ui) {
+        let total_time = f64::from(self.ui_state.total_time);
+        let current_time = f64::from(self.ui_state.current_time);
+        for (label, time) in [
+            ("Clip start:", &mut self.ui_state.clip_start),
+            ("Clip end:", &mut self.ui_state.clip_end),
+        ] {
+            ui.label(label);
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(time)
+                        .speed(0.01)
+                        .clamp_range(0.0..=total_time)
+                        .suffix(" s"),
+                );
+                if ui.button("Set to current").clicked() {
+                    *time = current_time;
+                }
+            });
+            ui.end_row();
+        }
+
+        let plane = self.ui_info_state.plane;
+        let region = &mut self.ui_state.clip_region;
+        ui.label("Clip region:");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut region.x)
+                    .prefix("x: ")
+                    .clamp_range(0..=plane.w().saturating_sub(1)),
+            );
+            ui.add(
+                egui::DragValue::new(&mut region.y)
+                    .prefix("y: ")
+                    .clamp_range(0..=plane.h().saturating_sub(1)),
+            );
+            ui.add(
+                egui::DragValue::new(&mut region.width)
+                    .prefix("w: ")
+                    .clamp_range(1..=plane.w().saturating_sub(region.x).max(1)),
+            );
+            ui.add(
+                egui::DragValue::new(&mut region.height)
+                    .prefix("h: ")
+                    .clamp_range(1..=plane.h().saturating_sub(region.y).max(1)),
+            );
+            if ui.button("Full frame").clicked() {
+                *region = ClipRegion::full(plane);
+            }
+        });
+        ui.end_row();
+
+        add_radio_row(
+            true,
+            "Clip format:",
+            vec![
+                ("ADΔER", ClipFormat::Adder),
+                ("Y4M video", ClipFormat::Y4m),
+                ("PNG sequence", ClipFormat::PngSequence),
+            ],
+            ui,
+            &mut self.ui_state.clip_format,
+        );
+        add_radio_row(
+            self.ui_state.clip_format == ClipFormat::Adder,
+            "Clip representation:",
+            vec![
+                ("Raw", EncoderType::Raw),
+                ("Compressed", EncoderType::Compressed),
+            ],
+            ui,
+            &mut self.ui_state.clip_encoder_type,
+        );
+
+        ui.label("");
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.player_path_buf.is_some() && self.export_rx.is_none(),
+                    egui::Button::new("Export clip"),
+                )
+                .clicked()
+            {
+                self.export_clip();
+            }
+            ui.label(self.ui_info_state.export_status.clone());
+        });
+        ui.end_row();
+    }
+
+    /// Ask for an output path, then export the selected clip of the current stream in the
+    /// background
+    fn export_clip(&mut self) {
+        let input_path = match &self.player_path_buf {
+            None => return,
+            Some(path) => path.clone(),
+        };
+        let output_path = match self.ui_state.clip_format {
+            ClipFormat::Adder => rfd::FileDialog::new()
+                .add_filter("adder video", &["adder"])
+                .save_file()
+                .map(|path| path.with_extension("adder")),
+            ClipFormat::Y4m => rfd::FileDialog::new()
+                .add_filter("Y4M video", &["y4m"])
+                .save_file()
+                .map(|path| path.with_extension("y4m")),
+            ClipFormat::PngSequence => rfd::FileDialog::new().pick_folder(),
+        };
+        let output_path = match output_path {
+            None => return,
+            Some(path) => path,
+        };
+
+        let settings = ClipSettings {
+            start: self.ui_state.clip_start,
+            end: self.ui_state.clip_end,
+            region: self.ui_state.clip_region,
+            format: self.ui_state.clip_format,
+            encoder_type: self.ui_state.clip_encoder_type,
+            view_mode: self.ui_state.view_mode,
+        };
+        let (export_tx, export_rx) = bounded(1);
+        rayon::spawn(move || {
+            let result = export_clip(&input_path, &output_path, &settings);
+            export_tx.send(result.map_err(|e| e.to_string())).ok();
+        });
+        self.export_rx = Some(export_rx);
+        self.ui_info_state.export_status = RichText::new("Exporting...");
+    }
+
     pub fn central_panel_ui(&mut self, ui: &mut Ui, time: Res<Time>) {
         ui.horizontal(|ui| {
             if ui.button("Open file").clicked() {
@@ -358,8 +750,25 @@ impl PlayerState {
 
             ui.label("OR drag and drop your ADΔER file here (.adder)");
         });
+        ui.horizontal(|ui| {
+            ui.label("Live stream:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.ui_state.live_address_input)
+                    .hint_text("host:port or unix:/path/to/socket"),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Connect").clicked() || submitted)
+                && !self.ui_state.live_address_input.trim().is_empty()
+            {
+                let address = self.ui_state.live_address_input.trim().to_string();
+                self.connect_live(&address);
+            }
+        });
 
         ui.label(self.ui_info_state.source_name.clone());
+        if let Some(latency) = self.ui_info_state.live_latency {
+            ui.label(format!("Live latency: {:.0} ms", latency * 1000.0));
+        }
 
         let duration_secs = self.ui_info_state.stream_state.current_t_ticks as f64
             / self.ui_info_state.stream_state.tps as f64;
@@ -391,6 +800,47 @@ impl PlayerState {
             self.ui_info_state.events_total,
             self.ui_info_state.events_ppc_total
         ));
+
+        if self.ui_state.comparison_path.is_some() {
+            Plot::new("comparison_plot")
+                .height(100.0)
+                .allow_drag(true)
+                .auto_bounds_y()
+                .legend(Legend::default().position(LeftTop))
+                .show(ui, |plot_ui| {
+                    let metrics = vec![
+                        (&self.ui_info_state.plot_points_psnr_y, "PSNR dB"),
+                        (&self.ui_info_state.plot_points_ssim_y, "SSIM"),
+                    ];
+
+                    for (line, label) in metrics {
+                        if line.points.iter().last().unwrap().is_some() {
+                            plot_ui.line(line.get_plotline(label, false));
+                        }
+                    }
+                });
+        }
+
+        if self.ui_info_state.analytics.ui(ui) {
+            self.reset_update_adder_params(true);
+        }
+    }
+
+    /// Get the title and subtitle of the image shown next to the main stream
+    pub fn comparison_label(&self) -> (String, String) {
+        let title = match self.ui_state.comparison_view {
+            ComparisonView::Reference => "Reference",
+            ComparisonView::Difference => "Difference",
+        };
+        let last = |plot: &PlotY| plot.points.iter().last().copied().flatten();
+        let mut subtitle = match last(&self.ui_info_state.plot_points_psnr_y) {
+            Some(psnr) => format!("PSNR {psnr:.2} dB"),
+            None => "No metrics (frames differ in shape)".to_string(),
+        };
+        if let Some(ssim) = last(&self.ui_info_state.plot_points_ssim_y) {
+            subtitle += &format!(" | SSIM {ssim:.2}");
+        }
+        (title.to_string(), subtitle)
     }
 
     fn reset_update_adder_params(&mut self, replace_player: bool) {
@@ -400,7 +850,18 @@ impl PlayerState {
         };
         self.ui_state.total_frames = 0;
         self.ui_state.current_time = 0.0;
-        self.ui_state.total_time = 0.0;
+
+        if self.live_address.is_some() {
+            if replace_player {
+                self.update_live_player();
+            } else {
+                self.live_address = None;
+                self.connect_rx = None;
+                self.live_settings_tx = None;
+                self.player_rx = None;
+            }
+            return;
+        }
 
         let path_buf = match &self.player_path_buf {
             None => {
@@ -418,38 +879,160 @@ impl PlayerState {
     }
 
     pub fn replace_player(&mut self, path_buf: &std::path::Path) {
+        if self.player_path_buf.as_deref() != Some(path_buf) || self.ui_state.total_time == 0.0 {
+            // Find the duration of the stream for the timeline, without blocking the UI
+            self.ui_state.total_time = 0.0;
+            let (duration_tx, duration_rx) = bounded(1);
+            let duration_path = PathBuf::from(path_buf);
+            rayon::spawn(move || {
+                if let Ok(duration) = stream_duration(&duration_path) {
+                    duration_tx.send(duration).ok();
+                }
+            });
+            self.duration_rx = Some(duration_rx);
+        }
         self.player_path_buf = Some(PathBuf::from(path_buf));
-        self.ui_info_state.events_total = 0;
-        self.ui_info_state.events_ppc_total = 0.0;
-        let mut player = match AdderPlayer::new(
+        self.live_address = None;
+        self.connect_rx = None;
+        self.live_settings_tx = None;
+        self.ui_info_state.live_start = None;
+        self.ui_info_state.live_latency = None;
+        let player = AdderPlayer::new(
             path_buf,
             self.ui_state.ui_sliders.playback_speed,
             self.ui_state.view_mode,
             self.ui_state.detect_features,
             self.ui_state.buffer_limit,
-        ) {
+        );
+        let source_name = match path_buf.to_str() {
+            None => "Error: couldn't get path string".to_string(),
+            Some(path) => path.to_string(),
+        };
+        self.start_player(player, source_name);
+    }
+
+    /// Play a live stream from a TCP or Unix socket address. Live streams can't be seeked,
+    /// looped, or exported, and are always played in real time. Connecting and reading the
+    /// stream's header can block, so they happen on the player's thread, and the player is
+    /// started once [`consume_source`](Self::consume_source) receives it.
+    pub fn connect_live(&mut self, address: &str) {
+        self.player_path_buf = None;
+        self.live_address = Some(address.to_string());
+        self.duration_rx = None;
+        self.player_rx = None;
+        self.live_settings_tx = None;
+        self.ui_state.total_time = 0.0;
+        self.ui_state.seek_time = None;
+        self.ui_info_state.live_start = None;
+        self.ui_info_state.live_latency = None;
+        self.ui_info_state.source_name = RichText::new(format!("Connecting to {address}..."));
+
+        let (connect_tx, connect_rx) = bounded(1);
+        let address = address.to_string();
+        let view_mode = self.ui_state.view_mode;
+        let detect_features = self.ui_state.detect_features;
+        let buffer_limit = self.ui_state.buffer_limit;
+        rayon::spawn(move || {
+            let player = AdderPlayer::new_live(&address, view_mode, detect_features, buffer_limit)
+                .map_err(|e| e.to_string());
+            connect_tx.send(player).ok();
+        });
+        self.connect_rx = Some(connect_rx);
+    }
+
+    /// Apply the current settings to a live stream's player, which keeps its connection. If the
+    /// stream is still connecting, its player is given the settings when it starts.
+    fn update_live_player(&mut self) {
+        if self.live_settings_tx.is_none() {
+            return;
+        }
+        let comparison = self.new_comparison(self.ui_info_state.plane);
+        let settings = self.live_settings(comparison);
+        if let Some(settings_tx) = &self.live_settings_tx {
+            settings_tx.send(settings).ok();
+        }
+    }
+
+    fn live_settings(&self, comparison: Option<Comparison>) -> LiveSettings {
+        LiveSettings {
+            view_mode: self.ui_state.view_mode,
+            detect_features: self.ui_state.detect_features,
+            buffer_limit: self.ui_state.buffer_limit,
+            reconstruction_method: self.ui_state.reconstruction_method.clone(),
+            optical_flow: self.ui_state.show_flow,
+            analytics: self.ui_info_state.analytics.enabled,
+            comparison,
+        }
+    }
+
+    /// Open the selected reference stream for comparison, reporting whether it could be opened
+    fn new_comparison(&mut self, plane: PlaneSize) -> Option<Comparison> {
+        let Some(comparison_path) = &self.ui_state.comparison_path else {
+            self.ui_info_state.comparison_name = RichText::new("No comparison selected");
+            return None;
+        };
+        let settings = PlaybackSettings {
+            playback_speed: self.ui_state.ui_sliders.playback_speed,
+            view_mode: self.ui_state.view_mode,
+            buffer_limit: self.ui_state.buffer_limit,
+            reconstruction_method: self.ui_state.reconstruction_method.clone(),
+        };
+        match Comparison::new(comparison_path, plane, settings, self.ui_state.metric_ssim) {
+            Ok(comparison) => {
+                self.ui_info_state.comparison_name =
+                    RichText::new(comparison_path.to_string_lossy()).color(Color32::DARK_GREEN);
+                Some(comparison)
+            }
+            Err(e) => {
+                self.ui_info_state.comparison_name =
+                    RichText::new(e.to_string()).color(Color32::RED);
+                None
+            }
+        }
+    }
+
+    fn start_player(&mut self, player: Result<AdderPlayer, Box<dyn Error>>, source_name: String) {
+        self.ui_info_state.events_total = 0;
+        self.ui_info_state.events_ppc_total = 0.0;
+        let mut player = match player {
             Ok(player) => {
-                self.ui_info_state.source_name = RichText::from(match path_buf.to_str() {
-                    None => "Error: couldn't get path string".to_string(),
-                    Some(path) => path.to_string(),
-                })
-                .color(Color32::DARK_GREEN);
+                self.ui_info_state.source_name =
+                    RichText::from(source_name).color(Color32::DARK_GREEN);
                 player
             }
             Err(e) => {
                 self.ui_info_state.source_name = RichText::new(e.to_string()).color(Color32::RED);
+                self.player_rx = None;
                 return;
             }
         };
 
         player = player.reconstruction_method(self.ui_state.reconstruction_method.clone());
+        player = player.optical_flow(self.ui_state.show_flow);
+        player = player.analytics(self.ui_info_state.analytics.enabled);
+        let plane = player.input_stream.as_ref().unwrap().decoder.meta().plane;
+        player = player.comparison(self.new_comparison(plane));
         // player = player.stream_pos(self.ui_info_state.stream_state.file_pos);
         // TODO: Restore
         player = player.stream_pos(0);
+        if let Some(seek_time) = self.ui_state.seek_time.take() {
+            player = player.seek_time(seek_time as f64);
+            self.ui_state.current_time = seek_time;
+            self.ui_state.timeline_time = seek_time;
+        }
+        self.ui_info_state.frame_length = player.frame_length();
 
         let plane = player.input_stream.as_ref().unwrap().decoder.meta().plane;
         self.ui_info_state.event_size = if plane.c() == 1 { 9 } else { 11 };
         self.ui_info_state.plane = plane;
+        let region = self.ui_state.clip_region;
+        if region.width == 0
+            || region.height == 0
+            || u32::from(region.x) + u32::from(region.width) > u32::from(plane.w())
+            || u32::from(region.y) + u32::from(region.height) > u32::from(plane.h())
+        {
+            self.ui_state.clip_region = ClipRegion::full(plane);
+        }
 
         self.ui_state.current_frame = 1;
 
@@ -457,9 +1040,15 @@ impl PlayerState {
             drop(current_rx);
         }
         let (player_tx, player_rx) = bounded(60);
-        let detect_features = self.ui_state.detect_features;
+        let mut detect_features = self.ui_state.detect_features;
+        let (settings_tx, settings_rx) = unbounded::<LiveSettings>();
+        self.live_settings_tx = player.is_live().then_some(settings_tx);
 
         rayon::spawn(move || loop {
+            while let Ok(settings) = settings_rx.try_recv() {
+                detect_features = settings.detect_features;
+                player.reconfigure(settings);
+            }
             let res = player.consume_source(detect_features);
             match player_tx.send(res) {
                 Ok(_) => {}
@@ -467,6 +1056,9 @@ impl PlayerState {
                     break;
                 }
             };
+            if player.is_finished() {
+                break;
+            }
         });
 
         self.player_rx = Some(player_rx);
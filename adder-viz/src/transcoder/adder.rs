@@ -240,8 +240,8 @@ impl AdderTranscoder {
                         })
                     }
 
-                    // Prophesee .dat files
-                    Some(ext) if ext == "dat" => {
+                    // Prophesee .dat files, and .raw files in the EVT 2.0 or EVT 3.0 encoding
+                    Some(ext) if ext == "dat" || ext == "raw" => {
                         let output_string = output_path_opt
                             .map(|output_path| output_path.to_str().expect("Bad path").to_string());
 
@@ -1,2 +1,3 @@
 pub mod adder;
+pub mod profile;
 pub mod ui;
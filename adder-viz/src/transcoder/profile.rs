@@ -0,0 +1,200 @@
+use crate::transcoder::adder::AdderTranscoder;
+use crate::transcoder::ui::ParamsUiState;
+use adder_codec_rs::adder_codec_core::codec::{EncoderType, EventOrder};
+use adder_codec_rs::adder_codec_core::{PixelMultiMode, TimeMode};
+#[cfg(feature = "open-cv")]
+use adder_codec_rs::transcoder::source::davis::TranscoderMode;
+use adder_codec_rs::transcoder::source::video::{Source, SourceError};
+use adder_codec_rs::utils::viz::ShowFeatureMode;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The transcoder parameters which can be tuned in the GUI, saved as a TOML "transcode profile".
+/// Fields missing from a profile file take their default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscodeProfile {
+    pub delta_t_ref: f32,
+    pub delta_t_max_mult: u32,
+    pub scale: f64,
+    pub thread_count: usize,
+    pub color: bool,
+    pub auto_quality: bool,
+    pub crf: u8,
+    pub c_thresh_baseline: u8,
+    pub c_thresh_max: u8,
+    pub c_increase_velocity: u8,
+    pub feature_c_radius: u16,
+    pub limit_bandwidth: bool,
+    pub bandwidth_target_event_rate: f64,
+    pub bandwidth_alpha: f64,
+    pub time_mode: TimeMode,
+    pub integration_mode: PixelMultiMode,
+    pub encoder_type: EncoderType,
+    pub event_order: EventOrder,
+    pub detect_features: bool,
+    pub show_features: ShowFeatureMode,
+    #[cfg(feature = "open-cv")]
+    pub davis_mode: TranscoderMode,
+    pub davis_output_fps: f64,
+    pub optimize_c: bool,
+    pub optimize_c_frequency: u32,
+}
+
+impl Default for TranscodeProfile {
+    fn default() -> Self {
+        ParamsUiState::default().to_profile()
+    }
+}
+
+impl TranscodeProfile {
+    /// Read a profile from a TOML file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Write the profile to a TOML file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Arguments for transcoding a file without the GUI
+#[derive(Parser, Debug)]
+pub struct HeadlessArgs {
+    /// Path to the transcode profile (.toml), as saved from the GUI
+    #[clap(short, long)]
+    pub profile: PathBuf,
+
+    /// Path to the input file (.mp4, .aedat4, .dat), or to the DVS socket for a live DAVIS source
+    #[clap(short, long)]
+    pub input: PathBuf,
+
+    /// Path to the APS socket, for a live DAVIS source
+    #[clap(long)]
+    pub input_aps: Option<PathBuf>,
+
+    /// Path to the output ADΔER file
+    #[clap(short, long)]
+    pub output: PathBuf,
+
+    /// Index of the first input frame to transcode, for framed sources
+    #[clap(long, default_value_t = 0)]
+    pub frame_idx_start: u32,
+
+    /// Max number of input intervals to transcode (0 = no limit)
+    #[clap(short, long, default_value_t = 0)]
+    pub frame_count_max: u32,
+}
+
+/// Transcode a file with the parameters of a profile, setting up the source exactly as the GUI
+/// does
+pub fn run_headless(args: &HeadlessArgs) -> Result<(), Box<dyn Error>> {
+    let profile = TranscodeProfile::load(&args.profile)?;
+    let mut ui_state = ParamsUiState::default();
+    ui_state.apply_profile(&profile);
+
+    let mut transcoder = AdderTranscoder::new(
+        &args.input,
+        &args.input_aps,
+        Some(args.output.clone()),
+        &mut ui_state,
+        args.frame_idx_start,
+    )?;
+    let source: &mut dyn Source<BufWriter<File>> = match (
+        &mut transcoder.framed_source,
+        &mut transcoder.prophesee_source,
+    ) {
+        (Some(source), _) => source,
+        (None, Some(source)) => source,
+        #[cfg(feature = "open-cv")]
+        (None, None) => match &mut transcoder.davis_source {
+            Some(source) => {
+                source
+                    .get_reconstructor_mut()
+                    .as_mut()
+                    .ok_or("DAVIS source has no reconstructor")?
+                    .set_optimize_c(ui_state.optimize_c, ui_state.optimize_c_frequency);
+                source
+            }
+            None => return Err("No source was created for the input".into()),
+        },
+        #[cfg(not(feature = "open-cv"))]
+        (None, None) => return Err("No source was created for the input".into()),
+    };
+
+    // Feature detection still adjusts the quality, but there's no display to show features on
+    ui_state.show_features = ShowFeatureMode::Off;
+    ui_state.apply_to_source(source);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(ui_state.thread_count.max(1))
+        .build()?;
+    let mut interval_count = 0;
+    while args.frame_count_max == 0 || interval_count < args.frame_count_max {
+        match source.consume(1, &pool) {
+            Ok(_) => {
+                interval_count += 1;
+                eprint!("\rTranscoded interval {interval_count}");
+            }
+            Err(SourceError::Open) => {}
+            Err(e) if e.is_end_of_input() => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    eprintln!();
+
+    if let Some(mut writer) = source.get_video_mut().end_write_stream()? {
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_round_trip() {
+        let profile = TranscodeProfile {
+            delta_t_ref: 100.0,
+            delta_t_max_mult: 60,
+            scale: 0.25,
+            thread_count: 8,
+            color: true,
+            auto_quality: false,
+            crf: 7,
+            c_thresh_baseline: 3,
+            c_thresh_max: 40,
+            c_increase_velocity: 2,
+            feature_c_radius: 9,
+            limit_bandwidth: true,
+            bandwidth_target_event_rate: 500_000.0,
+            bandwidth_alpha: 0.5,
+            time_mode: TimeMode::AbsoluteT,
+            integration_mode: PixelMultiMode::Normal,
+            encoder_type: EncoderType::Compressed,
+            event_order: EventOrder::Interleaved,
+            detect_features: true,
+            show_features: ShowFeatureMode::Hold,
+            davis_output_fps: 250.0,
+            optimize_c: false,
+            optimize_c_frequency: 10,
+            ..TranscodeProfile::default()
+        };
+
+        let content = toml::to_string_pretty(&profile).unwrap();
+        let parsed: TranscodeProfile = toml::from_str(&content).unwrap();
+        assert_eq!(parsed, profile);
+
+        let mut ui_state = ParamsUiState::default();
+        ui_state.apply_profile(&profile);
+        assert_eq!(ui_state.to_profile(), profile);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::transcoder::adder::{replace_adder_transcoder, AdderTranscoder};
+use crate::transcoder::profile::TranscodeProfile;
 use crate::utils::prep_bevy_image;
 use crate::{slider_pm, Images};
 #[cfg(feature = "open-cv")]
@@ -12,6 +13,7 @@ use rayon::current_num_threads;
 use std::collections::VecDeque;
 use std::error::Error;
 
+use crate::utils::analytics::{AnalyticsUiState, EventAnalyzer};
 use crate::utils::PlotY;
 use adder_codec_rs::adder_codec_core::codec::rate_controller::{Crf, CRF, DEFAULT_CRF_QUALITY};
 use adder_codec_rs::adder_codec_core::codec::{EncoderOptions, EncoderType, EventDrop, EventOrder};
@@ -119,6 +121,173 @@ impl Default for ParamsUiState {
     }
 }
 
+impl ParamsUiState {
+    /// Get the tunable parameters as a transcode profile
+    pub(crate) fn to_profile(&self) -> TranscodeProfile {
+        let parameters = self.encoder_options.crf.get_parameters();
+        TranscodeProfile {
+            delta_t_ref: self.delta_t_ref,
+            delta_t_max_mult: self.delta_t_max_mult,
+            scale: self.scale,
+            thread_count: self.thread_count,
+            color: self.color,
+            auto_quality: self.auto_quality,
+            crf: self
+                .encoder_options
+                .crf
+                .get_quality()
+                .unwrap_or(DEFAULT_CRF_QUALITY),
+            c_thresh_baseline: parameters.c_thresh_baseline,
+            c_thresh_max: parameters.c_thresh_max,
+            c_increase_velocity: parameters.c_increase_velocity,
+            feature_c_radius: parameters.feature_c_radius,
+            limit_bandwidth: self.limit_bandwidth,
+            bandwidth_target_event_rate: self.bandwidth_target_event_rate,
+            bandwidth_alpha: self.bandwidth_alpha,
+            time_mode: self.time_mode,
+            integration_mode: self.integration_mode_radio_state,
+            encoder_type: self.encoder_type,
+            event_order: self.encoder_options.event_order,
+            detect_features: self.detect_features,
+            show_features: self.show_features,
+            #[cfg(feature = "open-cv")]
+            davis_mode: self.davis_mode_radio_state,
+            davis_output_fps: self.davis_output_fps,
+            optimize_c: self.optimize_c,
+            optimize_c_frequency: self.optimize_c_frequency,
+        }
+    }
+
+    /// Set the tunable parameters, and their sliders, from a transcode profile
+    pub(crate) fn apply_profile(&mut self, profile: &TranscodeProfile) {
+        self.delta_t_ref = profile.delta_t_ref;
+        self.delta_t_ref_slider = profile.delta_t_ref;
+        self.delta_t_max_mult = profile.delta_t_max_mult;
+        self.delta_t_max_mult_slider = profile.delta_t_max_mult;
+        self.scale = profile.scale;
+        self.scale_slider = profile.scale;
+        self.thread_count = profile.thread_count;
+        self.thread_count_slider = profile.thread_count;
+        self.color = profile.color;
+        self.auto_quality = profile.auto_quality;
+        self.auto_quality_mirror = false;
+
+        let mut crf = Crf::new(Some(profile.crf), PlaneSize::default());
+        let parameters = crf.get_parameters_mut();
+        parameters.c_thresh_baseline = profile.c_thresh_baseline;
+        parameters.c_thresh_max = profile.c_thresh_max;
+        parameters.c_increase_velocity = profile.c_increase_velocity;
+        parameters.feature_c_radius = profile.feature_c_radius;
+        self.encoder_options.crf = crf;
+        self.crf_slider = profile.crf;
+        self.adder_tresh_baseline_slider = profile.c_thresh_baseline;
+        self.adder_tresh_max_slider = profile.c_thresh_max;
+        self.adder_tresh_velocity_slider = profile.c_increase_velocity;
+        self.feature_radius_slider = profile.feature_c_radius;
+
+        self.limit_bandwidth = profile.limit_bandwidth;
+        self.bandwidth_target_event_rate = profile.bandwidth_target_event_rate;
+        self.bandwidth_target_event_rate_slider = profile.bandwidth_target_event_rate;
+        self.bandwidth_alpha = profile.bandwidth_alpha;
+        self.alpha_slider = profile.bandwidth_alpha;
+        self.encoder_options.event_drop = if profile.limit_bandwidth {
+            EventDrop::Manual {
+                target_event_rate: profile.bandwidth_target_event_rate,
+                alpha: profile.bandwidth_alpha,
+            }
+        } else {
+            EventDrop::None
+        };
+        self.encoder_options.event_order = profile.event_order;
+
+        self.time_mode = profile.time_mode;
+        self.integration_mode_radio_state = profile.integration_mode;
+        self.encoder_type = profile.encoder_type;
+        self.detect_features = profile.detect_features;
+        self.show_features = profile.show_features;
+        #[cfg(feature = "open-cv")]
+        {
+            self.davis_mode_radio_state = profile.davis_mode;
+        }
+        self.davis_output_fps = profile.davis_output_fps;
+        self.davis_output_fps_slider = profile.davis_output_fps;
+        self.optimize_c = profile.optimize_c;
+        self.optimize_c_frequency = profile.optimize_c_frequency;
+        self.optimize_c_frequency_slider = profile.optimize_c_frequency;
+    }
+
+    /// Apply the quality, integration and feature detection parameters to a transcoder's source.
+    /// The GUI calls this whenever its parameters may have changed, and a headless transcode
+    /// calls it once after constructing the source.
+    pub(crate) fn apply_to_source(&mut self, source: &mut dyn Source<BufWriter<File>>) {
+        // TODO: Refactor all this garbage code
+        if self.auto_quality
+            && (!self.auto_quality_mirror
+                || self.encoder_options.crf.get_quality()
+                    != source
+                        .get_video_ref()
+                        .get_encoder_options()
+                        .crf
+                        .get_quality())
+        {
+            self.auto_quality_mirror = true;
+            source.crf(
+                self.encoder_options
+                    .crf
+                    .get_quality()
+                    .unwrap_or(DEFAULT_CRF_QUALITY),
+            );
+
+            let video = source.get_video_ref();
+
+            let binding = video.get_encoder_options();
+            let parameters = binding.crf.get_parameters();
+
+            self.encoder_options = binding;
+            // Update ui state to match
+            self.crf_slider = binding.crf.get_quality().unwrap_or(DEFAULT_CRF_QUALITY);
+            self.adder_tresh_baseline_slider = parameters.c_thresh_baseline;
+            self.adder_tresh_max_slider = parameters.c_thresh_max;
+            self.delta_t_max_mult = video.state.params.delta_t_max / video.state.params.ref_time;
+            self.delta_t_max_mult_slider = self.delta_t_max_mult;
+            self.adder_tresh_velocity_slider = parameters.c_increase_velocity;
+            self.feature_radius_slider = parameters.feature_c_radius;
+        } else if !self.auto_quality
+            && (self.delta_t_max_mult
+                != source.get_video_ref().state.params.delta_t_max
+                    / source.get_video_ref().state.params.ref_time
+                || self.encoder_options.crf.get_parameters()
+                    != source
+                        .get_video_ref()
+                        .get_encoder_options()
+                        .crf
+                        .get_parameters())
+        {
+            let video = source.get_video_mut();
+            let parameters = self.encoder_options.crf.get_parameters();
+            video.update_quality_manual(
+                parameters.c_thresh_baseline,
+                parameters.c_thresh_max,
+                self.delta_t_max_mult,
+                parameters.c_increase_velocity,
+                parameters.feature_c_radius as f32,
+            )
+        }
+
+        if !self.auto_quality {
+            self.auto_quality_mirror = false;
+        }
+        let video = source.get_video_mut();
+
+        if video.state.params.pixel_multi_mode != self.integration_mode_radio_state {
+            video.state.params.pixel_multi_mode = self.integration_mode_radio_state;
+        }
+
+        video.instantaneous_view_mode = self.view_mode_radio_state;
+        video.update_detect_features(self.detect_features, self.show_features);
+    }
+}
+
 pub struct InfoUiState {
     pub events_per_sec: f64,
     pub events_ppc_per_sec: f64,
@@ -140,6 +309,8 @@ pub struct InfoUiState {
     pub(crate) plot_points_mse_y: PlotY,
     pub(crate) plot_points_ssim_y: PlotY,
     plot_points_latency_y: PlotY,
+    pub(crate) analytics: AnalyticsUiState,
+    analyzer: Option<EventAnalyzer>,
     pub view_mode_radio_state: FramedViewMode, // TODO: Move to different struct
 }
 
@@ -194,6 +365,8 @@ impl Default for InfoUiState {
             plot_points_latency_y: PlotY {
                 points: plot_points,
             },
+            analytics: Default::default(),
+            analyzer: None,
             view_mode_radio_state: FramedViewMode::Intensity,
         }
     }
@@ -220,6 +393,31 @@ impl TranscoderState {
             if ui.add(egui::Button::new("Reset params")).clicked() {
                 self.ui_state = Default::default();
             }
+            if ui.add(egui::Button::new("Load profile")).clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("transcode profile", &["toml"])
+                    .pick_file()
+                {
+                    match TranscodeProfile::load(&path) {
+                        Ok(profile) => self.ui_state.apply_profile(&profile),
+                        Err(e) => eprintln!("Error loading profile: {}", e),
+                    }
+                }
+            }
+            if ui.add(egui::Button::new("Save profile")).clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("transcode profile", &["toml"])
+                    .save_file()
+                {
+                    if let Err(e) = self
+                        .ui_state
+                        .to_profile()
+                        .save(&path.with_extension("toml"))
+                    {
+                        eprintln!("Error saving profile: {}", e);
+                    }
+                }
+            }
             if ui.add(egui::Button::new("Reset video")).clicked() {
                 if let Some(framed_source) = &mut self.transcoder.framed_source {
                     match framed_source.get_video_mut().end_write_stream() {
@@ -256,7 +454,7 @@ impl TranscoderState {
                 if let Some(path) = rfd::FileDialog::new()
                     .add_filter("framed video", &["mp4"])
                     .add_filter("DVS/DAVIS video", &["aedat4"])
-                    .add_filter("Prophesee video", &["dat"])
+                    .add_filter("Prophesee video", &["dat", "raw"])
                     .pick_file()
                 {
                     self.ui_info_state.input_path_0 = Some(path.clone());
@@ -426,6 +624,10 @@ impl TranscoderState {
                     }
                 }
             });
+
+        if self.ui_info_state.analytics.ui(ui) && !self.ui_info_state.analytics.enabled {
+            self.ui_info_state.analyzer = None;
+        }
     }
 
     pub fn update_adder_params(&mut self, _: Res<Images>, mut images: ResMut<Assets<Image>>) {
@@ -560,79 +762,11 @@ impl TranscoderState {
             }
         };
 
-        let binding = source.get_video_ref().get_encoder_options();
-        let _parameters = binding.crf.get_parameters();
-
-        // TODO: Refactor all this garbage code
-        if self.ui_state.auto_quality
-            && (!self.ui_state.auto_quality_mirror
-                || self.ui_state.encoder_options.crf.get_quality()
-                    != source
-                        .get_video_ref()
-                        .get_encoder_options()
-                        .crf
-                        .get_quality())
-        {
-            self.ui_state.auto_quality_mirror = true;
-            source.crf(
-                self.ui_state
-                    .encoder_options
-                    .crf
-                    .get_quality()
-                    .unwrap_or(DEFAULT_CRF_QUALITY),
-            );
-
-            let video = source.get_video_ref();
-
-            let binding = video.get_encoder_options();
-            let parameters = binding.crf.get_parameters();
-
-            self.ui_state.encoder_options = binding;
-            // Update ui state to match
-            self.ui_state.crf_slider = binding.crf.get_quality().unwrap_or(DEFAULT_CRF_QUALITY);
-            self.ui_state.adder_tresh_baseline_slider = parameters.c_thresh_baseline;
-            self.ui_state.adder_tresh_max_slider = parameters.c_thresh_max;
-            self.ui_state.delta_t_max_mult =
-                video.state.params.delta_t_max / video.state.params.ref_time;
-            self.ui_state.delta_t_max_mult_slider = self.ui_state.delta_t_max_mult;
-            self.ui_state.adder_tresh_velocity_slider = parameters.c_increase_velocity;
-            self.ui_state.feature_radius_slider = parameters.feature_c_radius;
-        } else if !self.ui_state.auto_quality
-            && (self.ui_state.delta_t_max_mult
-                != source.get_video_ref().state.params.delta_t_max
-                    / source.get_video_ref().state.params.ref_time
-                || self.ui_state.encoder_options.crf.get_parameters()
-                    != source
-                        .get_video_ref()
-                        .get_encoder_options()
-                        .crf
-                        .get_parameters())
-        {
-            let video = source.get_video_mut();
-            let parameters = self.ui_state.encoder_options.crf.get_parameters();
-            video.update_quality_manual(
-                parameters.c_thresh_baseline,
-                parameters.c_thresh_max,
-                self.ui_state.delta_t_max_mult,
-                parameters.c_increase_velocity,
-                parameters.feature_c_radius as f32,
-            )
-        }
-
-        if !self.ui_state.auto_quality {
-            self.ui_state.auto_quality_mirror = false;
-        }
-        let video = source.get_video_mut();
-
-        if video.state.params.pixel_multi_mode != self.ui_state.integration_mode_radio_state {
-            video.state.params.pixel_multi_mode = self.ui_state.integration_mode_radio_state;
-        }
+        self.ui_state.apply_to_source(source);
 
+        let video = source.get_video_ref();
         self.ui_info_state.event_size = video.get_event_size();
         self.ui_info_state.plane = video.state.plane;
-
-        video.instantaneous_view_mode = self.ui_state.view_mode_radio_state;
-        video.update_detect_features(self.ui_state.detect_features, self.ui_state.show_features);
     }
 
     pub fn consume_source(
@@ -673,7 +807,7 @@ impl TranscoderState {
 
         match source.consume(1, &pool) {
             Ok(events_vec_vec) => {
-                for events_vec in events_vec_vec {
+                for events_vec in &events_vec_vec {
                     ui_info_state.events_total += events_vec.len() as u64;
                     ui_info_state.events_per_sec += events_vec.len() as f64;
                 }
@@ -684,6 +818,27 @@ impl TranscoderState {
                 ui_info_state.events_per_sec *= source_fps;
                 ui_info_state.events_ppc_per_sec = ui_info_state.events_per_sec
                     / (source.get_video_ref().state.plane.volume() as f64);
+
+                if ui_info_state.analytics.enabled {
+                    let video = source.get_video_ref();
+                    let plane = video.state.plane;
+                    // Start over if the source changed
+                    if ui_info_state
+                        .analyzer
+                        .as_ref()
+                        .map_or(true, |analyzer| analyzer.plane() != plane)
+                    {
+                        ui_info_state.analyzer =
+                            Some(EventAnalyzer::new(plane, video.get_time_mode()));
+                    }
+                    if let Some(analyzer) = &mut ui_info_state.analyzer {
+                        for event in events_vec_vec.iter().flatten() {
+                            analyzer.ingest(event);
+                        }
+                        ui_info_state.analytics.add_tally(analyzer.take_tally());
+                    }
+                    ui_info_state.analytics.end_frame(1.0 / source_fps);
+                }
             }
             Err(SourceError::Open) => {}
             Err(e) => {
@@ -716,6 +871,7 @@ impl TranscoderState {
                     mse: if self.ui_state.metric_mse {Some(0.0)} else {None},
                     psnr: if self.ui_state.metric_psnr {Some(0.0)} else {None},
                     ssim: if self.ui_state.metric_ssim {Some(0.0)} else {None},
+                    ms_ssim: None,
                 },
             );
             let metrics = metrics?;
@@ -743,6 +899,9 @@ impl TranscoderState {
             let handle = images.add(self.transcoder.live_image.clone());
             handles.image_view = handle;
         }
+        if let Some(image) = images.get_mut(&handles.image_view) {
+            self.ui_info_state.analytics.draw_density_overlay(image);
+        }
 
         // Repeat for the input view
         if self.ui_state.show_original && source.get_input().is_some() {
@@ -0,0 +1,341 @@
+use crate::utils::PlotY;
+use adder_codec_rs::adder_codec_core::{
+    DeltaT, Event, PlaneSize, TimeMode, D_EMPTY, D_MAX, D_ZERO_INTEGRATION,
+};
+use bevy::prelude::Image;
+use bevy_egui::egui;
+use bevy_egui::egui::plot::Corner::LeftTop;
+use bevy_egui::egui::plot::{Bar, BarChart, Legend, Plot};
+use bevy_egui::egui::Ui;
+use ndarray::{Array2, Array3};
+use std::collections::VecDeque;
+
+/// The side length of the square regions of the event density heat map, in pixels
+const DENSITY_REGION_SIZE: usize = 16;
+
+/// The number of power-of-two bins in the Δt histogram, enough for any [`DeltaT`]
+const DT_BINS: usize = DeltaT::BITS as usize + 1;
+
+/// How much of the histograms and heat map carries over from one frame to the next, so that they
+/// follow parameter changes within a second or two
+const DECAY: f64 = 0.9;
+
+/// The strongest tint of the density overlay, for the densest region
+const OVERLAY_ALPHA: f64 = 0.6;
+
+/// Statistics of a batch of events
+#[derive(Clone)]
+pub struct EventTally {
+    events: u64,
+    d_counts: [u64; D_MAX as usize + 1],
+    empty: u64,
+    zero_integration: u64,
+    dt_counts: [u64; DT_BINS],
+    region_counts: Array2<u64>,
+}
+
+impl EventTally {
+    fn new(plane: PlaneSize) -> Self {
+        Self {
+            events: 0,
+            d_counts: [0; D_MAX as usize + 1],
+            empty: 0,
+            zero_integration: 0,
+            dt_counts: [0; DT_BINS],
+            region_counts: Array2::zeros((
+                (plane.h_usize() + DENSITY_REGION_SIZE - 1) / DENSITY_REGION_SIZE,
+                (plane.w_usize() + DENSITY_REGION_SIZE - 1) / DENSITY_REGION_SIZE,
+            )),
+        }
+    }
+
+    fn merge(&mut self, other: &EventTally) {
+        self.events += other.events;
+        for (count, other) in self.d_counts.iter_mut().zip(other.d_counts.iter()) {
+            *count += other;
+        }
+        self.empty += other.empty;
+        self.zero_integration += other.zero_integration;
+        for (count, other) in self.dt_counts.iter_mut().zip(other.dt_counts.iter()) {
+            *count += other;
+        }
+        if self.region_counts.dim() == other.region_counts.dim() {
+            self.region_counts += &other.region_counts;
+        }
+    }
+}
+
+/// Gathers statistics from events as they are decoded or transcoded
+pub struct EventAnalyzer {
+    plane: PlaneSize,
+    time_mode: TimeMode,
+
+    /// The timestamp of the last event of each pixel, for finding Δt in absolute time mode
+    last_t: Array3<DeltaT>,
+    tally: EventTally,
+}
+
+impl EventAnalyzer {
+    pub fn new(plane: PlaneSize, time_mode: TimeMode) -> Self {
+        Self {
+            plane,
+            time_mode,
+            last_t: Array3::zeros((plane.h_usize(), plane.w_usize(), plane.c_usize())),
+            tally: EventTally::new(plane),
+        }
+    }
+
+    pub fn plane(&self) -> PlaneSize {
+        self.plane
+    }
+
+    pub fn ingest(&mut self, event: &Event) {
+        let y = event.coord.y as usize;
+        let x = event.coord.x as usize;
+        let c = event.coord.c.unwrap_or(0) as usize;
+
+        let tally = &mut self.tally;
+        tally.events += 1;
+        match event.d {
+            D_EMPTY => tally.empty += 1,
+            D_ZERO_INTEGRATION => tally.zero_integration += 1,
+            d if d <= D_MAX => tally.d_counts[d as usize] += 1,
+            _ => {}
+        }
+
+        let dt = match self.time_mode {
+            TimeMode::DeltaT => event.t,
+            TimeMode::AbsoluteT | TimeMode::Mixed => {
+                let last_t = &mut self.last_t[[y, x, c]];
+                let dt = event.t.saturating_sub(*last_t);
+                *last_t = event.t;
+                dt
+            }
+        };
+        tally.dt_counts[dt_bin(dt)] += 1;
+        tally.region_counts[[y / DENSITY_REGION_SIZE, x / DENSITY_REGION_SIZE]] += 1;
+    }
+
+    /// Get the statistics of the events ingested since the last call, and start a new tally
+    pub fn take_tally(&mut self) -> EventTally {
+        std::mem::replace(&mut self.tally, EventTally::new(self.plane))
+    }
+}
+
+/// The index of the power-of-two bin for a Δt: 0 holds Δt = 0, and bin `i` holds Δt in
+/// `[2^(i-1), 2^i)`
+fn dt_bin(dt: DeltaT) -> usize {
+    (DeltaT::BITS - dt.leading_zeros()) as usize
+}
+
+/// The live charts of event statistics, shared by the player and transcoder tabs
+pub struct AnalyticsUiState {
+    pub(crate) enabled: bool,
+    pub(crate) show_density: bool,
+    pending: Option<EventTally>,
+    d_histogram: Vec<f64>,
+    dt_histogram: Vec<f64>,
+    density: Array2<f64>,
+    plot_points_event_rate_y: PlotY,
+    plot_points_empty_y: PlotY,
+    plot_points_zero_integration_y: PlotY,
+}
+
+impl Default for AnalyticsUiState {
+    fn default() -> Self {
+        let plot_points: VecDeque<Option<f64>> = (0..1000).map(|_| None).collect();
+
+        AnalyticsUiState {
+            enabled: false,
+            show_density: false,
+            pending: None,
+            d_histogram: vec![0.0; D_MAX as usize + 1],
+            dt_histogram: vec![0.0; DT_BINS],
+            density: Array2::zeros((0, 0)),
+            plot_points_event_rate_y: PlotY {
+                points: plot_points.clone(),
+            },
+            plot_points_empty_y: PlotY {
+                points: plot_points.clone(),
+            },
+            plot_points_zero_integration_y: PlotY {
+                points: plot_points,
+            },
+        }
+    }
+}
+
+impl AnalyticsUiState {
+    /// Add the statistics of a batch of events to the current frame
+    pub fn add_tally(&mut self, tally: EventTally) {
+        match &mut self.pending {
+            None => self.pending = Some(tally),
+            Some(pending) => pending.merge(&tally),
+        }
+    }
+
+    /// Update the charts with the events of the current frame, which covers `seconds` of the
+    /// source
+    pub fn end_frame(&mut self, seconds: f64) {
+        for count in self
+            .d_histogram
+            .iter_mut()
+            .chain(self.dt_histogram.iter_mut())
+        {
+            *count *= DECAY;
+        }
+        self.density *= DECAY;
+
+        let tally = match self.pending.take() {
+            None => {
+                self.plot_points_event_rate_y.update(Some(0.0));
+                self.plot_points_empty_y.update(None);
+                self.plot_points_zero_integration_y.update(None);
+                return;
+            }
+            Some(tally) => tally,
+        };
+
+        for (count, new) in self.d_histogram.iter_mut().zip(tally.d_counts.iter()) {
+            *count += *new as f64;
+        }
+        for (count, new) in self.dt_histogram.iter_mut().zip(tally.dt_counts.iter()) {
+            *count += *new as f64;
+        }
+        if self.density.dim() != tally.region_counts.dim() {
+            self.density = Array2::zeros(tally.region_counts.dim());
+        }
+        self.density
+            .zip_mut_with(&tally.region_counts, |density, count| {
+                *density += *count as f64
+            });
+
+        self.plot_points_event_rate_y.update(if seconds > 0.0 {
+            Some(tally.events as f64 / seconds)
+        } else {
+            None
+        });
+        let fraction = |count: u64| {
+            if tally.events > 0 {
+                Some(count as f64 / tally.events as f64)
+            } else {
+                None
+            }
+        };
+        self.plot_points_empty_y.update(fraction(tally.empty));
+        self.plot_points_zero_integration_y
+            .update(fraction(tally.zero_integration));
+    }
+
+    /// Draw the analytics panel. Returns true if analytics were turned on or off.
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut toggled = false;
+        egui::CollapsingHeader::new("Analytics").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                toggled = ui
+                    .checkbox(&mut self.enabled, "Collect event statistics")
+                    .changed();
+                ui.add_enabled(
+                    self.enabled,
+                    egui::Checkbox::new(&mut self.show_density, "Event density overlay"),
+                );
+            });
+            if !self.enabled {
+                return;
+            }
+
+            Plot::new("event_rate_plot")
+                .height(100.0)
+                .allow_drag(true)
+                .auto_bounds_y()
+                .legend(Legend::default().position(LeftTop))
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        self.plot_points_event_rate_y
+                            .get_plotline("log10(Events per source sec)", true),
+                    );
+                });
+            Plot::new("special_d_plot")
+                .height(100.0)
+                .allow_drag(true)
+                .include_y(0.0)
+                .include_y(1.0)
+                .legend(Legend::default().position(LeftTop))
+                .show(ui, |plot_ui| {
+                    let fractions = vec![
+                        (&self.plot_points_empty_y, "D_EMPTY fraction"),
+                        (
+                            &self.plot_points_zero_integration_y,
+                            "D_ZERO_INTEGRATION fraction",
+                        ),
+                    ];
+
+                    for (line, label) in fractions {
+                        if line.points.iter().last().unwrap().is_some() {
+                            plot_ui.line(line.get_plotline(label, false));
+                        }
+                    }
+                });
+
+            ui.columns(2, |columns| {
+                Plot::new("d_histogram")
+                    .height(150.0)
+                    .allow_drag(false)
+                    .legend(Legend::default().position(LeftTop))
+                    .show(&mut columns[0], |plot_ui| {
+                        plot_ui.bar_chart(histogram_chart(&self.d_histogram, "D"));
+                    });
+                Plot::new("dt_histogram")
+                    .height(150.0)
+                    .allow_drag(false)
+                    .legend(Legend::default().position(LeftTop))
+                    .show(&mut columns[1], |plot_ui| {
+                        plot_ui.bar_chart(histogram_chart(&self.dt_histogram, "log2(Δt ticks)"));
+                    });
+            });
+        });
+        toggled
+    }
+
+    /// Tint the regions of a displayed frame red, in proportion to their recent event density
+    pub fn draw_density_overlay(&self, image: &mut Image) {
+        if !self.enabled || !self.show_density {
+            return;
+        }
+        let max = self.density.fold(0.0_f64, |max, density| max.max(*density));
+        if max <= 0.0 {
+            return;
+        }
+
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+        let (regions_y, regions_x) = self.density.dim();
+        if (height + DENSITY_REGION_SIZE - 1) / DENSITY_REGION_SIZE != regions_y
+            || (width + DENSITY_REGION_SIZE - 1) / DENSITY_REGION_SIZE != regions_x
+        {
+            // The frame doesn't match the events, e.g., while a new source is starting up
+            return;
+        }
+
+        for (idx, pixel) in image.data.chunks_exact_mut(4).enumerate() {
+            let (y, x) = (idx / width, idx % width);
+            let alpha = OVERLAY_ALPHA
+                * self.density[[y / DENSITY_REGION_SIZE, x / DENSITY_REGION_SIZE]]
+                / max;
+            pixel[0] = (pixel[0] as f64 * (1.0 - alpha) + 255.0 * alpha) as u8;
+            pixel[1] = (pixel[1] as f64 * (1.0 - alpha)) as u8;
+            pixel[2] = (pixel[2] as f64 * (1.0 - alpha)) as u8;
+        }
+    }
+}
+
+fn histogram_chart(counts: &[f64], name: &str) -> BarChart {
+    BarChart::new(
+        counts
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| Bar::new(bin as f64, *count).width(1.0))
+            .collect(),
+    )
+    .name(name)
+}
@@ -7,6 +7,7 @@ use std::collections::VecDeque;
 use std::error::Error;
 use video_rs_adder_dep::Frame;
 
+pub(crate) mod analytics;
 pub(crate) mod slider;
 
 pub(crate) struct PlotY {
@@ -44,6 +45,21 @@ impl PlotY {
     }
 }
 
+/// Map the absolute differences between two frames of the same shape to a heat map, from black
+/// (no difference) through red and yellow to white. Differences are averaged over the channels,
+/// and a difference of 64 or more is white.
+pub(crate) fn difference_heatmap(a: &Frame, b: &Frame) -> Frame {
+    let (height, width, channels) = a.dim();
+    Frame::from_shape_fn((height, width, 3), |(y, x, c)| {
+        let difference = (0..channels)
+            .map(|channel| u32::from(a[[y, x, channel]].abs_diff(b[[y, x, channel]])))
+            .sum::<u32>()
+            / channels as u32;
+        let heat = (difference * 12).min(765);
+        heat.saturating_sub(255 * c as u32).min(255) as u8
+    })
+}
+
 pub fn prep_bevy_image(
     image_mat: Frame,
     color: bool,